@@ -1,3 +1,4 @@
+use std::error::Error;
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 
@@ -18,9 +19,10 @@ pub trait Handle<T> {
     ) -> Result<T>;
 }
 
-impl<T, E> Handle<T> for std::result::Result<T, E> {
+// * The original error is kept as the source of the new one, so that it can be shown in verbose mode
+impl<T, E: Error> Handle<T> for std::result::Result<T, E> {
     fn replace_err<F: FnOnce() -> RushError>(self, new_error: F) -> Result<T> {
-        self.map_err(|_| new_error())
+        self.map_err(|error| new_error().set_source(&error))
     }
 
     fn replace_err_with_msg<F: FnOnce() -> RushError>(
@@ -28,7 +30,7 @@ impl<T, E> Handle<T> for std::result::Result<T, E> {
         new_error: F,
         context: &str,
     ) -> Result<T> {
-        self.map_err(|_| new_error().set_context(context))
+        self.map_err(|error| new_error().set_context(context).set_source(&error))
     }
 }
 
@@ -55,6 +57,45 @@ impl<T> Handle<T> for std::option::Option<T> {
 pub struct RushError {
     kind: ErrorKind,
     custom_message: Option<String>,
+    /// The error which caused this one, such as an I/O error
+    // * This is boxed to keep `Result`s small, since most errors have no source
+    source: Option<Box<ErrorSource>>,
+    /// Whether the error has already been written out, such as to a command's redirected error
+    /// stream, so that it is not reported again
+    reported: bool,
+}
+
+/// An error which caused a `RushError`, along with the error which caused it in turn, if any
+// * The original errors often borrow data or cannot be sent between threads,
+// * so only their messages are kept
+#[derive(Debug)]
+pub struct ErrorSource {
+    message: String,
+    source: Option<Box<ErrorSource>>,
+}
+
+impl ErrorSource {
+    /// Copies an error and the chain of errors which caused it
+    fn new(error: &dyn Error) -> Self {
+        Self {
+            message: error.to_string(),
+            source: error.source().map(|source| Box::new(Self::new(source))),
+        }
+    }
+}
+
+impl Display for ErrorSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ErrorSource {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
 }
 
 impl Display for RushError {
@@ -68,15 +109,62 @@ impl Display for RushError {
     }
 }
 
+impl Error for RushError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn Error + 'static))
+    }
+}
+
 impl RushError {
     /// Creates a `RushError` with no custom message.
     pub fn new(kind: ErrorKind) -> Self {
         Self {
             kind,
             custom_message: None,
+            source: None,
+            reported: false,
         }
     }
 
+    /// Takes a `RushError` and records the error which caused it, along with that error's sources.
+    pub fn set_source(mut self, source: &dyn Error) -> Self {
+        self.source = Some(Box::new(ErrorSource::new(source)));
+        self
+    }
+
+    /// Gets each error in the chain of errors which caused this one, starting with the closest.
+    pub fn sources(&self) -> impl Iterator<Item = &(dyn Error + 'static)> {
+        std::iter::successors(self.source(), |&error| error.source())
+    }
+
+    /// Records that the error has been written out, so that it is not reported again.
+    pub fn mark_reported(&mut self) {
+        self.reported = true;
+    }
+
+    /// Whether the error has already been written out.
+    pub fn is_reported(&self) -> bool {
+        self.reported
+    }
+
+    /// Gets the name of the error kind, such as 'UnknownCommand'.
+    pub fn name(&self) -> String {
+        self.kind.name()
+    }
+
+    /// Gets the extended description of the error kind.
+    pub fn explanation(&self) -> &'static str {
+        self.kind.description()
+    }
+
+    /// Gets the exit status which the shell reports for this error, which is used for '$?'
+    /// and as the exit status of the shell itself when it runs a script.
+    pub fn exit_code(&self) -> i32 {
+        self.kind.exit_code()
+    }
+
     /// Takes a `RushError` and gives it a custom message.
     pub fn set_context(mut self, context: &str) -> Self {
         self.custom_message = Some(context.to_owned());
@@ -84,6 +172,154 @@ impl RushError {
     }
 }
 
+impl ErrorKind {
+    /// Gets the name of the variant which describes the error, such as 'UnknownCommand'
+    pub fn name(&self) -> String {
+        let debug = match self {
+            Self::Dispatch(error) => format!("{:?}", error),
+            Self::Builtin(error) => format!("{:?}", error),
+            Self::Executable(error) => format!("{:?}", error),
+            Self::State(error) => format!("{:?}", error),
+            Self::Path(error) => format!("{:?}", error),
+        };
+
+        debug
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect()
+    }
+
+    /// Gets the extended description of the error kind, which 'explain-error' shows
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Dispatch(error) => error.description(),
+            Self::Builtin(error) => error.description(),
+            Self::Executable(error) => error.description(),
+            Self::State(error) => error.description(),
+            Self::Path(error) => error.description(),
+        }
+    }
+
+    /// Creates one error of every kind, with placeholder data, so that a kind can be found by name
+    // * A new variant has to be added here as well, so that it can be explained
+    fn variants() -> Vec<Self> {
+        use BuiltinError::*;
+        use DispatchError::*;
+        use ExecutableError::*;
+        use FileError::*;
+        use StateError::*;
+
+        let text = String::new;
+        let path = PathBuf::new;
+        let dispatch = [
+            UnknownCommand(text(), None),
+            CommandNotExecutable(0),
+            UnreadableExecutableMetadata(path()),
+            EmptyPipelineStage,
+            InvalidFileDescriptor(text()),
+            UnsupportedRedirection(text()),
+            MissingRedirectionTarget(text()),
+            MissingListCommand(text()),
+            UnexpectedToken(text()),
+            UnclosedSubshell,
+            UnclosedQuote('"'),
+            MissingKeyword(text()),
+            InvalidExpansion(text()),
+            NoGlobMatches(text()),
+        ];
+        let builtin = [
+            CouldNotParseArgs,
+            TerminalOperationFailed,
+            CouldNotWriteOutput,
+            InvalidSignal(text()),
+            ScriptFailed(text()),
+            UnknownError(text()),
+        ];
+        let executable = [
+            PathNoLongerExists(path()),
+            FailedToExecute(0),
+            CouldNotWait,
+            CouldNotConnectStreams,
+            CouldNotStartSubshell,
+        ];
+        let state = [
+            MissingEnv(EnvVariable::HOME),
+            CouldNotUpdateEnv(EnvVariable::HOME),
+            NoPreviousDirectory,
+            NoNextDirectory,
+            InvalidPathIndex(0),
+            UnsupportedTerminal,
+            UnknownJob(text()),
+            NoCurrentJob,
+            CouldNotSignalJob(text()),
+            CouldNotControlTerminal,
+            InvalidVariableName(text()),
+            ProtectedVariable(text()),
+            InvalidAliasName(text()),
+            UnknownAlias(text()),
+            UnknownSetting(text()),
+        ];
+        let file = [
+            FailedToConvertStringToPath(text()),
+            FailedToConvertPathToString(path()),
+            CouldNotCanonicalize(path()),
+            CouldNotGetParent(path()),
+            CouldNotOpenFile(path()),
+            CouldNotReadFile(path()),
+            InvalidConfigFile(path(), 0, 0, text()),
+            CouldNotCreateFile(path()),
+            CouldNotWriteFile(path()),
+            CouldNotDeleteFile(path()),
+            CouldNotCreateDirectory(path()),
+            UnreadableFileType(path()),
+            UnreadableFileName(path()),
+            UnreadablePermissions(path()),
+            UnreadableMetadata(path()),
+            UnreadableDirectory(path()),
+            UnknownPath(path()),
+            WouldClobberFile(path()),
+        ];
+
+        (dispatch.into_iter().map(Self::Dispatch))
+            .chain(builtin.into_iter().map(Self::Builtin))
+            .chain(executable.into_iter().map(Self::Executable))
+            .chain(state.into_iter().map(Self::State))
+            .chain(file.into_iter().map(Self::Path))
+            .collect()
+    }
+
+    /// Gets the exit status for this kind of error, using the statuses POSIX gives them:
+    /// 127 for a command which could not be found, 126 for one which could not be executed,
+    /// 2 for a line or builtin arguments which could not be parsed, the exit code of an executable
+    /// which failed (128 plus the signal number if it was killed by a signal), and 1 otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Dispatch(error) => error.exit_code(),
+            Self::Builtin(error) => error.exit_code(),
+            Self::Executable(error) => error.exit_code(),
+            Self::State(_) | Self::Path(_) => 1,
+        }
+    }
+}
+
+/// Finds the extended description of an error kind by the name of its variant, returning the
+/// variant's name along with the description
+/// The name is matched ignoring case, '-', and '_', so 'unknown-command' finds 'UnknownCommand'
+pub fn explain(name: &str) -> Option<(String, &'static str)> {
+    let normalize = |name: &str| {
+        name.chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let name = normalize(name);
+
+    ErrorKind::variants()
+        .into_iter()
+        .find(|kind| normalize(&kind.name()) == name)
+        .map(|kind| (kind.name(), kind.description()))
+}
+
 /// Enum representing every type of error which can occur in Rush.
 /// Downstream error variants will typically include data providing basic information
 /// about how the error occurred, such as the name of a command which was not found.
@@ -99,480 +335,1097 @@ pub enum ErrorKind {
 /// Error type for errors which occur during command dispatch.
 #[derive(Debug)]
 pub enum DispatchError {
-    /// OVERVIEW
-    /// This error occurs when a command cannot be resolved by the dispatcher.
-    ///
-    /// COMMON CAUSES
-    /// - The command name was misspelled.
-    /// - The command name was incorrect (e.g. 'vscode' instead of 'code')
-    /// - The associated program is not installed.
-    /// - The associated program is not in the PATH.
-    /// - The associated program is only executable by superuser.
-    ///
-    /// RARE CAUSES
-    /// - The associated program is installed but only available to a specific user.
-    /// - The shell did not properly load the PATH environment variable.
-    ///
-    /// SOLUTIONS
-    /// - Check the spelling of the command name.
-    /// - Check the command name against the documentation of the associated program.
-    /// - Ensure that the associated program is installed.
-    /// - Ensure that the associated program is in the PATH.
-    /// - Run the command using sudo.
-    ///
-    /// TECHNICAL DETAILS
-    /// When attempting to resolve a command, the dispatcher will first look in its builtin command
-    /// table, then scan all directories in the PATH environment variable for a file matching the
-    /// provided command name. If no file is found, this error is returned.
-    UnknownCommand(String),
-
-    /// OVERVIEW
-    /// This error occurs when the dispatcher locates a file matching the provided command name,
-    /// but the file is not executable.
-    ///
-    /// CAUSE
-    /// - The file permissions of the file do not allow it to be executed.
-    ///
-    /// SOLUTION
-    /// - Add executable permissions to the file using a command such as 'chmod'.
-    ///
-    /// TECHNICAL DETAILS
-    /// When attempting to resolve a command, the dispatcher will scan all directories in the PATH
-    /// environment variable for a file matching the provided command name. If a file is found, the
-    /// dispatcher then checks whether the file's permissions indicate that it is executable. If it
-    /// is not, this error is returned.
+    UnknownCommand(String, Option<String>),
     CommandNotExecutable(u32),
-
-    /// OVERVIEW
-    /// This error occurs when the dispatcher locates a file matching the provided command name,
-    /// but the file's permission metadata cannot be read.
-    ///
-    /// CAUSE
-    /// - The user does not have permission to read the file's metadata.
-    ///
-    /// SOLUTION
-    /// - Ensure that the user has permission to read the file's metadata.
-    ///
-    /// TECHNICAL DETAILS
-    /// When attempting to resolve a command, the dispatcher will scan all directories in the PATH
-    /// environment variable for a file matching the provided command name. If a file is found, the
-    /// dispatcher then checks whether the file's permissions indicate that it is executable. If it
-    /// cannot, this error is returned.
     UnreadableExecutableMetadata(PathBuf),
+    EmptyPipelineStage,
+    InvalidFileDescriptor(String),
+    UnsupportedRedirection(String),
+    MissingRedirectionTarget(String),
+    MissingListCommand(String),
+    UnexpectedToken(String),
+    UnclosedSubshell,
+    UnclosedQuote(char),
+    MissingKeyword(String),
+    InvalidExpansion(String),
+    NoGlobMatches(String),
 }
 
 /// Error type for errors that occur during the execution of builtin commands.
 #[derive(Debug)]
 pub enum BuiltinError {
-    /// OVERVIEW
-    /// This error occurs when a builtin command is provided with invalid argument(s).
-    ///
-    /// COMMON CAUSES
-    /// - The builtin received a different number of arguments than it expected.
-    /// - An argument was misspelled or malformed.
-    /// - An argument which should have been escaped or enclosed in quotes, but was not.
-    ///
-    /// RARE CAUSES
-    /// - A bug in the parsing logic prevented a valid argument from being parsed correctly.
-    ///
-    /// SOLUTION
-    /// - Check the builtin's documentation and adjust arguments accordingly.
-    /// - File an issue on the Rush repository if an internal bug is suspected.
-    ///
-    /// TECHNICAL DETAILS
-    /// When executing a builtin command, it will parse the provided arguments into values it can
-    /// use to perform an operation. If there is some error in parsing these arguments, it is unable
-    /// to run without proper input, so this error is returned.
     CouldNotParseArgs,
-
-    /// OVERVIEW
-    /// This error occurs when a builtin is unable to interact with the terminal.
-    ///
-    /// COMMON CAUSES
-    /// - The operation being performed is not supported by the terminal.
-    /// - The terminal is not supported by Rush.
-    ///
-    /// RARE CAUSES
-    /// - The stdout or stderr streams between the shell and the terminal have been corrupted.
-    ///
-    /// SOLUTIONS
-    /// - Run the command in a different terminal.
-    /// - Re-launch the terminal and run the command again.
-    ///
-    /// TECHNICAL DETAILS
-    /// When executing a builtin command, it may perform operations on the terminal such as clearing
-    /// the screen, moving the cursor around, or querying the terminal size. If for whatever reason
-    /// it is unable to do so, this error is returned.
     TerminalOperationFailed,
+    CouldNotWriteOutput,
+    InvalidSignal(String),
+    ScriptFailed(String),
+    UnknownError(String),
 }
 
 /// Error type for errors which occur during execution of executable files.
 #[derive(Debug)]
 pub enum ExecutableError {
-    /// OVERVIEW
-    /// This error occurs when an executable file is no longer accessible.
-    ///
-    /// CAUSE
-    /// - The executable file was deleted or moved after being located by the dispatcher, but before
-    /// being executed.
-    ///
-    /// SOLUTION
-    /// - Ensure that the executable file is in a location that will not be modified without
-    /// explicit user action.
-    ///
-    /// TECHNICAL DETAILS
-    /// When dispatching an executable, the dispatcher will first locate the executable file in one
-    /// of the directories in the PATH environment variable. If the file is found, it will then be
-    /// set up with the appropriate environment variables and executed. If the file has been deleted
-    /// or moved after being located but before being executed, this error is returned.
     PathNoLongerExists(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when an executable returns a non-zero exit code.
-    ///
-    /// COMMON CAUSES
-    /// - The arguments provided to the executable were invalid.
-    /// - The executable was unable to locate a file it needed.
-    /// - The executable was unable to complete its task for some other reason.
-    ///
-    /// RARE CAUSES
-    /// - The executable has a bug which causes it to return the wrong exit code.
-    /// - The executable uses non-conventional exit codes.
-    /// - The executable was located but could not be executed (code 126).
-    ///
-    /// SOLUTIONS
-    /// - Check the executable's documentation and adjust arguments accordingly.
-    /// - Ensure that all needed files are accessible to the executable.
-    /// - Reinstall the program associated with the executable.
-    ///
-    /// TECHNICAL DETAILS
-    /// It is conventional for executables to return a zero exit code when they complete
-    /// successfully, and a non-zero exit code when they fail. After running an executable, the
-    /// disptacher will check its exit code, and if it is non-zero, this error is returned.
     FailedToExecute(isize),
-
-    /// This error is exceedingly rare and its cause is unknown. It is not expected to occur.
     CouldNotWait,
+    CouldNotConnectStreams,
+    CouldNotStartSubshell,
 }
 
 /// Error type for errors which occur during state operations.
 #[derive(Debug)]
 pub enum StateError {
-    /// OVERVIEW
-    /// This error occurs when an environment variable is missing from the parent process.
-    ///
-    /// COMMON CAUSES
-    /// - The shell was launched from a non-standard environment.
-    ///
-    /// RARE CAUSES
-    /// - The environment variable exists but is not accessible to the shell.
-    ///
-    /// SOLUTIONS
-    /// - Ensure that the shell is launched from a standard environment.
-    ///
-    /// TECHNICAL DETAILS
-    /// When the shell is launched, it copies the environment variables such as HOME and PATH from
-    /// the parent process for its own use. If an environment variable is missing from the parent
-    /// process, this error is returned.
     MissingEnv(EnvVariable),
-
-    /// OVERVIEW
-    /// This error occurs when an environment variable cannot be updated.
-    ///
-    /// COMMON CAUSES
-    /// - The value provided to the environment variable was malformed due to an internal bug.
-    /// - The value provided to the environment variable was malformed due to invalid input, such as
-    /// a non-existent directory being provided to CWD.
-    ///
-    /// RARE CAUSES
-    /// - The environment variable is not accessible to the shell.
-    /// - The environment variable is not writable.
-    ///
-    /// SOLUTIONS
-    /// - Ensure that no invalid values are provided to environment variables.
-    /// - File an issue on the Rush repository if an internal bug is suspected.
-    ///
-    /// TECHNICAL DETAILS
-    /// During execution, the shell keeps track of its environment through two mechanisms. Firstly,
-    /// it has an internal representation of the environment variables, which are strongly-typed and
-    /// validated. Secondly, it updates its process environment, which is a construct of the OS, to
-    /// match its internal representation. This must be done in order for certain syscall-dependent
-    /// function calls to be correctly performed. If the process environment cannot be updated, this
-    /// error is returned.
     CouldNotUpdateEnv(EnvVariable),
-
-    /// OVERVIEW
-    /// This error occurs when the user erroneously invokes the 'previous-directory' builtin.
-    ///
-    /// CAUSE
-    /// - The user ran the 'previous-directory' builtin, but the directory history is empty.
-    ///
-    /// SOLUTION
-    /// - Navigate to another directory before returning to the previous one.
-    ///
-    /// TECHNICAL DETAILS
-    /// The 'previous-directory' builtin allows the user to navigate to the directory they were in
-    /// before navigating to the current one. This is implemented using a stack of directories, very
-    /// similarly to how browser tabs handle navigation. If the user attempts to navigate to the
-    /// previous directory when the stack is empty, this error is returned.
     NoPreviousDirectory,
-
-    /// OVERVIEW
-    /// This error occurs when the user erroneously invokes the 'next-directory' builtin.
-    ///
-    /// CAUSE
-    /// - The user ran the 'next-directory' builtin, but the directory history is empty.
-    ///
-    /// SOLUTION
-    /// - Navigate to another directory before returning to the next one.
-    ///
-    /// TECHNICAL DETAILS
-    /// The 'next-directory' builtin allows the user to navigate to the directory they were in after
-    /// navigating to the current one. This is implemented using a stack of directories, very
-    /// similarly to how browser tabs handle navigation. If the user attempts to navigate to the
-    /// next directory when the stack is empty, this error is returned.
     NoNextDirectory,
-
-    /// OVERVIEW
-    /// This error occurs when the shell tries to update the PATH variable using an invalid index.
-    ///
-    /// CAUSE
-    /// - A non-existent index was provided to the 'edit-path' builtin using the 'insert' or
-    /// 'delete' subcommand.
-    ///
-    /// SOLUTION
-    /// - Check the PATH variable using the 'environment' builtin and ensure that the index provided
-    /// is valid.
-    ///
-    /// TECHNICAL DETAILS
-    /// The 'edit-path' builtin allows the user to modify the PATH variable. The PATH variable is
-    /// represented using a vector (list) of paths, which is indexed when the user provides an index
-    /// to insert or delete a path. If the user provides an index which does not exist, this error
-    /// is returned.
     InvalidPathIndex(usize),
-
-    /// OVERVIEW
-    /// This error occurs when the line editor is unable to interact with the terminal.
-    ///
-    /// CAUSE
-    /// - The terminal is not supported by Rustyline (the line editor library).
-    ///
-    /// SOLUTION
-    /// - Run the shell in a different terminal.
-    ///
-    /// TECHNICAL DETAILS
-    /// The line editor is responsible for handling user input and displaying the prompt. It is
-    /// implemented using the Rustyline library, which should support most terminals. If the
-    /// terminal being used does not support the requisite features, this error is returned.
     UnsupportedTerminal,
+    UnknownJob(String),
+    NoCurrentJob,
+    CouldNotSignalJob(String),
+    CouldNotControlTerminal,
+    InvalidVariableName(String),
+    ProtectedVariable(String),
+    InvalidAliasName(String),
+    UnknownAlias(String),
+    UnknownSetting(String),
 }
 
 /// Error type for errors which occur during path operations.
 #[derive(Clone, Debug)]
 pub enum FileError {
-    /// OVERVIEW
-    /// This error occurs when the shell is unable to convert a string to a path.
-    ///
-    /// CAUSE
-    /// - One or more of the paths in the PATH environment variable are invalid.
-    ///
-    /// SOLUTION
-    /// - Make sure all paths in the PATH variable exist and are accessible.
-    ///
-    /// TECHNICAL DETAILS
-    /// When the shell is launched, it copies the PATH environment variable from the parent process
-    /// and converts it to an internal representation for its own use. Because of how environment
-    /// variables are largely just unvalidated strings, it is possible for the PATH to contain
-    /// invalid paths. If any of the paths scannot be converted, this error is returned.
     FailedToConvertStringToPath(String),
-
-    /// OVERVIEW
-    /// - This error occurs when the shell is unable to convert a path to a string.
-    ///
-    /// CAUSE
-    /// - The shell is attempting to display or operate on a path which is not valid UTF-8.
-    ///
-    /// SOLUTION
-    /// - File an issue on the Rush repository.
-    ///
-    /// TECHNICAL DETAILS
-    /// When the shell is attempting to display a path, or performing operations to manipulate the
-    /// path which require it to be a string, it may be unable to convert the path to a string, at
-    /// which point it returns this error. This is largely because, even though a path may be valid
-    /// on the filesystem, the OS and filesystem may not use UTF-8 encoding.
     FailedToConvertPathToString(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when the shell is unable to canonicalize a path.
-    ///
-    /// CAUSES
-    /// - The path does not exist or is inaccessible.
-    /// - The path is misspelled or otherwise malformed.
-    ///
-    /// SOLUTION
-    /// - Ensure that the path exists, is accessible by the user, and is formatted correctly.
-    ///
-    /// TECHNICAL DETAILS
-    /// When the shell receives a path as input, it will attempt to canonicalize it, which is to say
-    /// it will attempt to resolve any relative paths, symbolic links, and shorthands such as '~'.
-    /// If the absolute path cannot be determined, this error is returned.
     CouldNotCanonicalize(PathBuf),
-
-    /// This error is exceedingly rare and its cause is unknown. It is not expected to occur.
     CouldNotGetParent(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when a file cannot be opened.
-    ///
-    /// COMMON CAUSES
-    /// - The file does not exist or is inaccessible.
-    /// - The file is a directory.
-    ///
-    /// RARE CAUSES
-    /// - The file is a symbolic link.
-    /// - The file is a special file, such as a device file.
-    ///
-    /// SOLUTIONS
-    /// - Ensure that the file exists, is accessible by the user, and is not a directory any other
-    /// special file type.
     CouldNotOpenFile(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when a file's contents are invalid or inaccessible.
-    ///
-    /// COMMON CAUSES
-    /// - The file being read is the config file, and its contents are malformed.
-    ///
-    /// RARE CAUSES
-    /// - The file was modified by another program while the shell was reading it.
-    /// - The file has been corrupted.
-    ///
-    /// SOLUTIONS
-    /// - If the file in question is a config file, make sure it is formatted correctly.
-    /// - Ensure that the file is not open in or being modified by another program. This is usually
-    /// guaranteed by the OS/filesystem.
-    ///
-    /// TECHNICAL DETAILS
-    /// When the shell is reading a configuration file, it will attempt to parse its contents based
-    /// on the expected format for the config. If the contents are invalid, this error is returned.
-    /// This error may also be returned upon reading any file if the file is somehow externally
-    /// modified in such a way that the reading process is interrupted.
     CouldNotReadFile(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when a file cannot be created.
-    ///
-    /// COMMON CAUSES
-    /// - The file already exists.
-    /// - The file's enclosing directory does not exist or is invalid.
-    /// - The file's enclosing directory is not writable.
-    ///
-    /// RARE CAUSES
-    /// - The disk is full.
-    /// - The filesystem is read-only.
-    ///
-    /// SOLUTIONS
-    /// - Check that the file does not already exist.
-    /// - Ensure that the file's enclosing directory exists and is writable.
+    InvalidConfigFile(PathBuf, usize, usize, String),
     CouldNotCreateFile(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when a file cannot be deleted.
-    ///
-    /// COMMON CAUSES
-    /// - The file does not exist.
-    /// - The file is not writable.
-    /// - The file is open in another program.
-    ///
-    /// RARE CAUSES
-    /// - The file is a special file, such as a device file.
-    /// - The filesystem is read-only.
-    ///
-    /// SOLUTIONS
-    /// - Check that the file exists and is not a special file.
-    /// - Check that the file is not open in another program.
-    /// - Ensure that the file is writable.
+    CouldNotWriteFile(PathBuf),
     CouldNotDeleteFile(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when a directory cannot be created.
-    ///
-    /// COMMON CAUSES
-    /// - The directory already exists.
-    /// - The directory's parent directory does not exist or is invalid.
-    /// - The directory's parent directory is not writable.
-    ///
-    /// RARE CAUSES
-    /// - The disk is full.
-    /// - The filesystem is read-only.
-    ///
-    /// SOLUTIONS
-    /// - Check that the directory does not already exist.
-    /// - Ensure that the directory's parent directory exists and is writable.
     CouldNotCreateDirectory(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when the shell is unable to determine the type of a file.
-    ///
-    /// CAUSE
-    /// - The file does not exist or is inaccessible.
-    ///
-    /// SOLUTION
-    /// - Ensure that the file exists and is accessible by the user.
-    ///
-    /// TECHNICAL DETAILS
-    /// The "type" of a file refers to its filesystem classification. For example, a file may be a
-    /// regular file, a directory, a symbolic link, or a device file. When the shell needs to
-    /// determine the type of a file, it will attempt to read its metadata. If the relevant metadata
-    /// cannot be read, this error is returned.
     UnreadableFileType(PathBuf),
-
-    /// This error is exceedingly rare and its cause is unknown. It is not expected to occur.
     UnreadableFileName(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when the shell cannot read the permissions of a file or directory.
-    ///
-    /// CAUSE
-    /// - Inadequate user permissions.
-    /// - File or directory doesn't exist.
-    ///
-    /// SOLUTION
-    /// - Enure the user has adequate permissions to read the file.
-    /// - Enure the directory or file exists.
     UnreadablePermissions(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when a files metadata cannot be read.
-    ///
-    /// CAUSE
-    /// - Inadequate user permissions.
-    /// - File doesn't exist
-    ///
-    /// SOLUTION
-    /// - Enure the user has adequate permissions to read the file.
-    /// - Enure the file exists.
     UnreadableMetadata(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when the shell is unable to read the contents of a directory.
-    ///
-    /// CAUSE
-    /// - The directory does not exist or is inaccessible.
-    ///
-    /// SOLUTION
-    /// - Ensure that the directory exists and is accessible by the user.
     UnreadableDirectory(PathBuf),
-
-    /// OVERVIEW
-    /// This error occurs when the shell is unable to locate a file or directory.
-    ///
-    /// CAUSE
-    /// - The file or directory does not exist or is inaccessible.
-    ///
-    /// SOLUTION
-    /// - Ensure that the file or directory exists and is accessible by the user.
     UnknownPath(PathBuf),
+    WouldClobberFile(PathBuf),
+}
+
+impl DispatchError {
+    /// Gets the exit status for this error
+    // * Every variant is listed, so that the status of a new one has to be chosen deliberately
+    fn exit_code(&self) -> i32 {
+        use DispatchError::*;
+        match self {
+            UnknownCommand(..) => 127,
+            CommandNotExecutable(_) | UnreadableExecutableMetadata(_) => 126,
+            EmptyPipelineStage
+            | InvalidFileDescriptor(_)
+            | UnsupportedRedirection(_)
+            | MissingRedirectionTarget(_)
+            | MissingListCommand(_)
+            | UnexpectedToken(_)
+            | UnclosedSubshell
+            | UnclosedQuote(_)
+            | MissingKeyword(_) => 2,
+            InvalidExpansion(_) | NoGlobMatches(_) => 1,
+        }
+    }
+
+    /// Gets the extended description of this error, which 'explain-error' shows
+    pub fn description(&self) -> &'static str {
+        use DispatchError::*;
+        match self {
+            UnknownCommand(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a command cannot be resolved by the dispatcher.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The command name was misspelled.\n",
+                "- The command name was incorrect (e.g. 'vscode' instead of 'code')\n",
+                "- The associated program is not installed.\n",
+                "- The associated program is not in the PATH.\n",
+                "- The associated program is only executable by superuser.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The associated program is installed but only available to a specific user.\n",
+                "- The shell did not properly load the PATH environment variable.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Check the spelling of the command name.\n",
+                "- Check the command name against the documentation of the associated program.\n",
+                "- Ensure that the associated program is installed.\n",
+                "- Ensure that the associated program is in the PATH.\n",
+                "- Run the command using sudo.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When attempting to resolve a command, the dispatcher will first look in its\n",
+                "builtin command table, then scan all directories in the PATH environment\n",
+                "variable for a file matching the provided command name. If no file is found,\n",
+                "this error is returned. The error includes the closest match among the\n",
+                "builtins, aliases, functions, and executables in the PATH, if any of them are\n",
+                "close enough to be a likely typo.",
+            ),
+            CommandNotExecutable(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the dispatcher locates a file matching the provided\n",
+                "command name, but the file is not executable.\n",
+                "\n",
+                "CAUSE\n",
+                "- The file permissions of the file do not allow it to be executed.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Add executable permissions to the file using a command such as 'chmod'.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When attempting to resolve a command, the dispatcher will scan all directories\n",
+                "in the PATH environment variable for a file matching the provided command name.\n",
+                "If a file is found, the dispatcher then checks whether the file's permissions\n",
+                "indicate that it is executable. If it is not, this error is returned.",
+            ),
+            UnreadableExecutableMetadata(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the dispatcher locates a file matching the provided\n",
+                "command name, but the file's permission metadata cannot be read.\n",
+                "\n",
+                "CAUSE\n",
+                "- The user does not have permission to read the file's metadata.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Ensure that the user has permission to read the file's metadata.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When attempting to resolve a command, the dispatcher will scan all directories\n",
+                "in the PATH environment variable for a file matching the provided command name.\n",
+                "If a file is found, the dispatcher then checks whether the file's permissions\n",
+                "indicate that it is executable. If it cannot, this error is returned.",
+            ),
+            EmptyPipelineStage => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a pipeline contains a stage with no command in it.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A pipe ('|') was placed at the very beginning or end of the line.\n",
+                "- Two pipes were placed next to each other with nothing between them.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Make sure that there is a command on both sides of every pipe.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When the dispatcher receives a line containing pipes, it splits the line into\n",
+                "stages, each of which must contain a command whose output is passed on to the\n",
+                "next stage. If any stage is empty, there is nothing to run, so this error is\n",
+                "returned.",
+            ),
+            InvalidFileDescriptor(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a redirection refers to a file descriptor which the\n",
+                "shell does not support.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A redirection such as '3>file' or '2>&5' was used. Only standard input (0),\n",
+                "  standard output (1), and standard error (2) can be redirected.\n",
+                "- Standard input was duplicated onto an output stream, or vice versa.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Only redirect file descriptors 0, 1, and 2, and only duplicate streams of the\n",
+                "  same kind.",
+            ),
+            UnsupportedRedirection(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a redirection operator is recognized but not yet\n",
+                "supported.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A here-document ('<<') was used.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Write the input to a file and redirect it with '<' instead.",
+            ),
+            MissingRedirectionTarget(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a redirection operator is not followed by a file name or\n",
+                "file descriptor.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A redirection operator was placed at the very end of the line, as in 'ls >'.\n",
+                "- A redirection operator was followed directly by a pipe or another operator.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Place the name of a file after every redirection operator.",
+            ),
+            MissingListCommand(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a list operator ('&&', '||', or ';') does not have a\n",
+                "command on both sides of it.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A line started with a list operator, as in '&& ls'.\n",
+                "- A line ended with '&&' or '||', as in 'make &&'.\n",
+                "- Two list operators were placed next to each other, as in 'make && || ls'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Make sure that there is a command on both sides of every '&&' and '||', and\n",
+                "  before every ';'.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "A line can hold a list of commands separated by operators. '&&' runs the next\n",
+                "command only if the previous one succeeded, '||' runs it only if the previous\n",
+                "one failed, and ';' always runs it. A trailing ';' is allowed, but every other\n",
+                "operator must join two commands.",
+            ),
+            UnexpectedToken(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the parser finds an operator or word in a place where it\n",
+                "cannot be used.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A ')' was used without a matching '('.\n",
+                "- A subshell was empty, as in '()'.\n",
+                "- A word was placed directly after a subshell, as in '(ls) -l'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Check the line for misplaced operators, and quote any text which is meant to\n",
+                "  be used literally.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Before anything is run, the line is parsed into a tree of lists, pipelines, and\n",
+                "commands. If a token does not fit anywhere in that tree, the whole line is\n",
+                "rejected with this error.",
+            ),
+            UnclosedSubshell => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a subshell is opened with '(' but never closed.\n",
+                "\n",
+                "CAUSE\n",
+                "- The closing ')' was left out, as in '(cd src; ls'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Add a ')' after the last command in the subshell.",
+            ),
+            UnclosedQuote(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a line ends inside a single or double quote.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The closing quote was left out, as in 'echo \"hello'.\n",
+                "- An apostrophe was used inside single quotes, as in 'echo 'it's''.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Add the closing quote, or escape the quote character with a backslash.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "At the prompt, an unclosed quote prompts for more lines instead of failing, so\n",
+                "this error is only seen when the input ends before the quote does.",
+            ),
+            MissingKeyword(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a block such as 'if', 'while', 'for', or '{' is never\n",
+                "closed.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The closing reserved word was left out, as in 'if true; then echo yes'.\n",
+                "- A reserved word such as 'then' or 'do' was left out, as in 'while true; echo\n",
+                "  yes; done'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Add the missing reserved word, such as 'fi', 'done', or '}'.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "At the prompt, an unclosed block prompts for more lines instead of failing, so\n",
+                "this error is only seen when the input ends before the block does.",
+            ),
+            InvalidExpansion(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a variable expansion starting with '${' is not written\n",
+                "correctly.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The closing '}' was left out, as in '${HOME'.\n",
+                "- The name of the variable was left out or is invalid, as in '${}' or '${1A}'.\n",
+                "- An unsupported operator was used, as in '${NAME:=value}'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use one of the supported forms: '$NAME', '${NAME}', or '${NAME:-default}'.\n",
+                "- Quote the '$' with single quotes or a backslash if it is meant to be used\n",
+                "  literally.",
+            ),
+            NoGlobMatches(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a glob pattern does not match any files, and the shell\n",
+                "is configured to treat that as an error.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The pattern contains a typo, or is being matched in the wrong directory.\n",
+                "- A '*', '?', or '[' was meant to be used literally, as in 'echo Done?'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Check the pattern and the CWD.\n",
+                "- Quote the argument if it is meant to be used literally.\n",
+                "- Run 'configure --no-match-error false' to pass unmatched patterns through\n",
+                "  unchanged.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Unquoted arguments containing '*', '?', or '[' are matched against the\n",
+                "filesystem relative to the CWD. A '**' component matches any number of\n",
+                "directories.",
+            ),
+        }
+    }
+}
+
+impl BuiltinError {
+    /// Gets the exit status for this error
+    fn exit_code(&self) -> i32 {
+        use BuiltinError::*;
+        match self {
+            CouldNotParseArgs => 2,
+            TerminalOperationFailed
+            | CouldNotWriteOutput
+            | InvalidSignal(_)
+            | ScriptFailed(_)
+            | UnknownError(_) => 1,
+        }
+    }
+
+    /// Gets the extended description of this error, which 'explain-error' shows
+    pub fn description(&self) -> &'static str {
+        use BuiltinError::*;
+        match self {
+            CouldNotParseArgs => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a builtin command is provided with invalid argument(s).\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The builtin received a different number of arguments than it expected.\n",
+                "- An argument was misspelled or malformed.\n",
+                "- An argument which should have been escaped or enclosed in quotes, but was\n",
+                "  not.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- A bug in the parsing logic prevented a valid argument from being parsed\n",
+                "  correctly.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Check the builtin's documentation and adjust arguments accordingly.\n",
+                "- File an issue on the Rush repository if an internal bug is suspected.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When executing a builtin command, it will parse the provided arguments into\n",
+                "values it can use to perform an operation. If there is some error in parsing\n",
+                "these arguments, it is unable to run without proper input, so this error is\n",
+                "returned.",
+            ),
+            TerminalOperationFailed => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a builtin is unable to interact with the terminal.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The operation being performed is not supported by the terminal.\n",
+                "- The terminal is not supported by Rush.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The stdout or stderr streams between the shell and the terminal have been\n",
+                "  corrupted.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Run the command in a different terminal.\n",
+                "- Re-launch the terminal and run the command again.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When executing a builtin command, it may perform operations on the terminal\n",
+                "such as clearing the screen, moving the cursor around, or querying the terminal\n",
+                "size. If for whatever reason it is unable to do so, this error is returned.",
+            ),
+            CouldNotWriteOutput => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a builtin is unable to write its output.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The output of the builtin was piped into a command which exited before\n",
+                "  reading all of it.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The stdout stream between the shell and the terminal has been closed.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Make sure that any command receiving the output of the builtin reads all of\n",
+                "  its input.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Builtins write their output to a stream which is either the terminal or the\n",
+                "input of another command in a pipeline. If the other end of the stream has been\n",
+                "closed, this error is returned.",
+            ),
+            InvalidSignal(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a signal name or number is not recognized.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The signal name was misspelled.\n",
+                "- The signal number does not exist on the current platform.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use a signal name such as 'TERM', 'SIGKILL', or 'INT', or a valid signal\n",
+                "  number.",
+            ),
+            ScriptFailed(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a Rhai script fails to compile or run.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The script contains a syntax error.\n",
+                "- The script threw an error, or called a function which does not exist.\n",
+                "- A shell function called by the script failed, such as 'set_cwd' with a\n",
+                "  missing directory.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Check the script at the line and position given in the error message.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Scripts are run by an embedded Rhai engine. Commands called from a script\n",
+                "report their failures through the 'status' they return, so only errors in the\n",
+                "script itself end it early.",
+            ),
+            UnknownError(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when 'explain-error' is given a name which is not the name of\n",
+                "an error.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use the name shown by verbose mode, such as 'UnknownCommand' or\n",
+                "  'unknown-command'.\n",
+                "- Run 'explain-error' without a name to explain the most recent error.",
+            ),
+        }
+    }
+}
+
+impl ExecutableError {
+    /// Gets the exit status for this error
+    fn exit_code(&self) -> i32 {
+        use ExecutableError::*;
+        match self {
+            // * Exit codes are truncated to a byte by the OS, so this is always in range
+            FailedToExecute(code) => *code as i32,
+            PathNoLongerExists(_) => 127,
+            CouldNotWait | CouldNotConnectStreams | CouldNotStartSubshell => 1,
+        }
+    }
+
+    /// Gets the extended description of this error, which 'explain-error' shows
+    pub fn description(&self) -> &'static str {
+        use ExecutableError::*;
+        match self {
+            PathNoLongerExists(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when an executable file is no longer accessible.\n",
+                "\n",
+                "CAUSE\n",
+                "- The executable file was deleted or moved after being located by the\n",
+                "  dispatcher, but before being executed.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Ensure that the executable file is in a location that will not be modified\n",
+                "  without explicit user action.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When dispatching an executable, the dispatcher will first locate the executable\n",
+                "file in one of the directories in the PATH environment variable. If the file is\n",
+                "found, it will then be set up with the appropriate environment variables and\n",
+                "executed. If the file has been deleted or moved after being located but before\n",
+                "being executed, this error is returned.",
+            ),
+            FailedToExecute(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when an executable returns a non-zero exit code.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The arguments provided to the executable were invalid.\n",
+                "- The executable was unable to locate a file it needed.\n",
+                "- The executable was unable to complete its task for some other reason.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The executable has a bug which causes it to return the wrong exit code.\n",
+                "- The executable uses non-conventional exit codes.\n",
+                "- The executable was located but could not be executed (code 126).\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Check the executable's documentation and adjust arguments accordingly.\n",
+                "- Ensure that all needed files are accessible to the executable.\n",
+                "- Reinstall the program associated with the executable.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "It is conventional for executables to return a zero exit code when they\n",
+                "complete successfully, and a non-zero exit code when they fail. After running\n",
+                "an executable, the disptacher will check its exit code, and if it is non-zero,\n",
+                "this error is returned.",
+            ),
+            CouldNotWait => concat!(
+                "OVERVIEW\n",
+                "This error is exceedingly rare and its cause is unknown. It is not expected to\n",
+                "occur.",
+            ),
+            CouldNotConnectStreams => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the input or output of an executable cannot be\n",
+                "connected.\n",
+                "\n",
+                "CAUSE\n",
+                "- The shell has run out of available file descriptors.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Close some programs or raise the open file limit using a command such as\n",
+                "  'ulimit'.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Before an executable is started, its standard streams are connected to the\n",
+                "terminal or to the other commands in its pipeline. This requires duplicating\n",
+                "file descriptors, and if that is not possible, this error is returned.",
+            ),
+            CouldNotStartSubshell => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a command run in the background with '&' needs a copy\n",
+                "of the shell to run in, but the copy cannot be started.\n",
+                "\n",
+                "CAUSE\n",
+                "- The system has run out of processes or memory for the current user.\n",
+                "\n",
+                "SOLUTION\n",
+                "- End some processes or raise the process limit using a command such as\n",
+                "  'ulimit'.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Builtins, functions, and blocks such as '{ ...; }' or 'while' loops normally run\n",
+                "inside the shell itself. In the background, they are run by a forked copy of\n",
+                "the shell instead, so that the shell can carry on straight away. If the fork\n",
+                "fails, this error is returned.",
+            ),
+        }
+    }
+}
+
+impl StateError {
+    /// Gets the extended description of this error, which 'explain-error' shows
+    pub fn description(&self) -> &'static str {
+        use StateError::*;
+        match self {
+            MissingEnv(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when an environment variable is missing from the parent\n",
+                "process.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The shell was launched from a non-standard environment.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The environment variable exists but is not accessible to the shell.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Ensure that the shell is launched from a standard environment.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When the shell is launched, it copies the environment variables such as HOME\n",
+                "and PATH from the parent process for its own use. If an environment variable is\n",
+                "missing from the parent process, this error is returned.",
+            ),
+            CouldNotUpdateEnv(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when an environment variable cannot be updated.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The value provided to the environment variable was malformed due to an\n",
+                "  internal bug.\n",
+                "- The value provided to the environment variable was malformed due to invalid\n",
+                "  input, such as a non-existent directory being provided to CWD.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The environment variable is not accessible to the shell.\n",
+                "- The environment variable is not writable.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Ensure that no invalid values are provided to environment variables.\n",
+                "- File an issue on the Rush repository if an internal bug is suspected.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "During execution, the shell keeps track of its environment through two\n",
+                "mechanisms. Firstly, it has an internal representation of the environment\n",
+                "variables, which are strongly-typed and validated. Secondly, it updates its\n",
+                "process environment, which is a construct of the OS, to match its internal\n",
+                "representation. This must be done in order for certain syscall-dependent\n",
+                "function calls to be correctly performed. If the process environment cannot be\n",
+                "updated, this error is returned.",
+            ),
+            NoPreviousDirectory => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the user erroneously invokes the 'previous-directory'\n",
+                "builtin.\n",
+                "\n",
+                "CAUSE\n",
+                "- The user ran the 'previous-directory' builtin, but the directory history is\n",
+                "  empty.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Navigate to another directory before returning to the previous one.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "The 'previous-directory' builtin allows the user to navigate to the directory\n",
+                "they were in before navigating to the current one. This is implemented using a\n",
+                "stack of directories, very similarly to how browser tabs handle navigation. If\n",
+                "the user attempts to navigate to the previous directory when the stack is\n",
+                "empty, this error is returned.",
+            ),
+            NoNextDirectory => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the user erroneously invokes the 'next-directory'\n",
+                "builtin.\n",
+                "\n",
+                "CAUSE\n",
+                "- The user ran the 'next-directory' builtin, but the directory history is\n",
+                "  empty.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Navigate to another directory before returning to the next one.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "The 'next-directory' builtin allows the user to navigate to the directory they\n",
+                "were in after navigating to the current one. This is implemented using a stack\n",
+                "of directories, very similarly to how browser tabs handle navigation. If the\n",
+                "user attempts to navigate to the next directory when the stack is empty, this\n",
+                "error is returned.",
+            ),
+            InvalidPathIndex(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell tries to update the PATH variable using an\n",
+                "invalid index.\n",
+                "\n",
+                "CAUSE\n",
+                "- A non-existent index was provided to the 'edit-path' builtin using the\n",
+                "  'insert' or 'delete' subcommand.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Check the PATH variable using the 'environment' builtin and ensure that the\n",
+                "  index provided is valid.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "The 'edit-path' builtin allows the user to modify the PATH variable. The PATH\n",
+                "variable is represented using a vector (list) of paths, which is indexed when\n",
+                "the user provides an index to insert or delete a path. If the user provides an\n",
+                "index which does not exist, this error is returned.",
+            ),
+            UnsupportedTerminal => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the line editor is unable to interact with the terminal.\n",
+                "\n",
+                "CAUSE\n",
+                "- The terminal is not supported by Rustyline (the line editor library).\n",
+                "\n",
+                "SOLUTION\n",
+                "- Run the shell in a different terminal.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "The line editor is responsible for handling user input and displaying the\n",
+                "prompt. It is implemented using the Rustyline library, which should support\n",
+                "most terminals. If the terminal being used does not support the requisite\n",
+                "features, this error is returned.",
+            ),
+            UnknownJob(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a job specifier does not refer to any job in the job\n",
+                "table.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The job has already finished and been reported as done.\n",
+                "- The job number was mistyped.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use the 'jobs' builtin to list the jobs which are still running or stopped.",
+            ),
+            NoCurrentJob => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a job builtin is run without a job specifier while there\n",
+                "are no jobs.\n",
+                "\n",
+                "CAUSE\n",
+                "- 'fg' or 'bg' was run before any command was started in the background or\n",
+                "  stopped.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Start a command in the background by ending it with '&'.",
+            ),
+            CouldNotSignalJob(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to send a signal to a job or\n",
+                "process.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The process has already exited.\n",
+                "- The process belongs to another user.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use the 'jobs' builtin to check that the job is still running.\n",
+                "- Ensure that the user has permission to signal the process.",
+            ),
+            CouldNotControlTerminal => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to hand the terminal to a job, or\n",
+                "take it back.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The shell was started in the background, so it does not control the terminal.\n",
+                "- The terminal was closed while a job was running.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Start the shell in the foreground of a terminal.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "Only the foreground process group of a terminal receives keyboard signals such\n",
+                "as the ones sent by Ctrl-C and Ctrl-Z. The shell moves each foreground job into\n",
+                "the foreground process group using tcsetpgrp(), and moves itself back once the\n",
+                "job has finished or been stopped.",
+            ),
+            InvalidVariableName(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a variable name is not valid.\n",
+                "\n",
+                "CAUSE\n",
+                "- The name is empty, starts with a digit, or contains characters other than\n",
+                "  letters, digits, and underscores.\n",
+                "- A variable was set without a value, as in 'set NAME'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use a name such as 'DATABASE_URL', and set it in the form 'NAME=value'.",
+            ),
+            ProtectedVariable(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when trying to set CWD, or to remove one of the variables\n",
+                "managed by the shell.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- CWD (or PWD) was set directly, as in 'set PWD=/tmp'.\n",
+                "- USER, HOME, CWD, or PATH was unset.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use 'change-directory' to change CWD.\n",
+                "- Give USER, HOME, or PATH a new value instead of removing it.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "These variables are stored in a typed form rather than as plain text, so that\n",
+                "the shell can rely on them being valid. A value given to PATH is split into its\n",
+                "directories, leaving out any which do not exist. CWD always matches the\n",
+                "directory the shell is in. They are always passed to commands run by the shell.",
+            ),
+            InvalidAliasName(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when an alias name is not valid.\n",
+                "\n",
+                "CAUSE\n",
+                "- The name is empty, or contains whitespace, quotes, '=', '/', or an operator\n",
+                "  such as '|'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use a name such as 'gs', and define the alias in the form 'alias gs = git\n",
+                "  status'.",
+            ),
+            UnknownAlias(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when trying to show or remove an alias which has not been\n",
+                "defined.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Run 'alias' on its own to list the aliases which have been defined.",
+            ),
+            UnknownSetting(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when trying to show or reset a setting which does not exist.\n",
+                "\n",
+                "CAUSE\n",
+                "- The setting's name is misspelled, or is missing its section, such as\n",
+                "  'truncation' instead of 'prompt.truncation'.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Run 'configure show' to list every setting by its full name.",
+            ),
+        }
+    }
+}
+
+impl FileError {
+    /// Gets the extended description of this error, which 'explain-error' shows
+    pub fn description(&self) -> &'static str {
+        use FileError::*;
+        match self {
+            FailedToConvertStringToPath(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to convert a string to a path.\n",
+                "\n",
+                "CAUSE\n",
+                "- One or more of the paths in the PATH environment variable are invalid.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Make sure all paths in the PATH variable exist and are accessible.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When the shell is launched, it copies the PATH environment variable from the\n",
+                "parent process and converts it to an internal representation for its own use.\n",
+                "Because of how environment variables are largely just unvalidated strings, it\n",
+                "is possible for the PATH to contain invalid paths. If any of the paths scannot\n",
+                "be converted, this error is returned.",
+            ),
+            FailedToConvertPathToString(..) => concat!(
+                "OVERVIEW\n",
+                "- This error occurs when the shell is unable to convert a path to a string.\n",
+                "\n",
+                "CAUSE\n",
+                "- The shell is attempting to display or operate on a path which is not valid\n",
+                "  UTF-8.\n",
+                "\n",
+                "SOLUTION\n",
+                "- File an issue on the Rush repository.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When the shell is attempting to display a path, or performing operations to\n",
+                "manipulate the path which require it to be a string, it may be unable to\n",
+                "convert the path to a string, at which point it returns this error. This is\n",
+                "largely because, even though a path may be valid on the filesystem, the OS and\n",
+                "filesystem may not use UTF-8 encoding.",
+            ),
+            CouldNotCanonicalize(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to canonicalize a path.\n",
+                "\n",
+                "CAUSES\n",
+                "- The path does not exist or is inaccessible.\n",
+                "- The path is misspelled or otherwise malformed.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Ensure that the path exists, is accessible by the user, and is formatted\n",
+                "  correctly.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When the shell receives a path as input, it will attempt to canonicalize it,\n",
+                "which is to say it will attempt to resolve any relative paths, symbolic links,\n",
+                "and shorthands such as '~'. If the absolute path cannot be determined, this\n",
+                "error is returned.",
+            ),
+            CouldNotGetParent(..) => concat!(
+                "OVERVIEW\n",
+                "This error is exceedingly rare and its cause is unknown. It is not expected to\n",
+                "occur.",
+            ),
+            CouldNotOpenFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a file cannot be opened.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The file does not exist or is inaccessible.\n",
+                "- The file is a directory.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The file is a symbolic link.\n",
+                "- The file is a special file, such as a device file.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Ensure that the file exists, is accessible by the user, and is not a\n",
+                "  directory any other special file type.",
+            ),
+            CouldNotReadFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a file's contents are invalid or inaccessible.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The file being read is the config file, and its contents are malformed.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The file was modified by another program while the shell was reading it.\n",
+                "- The file has been corrupted.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- If the file in question is a config file, make sure it is formatted\n",
+                "  correctly.\n",
+                "- Ensure that the file is not open in or being modified by another program.\n",
+                "  This is usually guaranteed by the OS/filesystem.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "When the shell is reading a configuration file, it will attempt to parse its\n",
+                "contents based on the expected format for the config. If the contents are\n",
+                "invalid, this error is returned. This error may also be returned upon reading\n",
+                "any file if the file is somehow externally modified in such a way that the\n",
+                "reading process is interrupted.",
+            ),
+            InvalidConfigFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the config file is not valid TOML, so none of its\n",
+                "settings can be read.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- A string value is missing its quotes, such as `command = ls -l` instead of\n",
+                "  `command = \"ls -l\"`.\n",
+                "- A section header or array is missing a closing bracket.\n",
+                "- The same setting or section appears twice.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Fix the file at the line and column given in the error message.\n",
+                "- Delete the config file, and a new one with the default settings will be\n",
+                "  created the next time the shell starts.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "The config file is parsed as TOML (https://toml.io) before any of its settings\n",
+                "are read. Settings which are unknown or have invalid values only produce\n",
+                "warnings, and are skipped, but a file which cannot be parsed at all is ignored\n",
+                "entirely and the defaults are used.",
+            ),
+            CouldNotCreateFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a file cannot be created.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The file already exists.\n",
+                "- The file's enclosing directory does not exist or is invalid.\n",
+                "- The file's enclosing directory is not writable.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The disk is full.\n",
+                "- The filesystem is read-only.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Check that the file does not already exist.\n",
+                "- Ensure that the file's enclosing directory exists and is writable.",
+            ),
+            CouldNotWriteFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a file cannot be written to.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The file or its enclosing directory is not writable.\n",
+                "- The file's enclosing directory does not exist.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The disk is full.\n",
+                "- The filesystem is read-only.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Ensure that the file's enclosing directory exists and is writable.",
+            ),
+            CouldNotDeleteFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a file cannot be deleted.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The file does not exist.\n",
+                "- The file is not writable.\n",
+                "- The file is open in another program.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The file is a special file, such as a device file.\n",
+                "- The filesystem is read-only.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Check that the file exists and is not a special file.\n",
+                "- Check that the file is not open in another program.\n",
+                "- Ensure that the file is writable.",
+            ),
+            CouldNotCreateDirectory(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a directory cannot be created.\n",
+                "\n",
+                "COMMON CAUSES\n",
+                "- The directory already exists.\n",
+                "- The directory's parent directory does not exist or is invalid.\n",
+                "- The directory's parent directory is not writable.\n",
+                "\n",
+                "RARE CAUSES\n",
+                "- The disk is full.\n",
+                "- The filesystem is read-only.\n",
+                "\n",
+                "SOLUTIONS\n",
+                "- Check that the directory does not already exist.\n",
+                "- Ensure that the directory's parent directory exists and is writable.",
+            ),
+            UnreadableFileType(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to determine the type of a file.\n",
+                "\n",
+                "CAUSE\n",
+                "- The file does not exist or is inaccessible.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Ensure that the file exists and is accessible by the user.\n",
+                "\n",
+                "TECHNICAL DETAILS\n",
+                "The \"type\" of a file refers to its filesystem classification. For example, a\n",
+                "file may be a regular file, a directory, a symbolic link, or a device file.\n",
+                "When the shell needs to determine the type of a file, it will attempt to read\n",
+                "its metadata. If the relevant metadata cannot be read, this error is returned.",
+            ),
+            UnreadableFileName(..) => concat!(
+                "OVERVIEW\n",
+                "This error is exceedingly rare and its cause is unknown. It is not expected to\n",
+                "occur.",
+            ),
+            UnreadablePermissions(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell cannot read the permissions of a file or\n",
+                "directory.\n",
+                "\n",
+                "CAUSE\n",
+                "- Inadequate user permissions.\n",
+                "- File or directory doesn't exist.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Enure the user has adequate permissions to read the file.\n",
+                "- Enure the directory or file exists.",
+            ),
+            UnreadableMetadata(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a files metadata cannot be read.\n",
+                "\n",
+                "CAUSE\n",
+                "- Inadequate user permissions.\n",
+                "- File doesn't exist\n",
+                "\n",
+                "SOLUTION\n",
+                "- Enure the user has adequate permissions to read the file.\n",
+                "- Enure the file exists.",
+            ),
+            UnreadableDirectory(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to read the contents of a directory.\n",
+                "\n",
+                "CAUSE\n",
+                "- The directory does not exist or is inaccessible.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Ensure that the directory exists and is accessible by the user.",
+            ),
+            UnknownPath(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when the shell is unable to locate a file or directory.\n",
+                "\n",
+                "CAUSE\n",
+                "- The file or directory does not exist or is inaccessible.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Ensure that the file or directory exists and is accessible by the user.",
+            ),
+            WouldClobberFile(..) => concat!(
+                "OVERVIEW\n",
+                "This error occurs when a redirection would overwrite an existing file while the\n",
+                "no-clobber setting is enabled.\n",
+                "\n",
+                "CAUSE\n",
+                "- Output was redirected with '>' or '&>' into a file which already exists.\n",
+                "\n",
+                "SOLUTION\n",
+                "- Use '>|' to overwrite the file anyway, or '>>' to append to it instead.\n",
+                "- Disable the setting with 'configure --no-clobber false'.",
+            ),
+        }
+    }
 }
 
 impl Display for ErrorKind {
@@ -592,8 +1445,12 @@ impl Display for DispatchError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         use DispatchError::*;
         match self {
-            UnknownCommand(command_name) => {
-                write!(f, "Command '{}' could not be found", command_name)
+            UnknownCommand(command_name, suggestion) => {
+                write!(f, "Command '{}' could not be found", command_name)?;
+                match suggestion {
+                    Some(suggestion) => write!(f, ", did you mean '{}'?", suggestion),
+                    None => Ok(()),
+                }
             }
             CommandNotExecutable(permission_code) => {
                 write!(
@@ -609,6 +1466,25 @@ impl Display for DispatchError {
                     path.display()
                 )
             }
+            EmptyPipelineStage => write!(f, "Pipeline contains a stage with no command"),
+            InvalidFileDescriptor(fd) => {
+                write!(f, "File descriptor '{}' cannot be redirected", fd)
+            }
+            UnsupportedRedirection(operator) => {
+                write!(f, "Redirection operator '{}' is not supported", operator)
+            }
+            MissingRedirectionTarget(operator) => {
+                write!(f, "Redirection operator '{}' has no target", operator)
+            }
+            MissingListCommand(operator) => {
+                write!(f, "Operator '{}' is missing a command", operator)
+            }
+            UnexpectedToken(token) => write!(f, "Unexpected token '{}'", token),
+            UnclosedSubshell => write!(f, "Subshell is missing a closing ')'"),
+            UnclosedQuote(quote) => write!(f, "Quote is missing a closing {}", quote),
+            MissingKeyword(keyword) => write!(f, "Block is missing a closing '{}'", keyword),
+            InvalidExpansion(expansion) => write!(f, "Expansion '{}' is not valid", expansion),
+            NoGlobMatches(pattern) => write!(f, "No files match the pattern '{}'", pattern),
         }
     }
 }
@@ -619,6 +1495,10 @@ impl Display for BuiltinError {
         match self {
             CouldNotParseArgs => write!(f, "Unable to parse the provided arguments"),
             TerminalOperationFailed => write!(f, "Terminal operation failed"),
+            CouldNotWriteOutput => write!(f, "Could not write output"),
+            InvalidSignal(signal) => write!(f, "Signal '{}' is not recognized", signal),
+            ScriptFailed(message) => write!(f, "Script failed: {}", message),
+            UnknownError(name) => write!(f, "There is no error named '{}'", name),
         }
     }
 }
@@ -634,6 +1514,8 @@ impl Display for ExecutableError {
                 write!(f, "Executable failed with exit code {}", exit_code)
             }
             CouldNotWait => write!(f, "Failed to wait for executable to complete"),
+            CouldNotConnectStreams => write!(f, "Could not connect executable input and output"),
+            CouldNotStartSubshell => write!(f, "Could not start a subshell for a background job"),
         }
     }
 }
@@ -662,6 +1544,17 @@ impl Display for StateError {
                 write!(f, "Path index {} is invalid", index)
             }
             UnsupportedTerminal => write!(f, "Terminal is not supported"),
+            UnknownJob(job) => write!(f, "Job '{}' does not exist", job),
+            NoCurrentJob => write!(f, "There are no jobs"),
+            CouldNotSignalJob(job) => write!(f, "Could not send a signal to '{}'", job),
+            CouldNotControlTerminal => write!(f, "Could not take control of the terminal"),
+            InvalidVariableName(name) => write!(f, "'{}' is not a valid variable name", name),
+            InvalidAliasName(name) => write!(f, "'{}' is not a valid alias name", name),
+            UnknownAlias(name) => write!(f, "Alias '{}' does not exist", name),
+            UnknownSetting(name) => write!(f, "Setting '{}' does not exist", name),
+            ProtectedVariable(name) => {
+                write!(f, "Variable '{}' is managed by the shell", name)
+            }
         }
     }
 }
@@ -693,9 +1586,20 @@ impl Display for FileError {
             CouldNotReadFile(path) => {
                 write!(f, "Could not read file at path '{}'", path.display())
             }
+            InvalidConfigFile(path, line, column, message) => write!(
+                f,
+                "Config file '{}' is invalid at line {}, column {}: {}",
+                path.display(),
+                line,
+                column,
+                message.trim()
+            ),
             CouldNotCreateFile(path) => {
                 write!(f, "Could not create file at path '{}'", path.display())
             }
+            CouldNotWriteFile(path) => {
+                write!(f, "Could not write to file at path '{}'", path.display())
+            }
             CouldNotDeleteFile(path) => {
                 write!(f, "Could not delete file at path '{}'", path.display())
             }
@@ -717,18 +1621,10 @@ impl Display for FileError {
                 )
             }
             UnreadablePermissions(path) => {
-                write!(
-                    f,
-                    "Could not get permissions at path '{}'",
-                    path.display()
-                )
+                write!(f, "Could not get permissions at path '{}'", path.display())
             }
             UnreadableMetadata(path) => {
-                write!(
-                    f,
-                    "Could not read metadata at path '{}'",
-                    path.display()
-                )
+                write!(f, "Could not read metadata at path '{}'", path.display())
             }
             UnreadableDirectory(path) => {
                 write!(f, "Could not read directory at path '{}'", path.display())
@@ -740,6 +1636,13 @@ impl Display for FileError {
                     path.display()
                 )
             }
+            WouldClobberFile(path) => {
+                write!(
+                    f,
+                    "File at path '{}' already exists and no-clobber is enabled",
+                    path.display()
+                )
+            }
         }
     }
 }
@@ -752,12 +1655,12 @@ macro_rules! dispatch_err {
         use crate::errors::RushError;
         RushError::new(ErrorKind::Dispatch(DispatchError::$variant))
     }};
-    ($variant:ident: $content:expr) => {{
+    ($variant:ident: $($content:expr),+ $(,)?) => {{
         use crate::errors::DispatchError;
         use crate::errors::ErrorKind;
         use crate::errors::RushError;
         RushError::new(ErrorKind::Dispatch(DispatchError::$variant(
-            $content.clone().into(),
+            $($content.clone().into()),+
         )))
     }};
 }
@@ -798,18 +1701,145 @@ macro_rules! file_err {
     }};
 }
 
-/// Shortcut for printing a `clap::Error` and returning a `BuiltinError::CouldNotParseArgs`
+/// Shortcut for printing the help of a builtin, or returning a `BuiltinError::CouldNotParseArgs`
+/// with the message from a `clap::Error`
+// * The message is part of the error, rather than being printed here, so that it is reported
+// * like any other error, such as to a redirected error stream or not at all with 'show-errors'
 macro_rules! clap_handle {
     ($expr:expr) => {
         match $expr {
             Ok(val) => val,
-            Err(e) => {
-                eprintln!("{}", e.render().ansi());
-                match e.kind() {
-                    clap::error::ErrorKind::DisplayHelp => return crate::errors::Result::Ok(()),
-                    _ => return crate::errors::Result::Err(builtin_err!(CouldNotParseArgs)),
+            Err(e) => match e.kind() {
+                clap::error::ErrorKind::DisplayHelp => {
+                    eprintln!("{}", e.render().ansi());
+                    return crate::errors::Result::Ok(());
                 }
-            }
+                _ => {
+                    let message = e.render().ansi().to_string();
+                    return crate::errors::Result::Err(
+                        builtin_err!(CouldNotParseArgs).set_context(message.trim_end()),
+                    );
+                }
+            },
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_every_error_kind() {
+        let variants = ErrorKind::variants();
+        for kind in &variants {
+            let description = kind.description();
+            let overview = description.strip_prefix("OVERVIEW\n");
+            assert!(
+                overview.is_some_and(|text| !text.trim().is_empty()),
+                "{}",
+                kind.name()
+            );
+        }
+
+        let mut names: Vec<String> = variants.iter().map(ErrorKind::name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), variants.len());
+    }
+
+    #[test]
+    fn explains_errors_by_name() {
+        let (name, description) = explain("unknown-command").unwrap();
+        assert_eq!(name, "UnknownCommand");
+        assert_eq!(
+            description,
+            DispatchError::UnknownCommand(String::new(), None).description()
+        );
+
+        let (name, _) = explain("would_clobber_file").unwrap();
+        assert_eq!(name, "WouldClobberFile");
+        assert!(explain("NotAnError").is_none());
+    }
+
+    #[test]
+    fn maps_errors_to_exit_statuses() {
+        assert_eq!(
+            dispatch_err!(UnknownCommand: "x", None::<String>).exit_code(),
+            127
+        );
+        assert_eq!(
+            dispatch_err!(CommandNotExecutable: 0o644u32).exit_code(),
+            126
+        );
+        assert_eq!(dispatch_err!(UnexpectedToken: ")").exit_code(), 2);
+        assert_eq!(dispatch_err!(NoGlobMatches: "*.x").exit_code(), 1);
+        assert_eq!(builtin_err!(CouldNotParseArgs).exit_code(), 2);
+        assert_eq!(executable_err!(FailedToExecute: 42isize).exit_code(), 42);
+        assert_eq!(
+            executable_err!(PathNoLongerExists: PathBuf::new()).exit_code(),
+            127
+        );
+        assert_eq!(state_err!(NoPreviousDirectory).exit_code(), 1);
+        assert_eq!(file_err!(UnknownPath: PathBuf::new()).exit_code(), 1);
+
+        // * Only an executable's own status could be 0, which is never an error
+        for kind in ErrorKind::variants() {
+            let status = kind.exit_code();
+            let executable = matches!(
+                kind,
+                ErrorKind::Executable(ExecutableError::FailedToExecute(_))
+            );
+            assert!(executable || (1..=255).contains(&status), "{}", kind.name());
+        }
+    }
+
+    #[test]
+    fn keeps_the_sources_of_replaced_errors() {
+        let cause = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let inner = file_err!(CouldNotReadFile: PathBuf::from("a.txt")).set_source(&cause);
+        let error = Err::<(), _>(inner)
+            .replace_err_with_msg(|| builtin_err!(ScriptFailed: "x"), "The script failed")
+            .unwrap_err();
+
+        let sources: Vec<String> = error.sources().map(ToString::to_string).collect();
+        let inner = FileError::CouldNotReadFile(PathBuf::from("a.txt")).to_string();
+        assert_eq!(sources, [inner.as_str(), "no such file"]);
+        assert_eq!(error.to_string(), "The script failed");
+        assert_eq!(error.set_context("script:1: x").sources().count(), 2);
+
+        let error = None::<()>
+            .replace_err(|| state_err!(NoCurrentJob))
+            .unwrap_err();
+        assert_eq!(error.sources().count(), 0);
+    }
+
+    #[test]
+    fn keeps_the_sources_of_filesystem_errors() {
+        let path = std::env::temp_dir().join("rush-test-missing-directory/file.txt");
+        let error = fs_err::read_to_string(&path)
+            .replace_err(|| file_err!(CouldNotReadFile: path))
+            .unwrap_err();
+
+        let sources: Vec<String> = error.sources().map(ToString::to_string).collect();
+        assert_eq!(sources.len(), 2);
+        assert!(sources[0].contains(&path.display().to_string()));
+        let cause = std::io::Error::from_raw_os_error(nix::libc::ENOENT);
+        assert_eq!(sources[1], cause.to_string());
+    }
+
+    #[test]
+    fn keeps_the_messages_of_argument_errors() {
+        fn parse(args: &[&str]) -> Result<()> {
+            clap_handle!(clap::Command::new("rush-test").try_get_matches_from(args));
+            Ok(())
+        }
+
+        let error = parse(&["rush-test", "--bogus"]).unwrap_err();
+        assert_eq!(error.name(), "CouldNotParseArgs");
+        let message = error.to_string();
+        assert!(message.contains("unexpected argument") && message.contains("--bogus"));
+        assert!(!message.ends_with('\n'));
+        assert!(parse(&["rush-test", "--help"]).is_ok());
+    }
+}
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use super::redirection::Redirection;
+
+/// A range of bytes in the line of input which a node was parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Creates a span covering both this span and another one
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// Represents a sequence of pipelines joined by list operators, such as `make && run ./app; pwd`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct List {
+    pub items: Vec<ListItem>,
+    pub span: Span,
+}
+
+/// A single pipeline in a list, along with the operator which decides whether it is run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListItem {
+    pub connector: Connector,
+    pub pipeline: Pipeline,
+    /// Whether the pipeline was followed by `&`, meaning it runs as a background job
+    // * As with ';', a '&' only applies to the pipeline directly before it
+    pub background: bool,
+}
+
+/// Represents the operator before a pipeline in a list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// `;` or `&` (or the start of the list): The pipeline always runs
+    Always,
+    /// `&&`: The pipeline only runs if the previous pipeline succeeded
+    IfSucceeded,
+    /// `||`: The pipeline only runs if the previous pipeline failed
+    IfFailed,
+}
+
+/// Represents a sequence of commands joined by pipes, such as `read-file log | grep error`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pipeline {
+    pub commands: Vec<Command>,
+    pub span: Span,
+}
+
+/// Represents a single stage of a pipeline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    Subshell(Subshell),
+    Compound(CompoundCommand),
+    Function(FunctionDefinition),
+}
+
+/// Represents a command name and its arguments, along with any assignments and redirections
+/// which apply to it, such as `LOG=1 build --release > out`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimpleCommand {
+    pub assignments: Vec<Assignment>,
+    pub words: Vec<Word>,
+    pub redirections: Vec<Redirection>,
+    pub span: Span,
+}
+
+/// Represents a list which is run in a copy of the shell state, such as `(cd src; ls)`,
+/// so that any changes it makes do not affect the shell itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subshell {
+    pub body: List,
+    pub redirections: Vec<Redirection>,
+    pub span: Span,
+}
+
+/// Represents a command which controls how the lists inside it are run, such as a loop,
+/// along with any redirections which apply to all of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompoundCommand {
+    pub kind: CompoundKind,
+    pub redirections: Vec<Redirection>,
+    pub span: Span,
+}
+
+/// Represents the different kinds of compound commands
+// * Unlike a subshell, each of these runs in the shell itself, so any changes they make are kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompoundKind {
+    /// `{ cd src; ls; }`: The list is run as a single command
+    Group(List),
+    /// `if a; then b; elif c; then d; else e; fi`: The body of the first condition which succeeds
+    /// is run, or the `else` body if none of them do
+    If {
+        branches: Vec<Branch>,
+        otherwise: Option<List>,
+    },
+    /// `while a; do b; done`: The body is run for as long as the condition succeeds,
+    /// or for as long as it fails with `until`
+    While {
+        condition: List,
+        body: List,
+        until: bool,
+    },
+    /// `for name in a b c; do body; done`: The body is run once for each argument the words expand
+    /// to, with the variable set to that argument (the words default to the positional arguments)
+    For {
+        variable: String,
+        words: Option<Vec<Word>>,
+        body: List,
+    },
+}
+
+/// A condition of an `if` command, along with the list which runs if it succeeds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    pub condition: List,
+    pub body: List,
+}
+
+/// Represents the definition of a function, such as `greet() { echo hello $1; }`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub body: CompoundCommand,
+    pub span: Span,
+}
+
+/// A function which has been defined in the shell, along with the line it was defined in
+// * The spans in the body refer to that line, which is used to describe any jobs the function starts
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub body: Arc<CompoundCommand>,
+    pub source: Arc<str>,
+}
+
+/// Represents a variable assignment at the start of a command, such as `NAME=value`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assignment {
+    pub name: String,
+    pub value: Word,
+    pub span: Span,
+}
+
+/// Represents a single word of input, keeping track of which parts of it were quoted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+    pub span: Span,
+}
+
+/// A section of a word which was quoted (or not quoted) in the same way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    /// Text which was not quoted at all
+    Unquoted(String),
+    /// Text which was single-quoted or escaped with a backslash, and must be used exactly as written
+    Literal(String),
+    /// Text which was inside double quotes
+    DoubleQuoted(String),
+}
+
+impl Word {
+    /// Gets the text of the word with all of its quoting removed
+    pub fn text(&self) -> String {
+        self.parts.iter().map(WordPart::text).collect()
+    }
+
+    /// Gets the text of the word with the quoting of each part put back, so that it is parsed into
+    /// the same word again
+    pub fn quoted(&self) -> String {
+        let literals = |a: &WordPart, b: &WordPart| {
+            matches!((a, b), (WordPart::Literal(_), WordPart::Literal(_)))
+        };
+        self.parts
+            .chunk_by(literals)
+            .map(|parts| match parts {
+                [WordPart::Unquoted(text)] => text.clone(),
+                [WordPart::DoubleQuoted(text)] => format!("\"{}\"", text),
+                // * Neighbouring literal parts, such as those of 'it'\''s', are quoted together
+                // * A single quote cannot be escaped inside single quotes, so it is escaped between
+                // * two quoted pieces instead
+                literals => {
+                    let text: String = literals.iter().map(WordPart::text).collect();
+                    format!("'{}'", text.replace('\'', r"'\''"))
+                }
+            })
+            .collect()
+    }
+}
+
+impl WordPart {
+    /// Gets the text of the part with its quoting removed
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Unquoted(text) | Self::Literal(text) | Self::DoubleQuoted(text) => text,
+        }
+    }
+}
@@ -1,10 +1,23 @@
+use std::io::{self, Cursor, Read, Write};
 use std::os::unix::prelude::PermissionsExt;
+use std::path::Path as StdPath;
+use std::process::Child;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 
-use super::tokenizer::tokenize;
-use crate::errors::Result;
+use nix::unistd::{fork, setpgid, ForkResult, Pid};
+
+use super::ast::{
+    Command, CompoundCommand, CompoundKind, Connector, Function, FunctionDefinition, List,
+    Pipeline, SimpleCommand, Subshell, Word,
+};
+use super::expansion::Expander;
+use super::parser::{is_incomplete, parse_with_aliases};
+use super::redirection::Redirection;
+use crate::errors::{Handle, Result, RushError};
 use crate::exec::{builtin_funcs, Builtin};
-use crate::exec::{Executable, Runnable};
-use crate::state::{Path, ShellState};
+use crate::exec::{Executable, Input, Output, Runnable, Streams};
+use crate::state::{Job, Path, ShellState};
 
 /// Represents a collection of builtin commands
 /// Allows for command resolution and execution through aliases
@@ -34,6 +47,19 @@ impl Default for Dispatcher {
         dispatcher.add_builtin("configure", vec!["config", "conf"], builtin_funcs::configure);
         dispatcher.add_builtin("environment-variable", vec!["environment", "env", "ev"], builtin_funcs::environment_variable);
         dispatcher.add_builtin("edit-path", vec!["path", "ep"], builtin_funcs::edit_path);
+        dispatcher.add_builtin("list-jobs", vec!["jobs"], builtin_funcs::list_jobs);
+        dispatcher.add_builtin("foreground-job", vec!["fg"], builtin_funcs::foreground_job);
+        dispatcher.add_builtin("background-job", vec!["bg"], builtin_funcs::background_job);
+        dispatcher.add_builtin("wait-for-job", vec!["wait"], builtin_funcs::wait_for_job);
+        dispatcher.add_builtin("send-signal", vec!["kill"], builtin_funcs::send_signal);
+        dispatcher.add_builtin("set-variable", vec!["set", "sv"], builtin_funcs::set_variable);
+        dispatcher.add_builtin("unset-variable", vec!["unset", "uv"], builtin_funcs::unset_variable);
+        dispatcher.add_builtin("export-variable", vec!["export", "xv"], builtin_funcs::export_variable);
+        dispatcher.add_builtin("list-variables", vec!["variables", "vars", "lv"], builtin_funcs::list_variables);
+        dispatcher.add_builtin("evaluate-script", vec!["rhai", "script", "es"], builtin_funcs::evaluate_script);
+        dispatcher.add_builtin("edit-aliases", vec!["alias", "ea"], builtin_funcs::edit_aliases);
+        dispatcher.add_builtin("explain-error", vec!["explain", "why", "ee"], builtin_funcs::explain_error);
+        dispatcher.add_builtin("source-file", vec!["source", "."], builtin_funcs::source_file);
 
         dispatcher
     }
@@ -47,18 +73,25 @@ impl Dispatcher {
     }
 
     /// Adds a builtin to the `Dispatcher`
-    fn add_builtin<F: Fn(&mut ShellState, Vec<&str>) -> Result<()> + 'static>(
-        &mut self,
-        true_name: &str,
-        aliases: Vec<&str>,
-        function: F,
-    ) {
+    fn add_builtin<F>(&mut self, true_name: &str, aliases: Vec<&str>, function: F)
+    where
+        F: Fn(&mut ShellState, Vec<&str>, &mut Streams) -> Result<()> + Send + Sync + 'static,
+    {
         self.commands
             .push(Builtin::new(true_name, aliases, function))
     }
 
+    /// Attempts to locate a function or builtin command by name or alias
+    // * Functions are checked first, so that a function can replace a builtin of the same name
+    fn resolve(&self, shell: &ShellState, command_name: &str) -> Option<Resolved<'_>> {
+        match shell.functions.get(command_name) {
+            Some(function) => Some(Resolved::Function(function.clone())),
+            None => self.resolve_builtin(command_name).map(Resolved::Builtin),
+        }
+    }
+
     /// Attempts to locate a builtin command by name or alias
-    fn resolve(&self, command_name: &str) -> Option<&Builtin> {
+    pub fn resolve_builtin(&self, command_name: &str) -> Option<&Builtin> {
         for command in &self.commands {
             if command.true_name == command_name {
                 return Some(command);
@@ -72,26 +105,839 @@ impl Dispatcher {
         None
     }
 
+    /// Gets the names and aliases of every builtin
+    pub fn builtin_names(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .flat_map(|command| {
+                std::iter::once(command.true_name.as_str())
+                    .chain(command.aliases.iter().map(String::as_str))
+            })
+            .collect()
+    }
+
+    /// Finds the command name closest to one which could not be resolved, as a likely typo
+    // * The candidates are checked in order of precedence, so that a builtin's true name
+    // * is preferred over an alias or executable which is just as close
+    fn suggest_command(&self, shell: &ShellState, command_name: &str) -> Option<String> {
+        let mut candidates: Vec<String> = self
+            .commands
+            .iter()
+            .map(|command| command.true_name.clone())
+            .collect();
+        candidates.extend(
+            self.commands
+                .iter()
+                .flat_map(|command| command.aliases.iter().cloned()),
+        );
+        candidates.extend(shell.aliases.iter().map(|(name, _)| name.clone()));
+        candidates.extend(shell.functions.keys().cloned());
+        for directory in shell.environment.PATH() {
+            // * Directories in the PATH which cannot be read are skipped, as they are when resolving
+            if let Ok(entries) = fs_err::read_dir(directory.path()) {
+                candidates.extend(
+                    entries
+                        .flatten()
+                        .filter_map(|entry| entry.file_name().into_string().ok()),
+                );
+            }
+        }
+
+        // Only a match which differs by about one edit for every four characters is suggested,
+        // so that short names are not matched with unrelated commands
+        // * The name itself is never suggested, such as an alias which expands to a missing command
+        let limit = (command_name.chars().count() / 4).max(1);
+        candidates
+            .into_iter()
+            .filter(|candidate| candidate != command_name)
+            .map(|candidate| {
+                (
+                    strsim::damerau_levenshtein(command_name, &candidate),
+                    candidate,
+                )
+            })
+            .filter(|(distance, _)| *distance <= limit)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate)
+    }
+
+    /// Finds the builtin which runs Rhai scripts, if the command is the path of one
+    fn resolve_script(&self, command_name: &str) -> Option<&Builtin> {
+        let path = std::path::Path::new(command_name);
+        let is_script = path
+            .extension()
+            .is_some_and(|extension| extension == "rhai");
+        match is_script && path.is_file() {
+            true => self.resolve_builtin("evaluate-script"),
+            false => None,
+        }
+    }
+
     /// Evaluates and executes a command from a string
     pub fn eval(&self, shell: &mut ShellState, line: &str) -> Result<()> {
-        let args = tokenize(line);
-        let command_name = args.get(0).unwrap().as_str();
-        let command_args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
-        self.dispatch(shell, command_name, command_args)?;
+        // The whole line is parsed before anything runs, so that a mistake near the end of a list
+        // cannot leave the commands before it half-finished
+        let list = parse_with_aliases(line, &shell.aliases)?;
+        self.run_list(shell, &list, line, &mut Streams::default())
+    }
 
-        Ok(())
+    /// Runs each command in a file in the current shell, as for 'source-file' and the startup scripts
+    pub fn eval_file(
+        &self,
+        shell: &mut ShellState,
+        filename: &StdPath,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        let contents = fs_err::read_to_string(filename)
+            .replace_err(|| file_err!(CouldNotReadFile: filename))?;
+        let name = filename.display().to_string();
+        self.eval_lines(shell, &name, contents.lines().map(str::to_owned), streams)
     }
 
-    /// Resolves and dispatches a command to the appropriate builtin or executable
-    fn dispatch(
+    /// Runs each command in a sequence of lines, such as a script, a '-c' string or piped input
+    /// Errors are reported with the name of the source and the line which the command started on
+    // * Only the status of the final command is returned, so any earlier error is reported here
+    pub fn eval_lines(
         &self,
         shell: &mut ShellState,
-        command_name: &str,
-        command_args: Vec<&str>,
+        name: &str,
+        lines: impl Iterator<Item = String>,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        let mut result = Ok(());
+        let mut buffer = String::new();
+        let mut start = 0;
+        let mut lines = lines.enumerate().peekable();
+        while let Some((index, line)) = lines.next() {
+            // Blank lines and comments are skipped, so that they do not change the status
+            let trimmed = line.trim_start();
+            if buffer.is_empty() && (trimmed.is_empty() || trimmed.starts_with('#')) {
+                continue;
+            }
+
+            // * A block which spans several lines is collected until it is closed, or the input ends
+            if buffer.is_empty() {
+                start = index + 1;
+            }
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+            if is_incomplete(&buffer) && lines.peek().is_some() {
+                continue;
+            }
+
+            if let Err(error) = std::mem::replace(&mut result, Ok(())) {
+                shell.report_error(&error);
+            }
+
+            result = parse_with_aliases(&buffer, &shell.aliases)
+                .and_then(|list| self.run_list(shell, &list, &buffer, streams))
+                .map_err(|error| {
+                    let message = format!("{}:{}: {}", name, start, error);
+                    error.set_context(&message)
+                });
+            shell.set_status(&result);
+            buffer.clear();
+            if shell.should_exit {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Runs each pipeline of a list, deciding whether to run it based on the previous result
+    // * The source is the line which the list was parsed from, which is used to describe jobs
+    fn run_list(
+        &self,
+        shell: &mut ShellState,
+        list: &List,
+        source: &str,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        let mut result = Ok(());
+        for item in &list.items {
+            let should_run = match item.connector {
+                Connector::Always => true,
+                Connector::IfSucceeded => result.is_ok(),
+                Connector::IfFailed => result.is_err(),
+            };
+
+            if !should_run {
+                continue;
+            }
+
+            // * Only the status of the final command is returned, so any earlier error is reported
+            // * here before it is replaced, unless a '||' has already handled it
+            if let Err(error) = std::mem::replace(&mut result, Ok(())) {
+                if item.connector != Connector::IfFailed {
+                    shell.report_error(&error);
+                }
+            }
+
+            result = self.run_pipeline(shell, &item.pipeline, source, streams, item.background);
+            shell.set_status(&result);
+            if shell.should_exit {
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// Runs each stage of a pipeline, connecting the output of each stage to the input of the next
+    /// In the background, the pipeline is left running as a job
+    fn run_pipeline(
+        &self,
+        shell: &mut ShellState,
+        pipeline: &Pipeline,
+        source: &str,
+        streams: &mut Streams,
+        background: bool,
+    ) -> Result<()> {
+        let stages = pipeline
+            .commands
+            .iter()
+            .map(|command| {
+                self.resolve_stage(shell, command).map_err(|mut error| {
+                    self.report_unresolved(shell, command, &mut error, streams);
+                    error
+                })
+            })
+            .collect::<Result<Vec<Stage>>>()?;
+
+        // * A stage inside the shell would hold up the shell until it finished, so a pipeline with
+        // * one is run in the background by a forked copy of the shell, as a subshell would be
+        if background && stages.iter().any(Stage::runs_in_shell) {
+            return self.fork_pipeline(shell, pipeline, &stages, source, streams);
+        }
+
+        self.run_stages(shell, pipeline, &stages, source, streams, background)
+    }
+
+    /// Runs the stages of a pipeline which have already been resolved
+    /// In the background, the executables in the pipeline are left running as a job
+    // * Executables are all started before anything else is run, so that a builtin writing into a
+    // * pipe always has a running process on the other end to consume its output
+    fn run_stages(
+        &self,
+        shell: &mut ShellState,
+        pipeline: &Pipeline,
+        stages: &[Stage],
+        source: &str,
+        streams: &mut Streams,
+        background: bool,
+    ) -> Result<()> {
+        // Stages which run inside the shell run one after another, so the output which feeds into one
+        // is collected in memory by a separate thread until it runs
+        // * This includes the output of an executable, which would otherwise block once the pipe is
+        // * full, while the shell waits for it before running the next stage
+        let mut inputs = vec![streams
+            .stdin
+            .share()
+            .replace_err(|| executable_err!(CouldNotConnectStreams))?];
+        let mut outputs = Vec::new();
+        let mut collectors: Vec<Option<JoinHandle<io::Result<Vec<u8>>>>> = Vec::new();
+        for pair in stages.windows(2) {
+            let (mut reader, writer) =
+                io::pipe().replace_err(|| executable_err!(CouldNotConnectStreams))?;
+            outputs.push(Output::Pipe(writer));
+            if pair[1].runs_in_shell() {
+                inputs.push(Input::Buffer(Cursor::new(Vec::new())));
+                collectors.push(Some(thread::spawn(move || {
+                    let mut buffer = Vec::new();
+                    reader.read_to_end(&mut buffer).map(|_| buffer)
+                })));
+            } else {
+                inputs.push(Input::Pipe(reader));
+                collectors.push(None);
+            }
+        }
+        outputs.push(
+            streams
+                .stdout
+                .try_clone()
+                .replace_err(|| executable_err!(CouldNotConnectStreams))?,
+        );
+
+        let mut stage_streams = Vec::new();
+        for (stdin, stdout) in inputs.into_iter().zip(outputs) {
+            let stderr = streams
+                .stderr
+                .try_clone()
+                .replace_err(|| executable_err!(CouldNotConnectStreams))?;
+            stage_streams.push(Some(Streams {
+                stdin,
+                stdout,
+                stderr,
+            }));
+        }
+
+        let mut results: Vec<Option<Result<()>>> = stages.iter().map(|_| None).collect();
+        let mut processes: Vec<Option<Child>> = stages.iter().map(|_| None).collect();
+        // * The errors of executables are only known once they have been waited for, so the streams
+        // * which they should be written to are kept until then
+        let mut error_outputs: Vec<Option<Output>> = stages.iter().map(|_| None).collect();
+
+        // * Jobs get a process group of their own, led by their first process, so that they can be
+        // * signalled as a whole without affecting the shell
+        // * Without job control, foreground jobs stay in the shell's process group instead
+        let mut process_group = (background || shell.jobs.has_job_control()).then_some(0);
+
+        for (index, stage) in stages.iter().enumerate() {
+            if let Stage::Executable(executable, command, arguments) = stage {
+                // * Taking the streams out of the list drops them once the process is spawned,
+                // * which closes the shell's copies of the pipes so the process can receive EOF
+                if let Some(streams) = stage_streams[index].take() {
+                    let spawned = self
+                        .redirect(shell, &command.redirections, streams)
+                        .and_then(|mut streams| {
+                            let arguments = arguments.iter().map(String::as_str).collect();
+                            let mut spawned =
+                                executable.spawn(arguments, &mut streams, process_group);
+                            if let Err(error) = &mut spawned {
+                                report_to_stage(shell, error, &mut streams.stderr);
+                            }
+
+                            // * A pipe is not kept, since holding it open would stop the command
+                            // * reading from it from ever finishing
+                            if !matches!(streams.stderr, Output::Pipe(_)) {
+                                error_outputs[index] = streams.stderr.try_clone().ok();
+                            }
+
+                            spawned
+                        });
+
+                    match spawned {
+                        Ok(process) => {
+                            if process_group == Some(0) {
+                                process_group = Some(process.id() as i32);
+                                // * The terminal is handed over straight away, since the job may
+                                // * need it before the stages inside the shell have finished
+                                if !background {
+                                    let pgid = Pid::from_raw(process.id() as i32);
+                                    if let Err(error) = shell.jobs.give_terminal(pgid) {
+                                        shell.report_error(&error);
+                                    }
+                                }
+                            }
+
+                            processes[index] = Some(process);
+                        }
+                        Err(error) => results[index] = Some(Err(error)),
+                    }
+                }
+            }
+        }
+
+        for (index, stage) in stages.iter().enumerate() {
+            if let Some(streams) = stage_streams[index].take() {
+                // * The redirections after the body of a function apply every time it is called
+                let redirected = match stage {
+                    Stage::Executable(..) => continue,
+                    Stage::Builtin(_, command, _) | Stage::Assignments(command) => {
+                        self.redirect(shell, &command.redirections, streams)
+                    }
+                    Stage::Function(function, command, _) => self
+                        .redirect(shell, &command.redirections, streams)
+                        .and_then(|streams| {
+                            self.redirect(shell, &function.body.redirections, streams)
+                        }),
+                    Stage::Subshell(subshell) => {
+                        self.redirect(shell, &subshell.redirections, streams)
+                    }
+                    Stage::Compound(compound) => {
+                        self.redirect(shell, &compound.redirections, streams)
+                    }
+                    Stage::Definition(_) => Ok(streams),
+                };
+
+                results[index] = Some(redirected.and_then(|mut streams| {
+                    let mut result = self.run_stage(shell, stage, source, &mut streams);
+                    if let Err(error) = &mut result {
+                        report_to_stage(shell, error, &mut streams.stderr);
+                    }
+
+                    result
+                }));
+            }
+
+            // Pass any output collected in memory to the next stage in the pipeline
+            // * The stage's streams have been dropped by this point, so the collector
+            // * has received EOF and is guaranteed to finish
+            if let Some(collector) = collectors.get_mut(index).and_then(Option::take) {
+                let buffer = collector
+                    .join()
+                    // * A thread which panicked has no error which can be kept as the cause
+                    .ok()
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?;
+                if let Some(next_streams) =
+                    stage_streams.get_mut(index + 1).and_then(Option::as_mut)
+                {
+                    next_streams.stdin = Input::Buffer(Cursor::new(buffer));
+                }
+            }
+        }
+
+        let indices: Vec<usize> = (0..processes.len())
+            .filter(|&index| processes[index].is_some())
+            .collect();
+        let pids: Vec<u32> = processes.iter().flatten().map(Child::id).collect();
+        if !pids.is_empty() {
+            let command = source.get(pipeline.span.start..pipeline.span.end);
+            let job = Job::new(pids, command.unwrap_or(source));
+            if background {
+                // The processes are tracked by the job table from here on, instead of being waited for
+                add_background_job(shell, job);
+            } else {
+                let statuses = shell.jobs.wait_in_foreground(job)?;
+                for (index, status) in indices.into_iter().zip(statuses) {
+                    results[index] = Some(status.into_result());
+                }
+            }
+        }
+
+        for (result, output) in results.iter_mut().zip(error_outputs) {
+            if let (Some(Err(error)), Some(mut output)) = (result.as_mut(), output) {
+                report_to_stage(shell, error, &mut output);
+            }
+        }
+
+        // * POSIX gives a pipeline the status of its last stage,
+        // * but errors from earlier stages are still reported so that they are not lost
+        let last_result = results.pop().flatten().unwrap_or(Ok(()));
+        for error in results
+            .into_iter()
+            .flatten()
+            .filter_map(|result| result.err())
+        {
+            shell.report_error(&error);
+        }
+
+        last_result
+    }
+
+    /// Runs a pipeline in a forked copy of the shell, which is left running as a background job
+    // * The copy has all of the shell's functions and variables, but any changes it makes to them
+    // * are lost when it exits, just as with a subshell
+    fn fork_pipeline(
+        &self,
+        shell: &mut ShellState,
+        pipeline: &Pipeline,
+        stages: &[Stage],
+        source: &str,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        // * Output which is still buffered would otherwise be written out by both processes
+        let _ = io::stdout().flush();
+        // * The copy of the shell only runs the pipeline and exits, so none of the state which
+        // * other threads may have been changing at the time of the fork is relied upon
+        let fork = unsafe { fork() }.replace_err(|| executable_err!(CouldNotStartSubshell))?;
+        match fork {
+            ForkResult::Child => {
+                // * The copy leads a process group of its own, so that it can be signalled as a job,
+                // * and never takes the terminal, since it is in the background
+                if shell.jobs.has_job_control() {
+                    let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
+                }
+                shell.jobs.leave_job_control();
+
+                let result = self.run_stages(shell, pipeline, stages, source, streams, false);
+                if let Err(error) = &result {
+                    shell.report_error(error);
+                }
+
+                shell.set_status(&result);
+                std::process::exit(shell.last_exit_code);
+            }
+            ForkResult::Parent { child } => {
+                // * The process group is set by both processes, since either may run first
+                if shell.jobs.has_job_control() {
+                    let _ = setpgid(child, child);
+                }
+
+                let command = source.get(pipeline.span.start..pipeline.span.end);
+                let job = Job::new(vec![child.as_raw() as u32], command.unwrap_or(source));
+                add_background_job(shell, job);
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes an error from resolving a command, such as one which could not be found, to the error
+    /// stream it was redirected to, if any
+    // * The redirections are still applied, since POSIX performs them before the command is run,
+    // * so a file such as the target of '> out' is created even if the command is not found
+    fn report_unresolved(
+        &self,
+        shell: &ShellState,
+        command: &Command,
+        error: &mut RushError,
+        streams: &Streams,
+    ) {
+        let Command::Simple(command) = command else {
+            return;
+        };
+
+        let (Ok(stdout), Ok(stderr)) = (streams.stdout.try_clone(), streams.stderr.try_clone())
+        else {
+            return;
+        };
+
+        let streams = Streams {
+            stdin: Input::Inherit,
+            stdout,
+            stderr,
+        };
+        if let Ok(mut streams) = self.redirect(shell, &command.redirections, streams) {
+            report_to_stage(shell, error, &mut streams.stderr);
+        }
+    }
+
+    /// Runs a stage of a pipeline which runs inside the shell, with its redirections already applied
+    fn run_stage(
+        &self,
+        shell: &mut ShellState,
+        stage: &Stage,
+        source: &str,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        match stage {
+            // * Executables are spawned before any stage inside the shell is run
+            Stage::Executable(..) => Ok(()),
+            Stage::Builtin(builtin, command, arguments) => {
+                self.with_assignments(shell, command, |shell| {
+                    builtin.run(
+                        shell,
+                        arguments.iter().map(String::as_str).collect(),
+                        streams,
+                    )
+                })
+            }
+            Stage::Function(function, command, arguments) => {
+                self.with_assignments(shell, command, |shell| {
+                    self.run_function(shell, function, arguments, streams)
+                })
+            }
+            Stage::Subshell(subshell) => self.run_subshell(shell, subshell, source, streams),
+            Stage::Compound(compound) => self.run_compound(shell, compound, source, streams),
+            Stage::Definition(definition) => {
+                let function = Function {
+                    body: Arc::new(definition.body.clone()),
+                    source: Arc::from(source),
+                };
+                shell.functions.insert(definition.name.clone(), function);
+                Ok(())
+            }
+            Stage::Assignments(command) => {
+                // * Each assignment can use the variables set by the ones before it
+                for assignment in &command.assignments {
+                    let value = Expander::new(self, shell).expand_word(&assignment.value)?;
+                    shell.environment.set_variable(&assignment.name, &value)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs a builtin or function with the assignments before it, such as 'FOO=1' in 'FOO=1 source-file
+    /// script', set only for as long as it runs
+    fn with_assignments(
+        &self,
+        shell: &mut ShellState,
+        command: &SimpleCommand,
+        run: impl FnOnce(&mut ShellState) -> Result<()>,
+    ) -> Result<()> {
+        let mut saved = Vec::new();
+        let mut result = Ok(());
+        for assignment in &command.assignments {
+            // * Each assignment can use the variables set by the ones before it
+            let variable = Expander::new(self, shell)
+                .expand_word(&assignment.value)
+                .and_then(|value| {
+                    shell
+                        .environment
+                        .set_temporary_variable(&assignment.name, &value)
+                });
+            match variable {
+                Ok(variable) => saved.push(variable),
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+
+        if result.is_ok() {
+            result = run(shell);
+        }
+
+        // * The variables are restored in reverse, so that one assigned twice ends up as it started
+        for variable in saved.into_iter().rev() {
+            shell.environment.restore_variable(variable);
+        }
+
+        result
+    }
+
+    /// Runs a list in a copy of the shell state, so that any changes it makes are discarded
+    fn run_subshell(
+        &self,
+        shell: &ShellState,
+        subshell: &Subshell,
+        source: &str,
+        streams: &mut Streams,
     ) -> Result<()> {
-        // If the command resides in the Dispatcher (generally means it is a builtin) run it
-        if let Some(command) = self.resolve(command_name) {
-            command.run(shell, command_args)
+        let mut subshell_state = shell.clone();
+        let result = self.run_list(&mut subshell_state, &subshell.body, source, streams);
+
+        // * The shell process is shared with the subshell, so its working directory has to be
+        // * restored in case the subshell changed it
+        shell.environment.sync_process_env()?;
+        result
+    }
+
+    /// Runs a compound command in the shell, so that any changes it makes are kept
+    fn run_compound(
+        &self,
+        shell: &mut ShellState,
+        compound: &CompoundCommand,
+        source: &str,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        match &compound.kind {
+            CompoundKind::Group(list) => self.run_list(shell, list, source, streams),
+            CompoundKind::If {
+                branches,
+                otherwise,
+            } => {
+                for branch in branches {
+                    if self.run_condition(shell, &branch.condition, source, streams) {
+                        return self.run_list(shell, &branch.body, source, streams);
+                    }
+
+                    if shell.should_exit {
+                        return Ok(());
+                    }
+                }
+
+                match otherwise {
+                    Some(list) => self.run_list(shell, list, source, streams),
+                    None => Ok(()),
+                }
+            }
+            CompoundKind::While {
+                condition,
+                body,
+                until,
+            } => {
+                let mut result = Ok(());
+                while !shell.should_exit
+                    && self.run_condition(shell, condition, source, streams) != *until
+                {
+                    // * Only the status of the last iteration is returned, as with a list
+                    if let Err(error) = std::mem::replace(&mut result, Ok(())) {
+                        shell.report_error(&error);
+                    }
+
+                    result = self.run_list(shell, body, source, streams);
+                }
+
+                result
+            }
+            CompoundKind::For {
+                variable,
+                words,
+                body,
+            } => {
+                let values = match words {
+                    Some(words) => Expander::new(self, shell).expand_arguments(words)?,
+                    None => shell.arguments.iter().skip(1).cloned().collect(),
+                };
+
+                let mut result = Ok(());
+                for value in values {
+                    if shell.should_exit {
+                        break;
+                    }
+
+                    if let Err(error) = std::mem::replace(&mut result, Ok(())) {
+                        shell.report_error(&error);
+                    }
+
+                    shell.environment.set_variable(variable, &value)?;
+                    result = self.run_list(shell, body, source, streams);
+                }
+
+                result
+            }
+        }
+    }
+
+    /// Runs the condition of an `if` or `while`, returning whether it succeeded
+    // * A failing condition is expected, so its error is not reported, just as with '||'
+    fn run_condition(
+        &self,
+        shell: &mut ShellState,
+        condition: &List,
+        source: &str,
+        streams: &mut Streams,
+    ) -> bool {
+        self.run_list(shell, condition, source, streams).is_ok()
+    }
+
+    /// Runs the body of a function, with the positional arguments set to the arguments it was
+    /// called with
+    fn run_function(
+        &self,
+        shell: &mut ShellState,
+        function: &Function,
+        arguments: &[String],
+        streams: &mut Streams,
+    ) -> Result<()> {
+        // * Only '$1' onwards are set to the arguments, since POSIX leaves '$0' as the name of the
+        // * shell or script
+        let name = shell.arguments.first().cloned().unwrap_or_default();
+        let positional = std::iter::once(name)
+            .chain(arguments.iter().skip(1).cloned())
+            .collect();
+        let previous = std::mem::replace(&mut shell.arguments, positional);
+
+        let result = self.run_compound(shell, &function.body, &function.source, streams);
+
+        shell.arguments = previous;
+        result
+    }
+
+    /// Applies redirections to a set of streams, in the order they were written
+    fn redirect(
+        &self,
+        shell: &ShellState,
+        redirections: &[Redirection],
+        mut streams: Streams,
+    ) -> Result<Streams> {
+        for redirection in redirections {
+            let target = Expander::new(self, shell).expand_word(&redirection.target)?;
+            redirection.apply(&target, &mut streams, shell.config.no_clobber)?;
+        }
+
+        Ok(streams)
+    }
+
+    /// Runs a line in a copy of the shell state and collects everything it writes to its output,
+    /// as is done for a command substitution such as `$(pwd)`
+    pub fn capture_output(&self, shell: &ShellState, line: &str) -> Result<String> {
+        let (result, output) = self.eval_captured(&mut shell.clone(), line)?;
+        shell.environment.sync_process_env()?;
+
+        // * A failed command still substitutes whatever it wrote, since POSIX replaces '$(...)'
+        // * with the output regardless of the exit status
+        if let Err(error) = result {
+            shell.report_error(&error);
+        }
+
+        Ok(output)
+    }
+
+    /// Evaluates a line and collects everything it writes to its output, returning the result of
+    /// the line along with the output
+    pub fn eval_captured(
+        &self,
+        shell: &mut ShellState,
+        line: &str,
+    ) -> Result<(Result<()>, String)> {
+        let list = parse_with_aliases(line, &shell.aliases)?;
+        let (mut reader, writer) =
+            io::pipe().replace_err(|| executable_err!(CouldNotConnectStreams))?;
+
+        // * The output is read on a separate thread, so that a command which writes more than the
+        // * pipe can hold does not block the shell
+        let collector = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            reader.read_to_end(&mut buffer).map(|_| buffer)
+        });
+
+        let mut streams = Streams::new(Input::Inherit, Output::Pipe(writer));
+        let result = self.run_list(shell, &list, line, &mut streams);
+        // * The shell's end of the pipe has to be closed before the collector can finish
+        drop(streams);
+
+        let buffer = collector
+            .join()
+            .ok()
+            .replace_err(|| executable_err!(CouldNotConnectStreams))?
+            .replace_err(|| executable_err!(CouldNotConnectStreams))?;
+        Ok((result, String::from_utf8_lossy(&buffer).into_owned()))
+    }
+
+    /// Resolves a pipeline stage to something which can be run
+    fn resolve_stage<'a>(&'a self, shell: &ShellState, command: &'a Command) -> Result<Stage<'a>> {
+        match command {
+            Command::Subshell(subshell) => Ok(Stage::Subshell(subshell)),
+            Command::Compound(compound) => Ok(Stage::Compound(compound)),
+            Command::Function(definition) => Ok(Stage::Definition(definition)),
+            Command::Simple(command) => {
+                // * The command name is expanded along with its arguments, so a command made up
+                // * only of expansions which produce nothing has no name, and POSIX 2.9.1 says
+                // * that such a command does nothing
+                let arguments = self.expand_command(shell, command)?;
+                match arguments.first().cloned() {
+                    Some(name) => self.resolve_command(shell, command, &name, arguments),
+                    None => Ok(Stage::Assignments(command)),
+                }
+            }
+        }
+    }
+
+    /// Expands the words of a command into its arguments
+    // * The command line of an alias definition, such as 'alias ll = ls -l "$DIR"', is passed on as
+    // * it was written, so that it is expanded each time the alias is used rather than once here
+    fn expand_command(&self, shell: &ShellState, command: &SimpleCommand) -> Result<Vec<String>> {
+        let expander = Expander::new(self, shell);
+        let defines_alias = match command.words.first() {
+            Some(name) => matches!(
+                self.resolve(shell, &name.text()),
+                Some(Resolved::Builtin(builtin)) if builtin.true_name == "edit-aliases"
+            ),
+            None => false,
+        };
+        let definition = command
+            .words
+            .iter()
+            .position(|word| word.text().contains('='));
+
+        match definition {
+            Some(index) if defines_alias => {
+                let mut arguments = expander.expand_arguments(&command.words[..=index])?;
+                arguments.extend(command.words[index + 1..].iter().map(Word::quoted));
+                Ok(arguments)
+            }
+            _ => expander.expand_arguments(&command.words),
+        }
+    }
+
+    /// Resolves a command name to the appropriate builtin or executable
+    fn resolve_command<'a>(
+        &'a self,
+        shell: &ShellState,
+        command: &'a SimpleCommand,
+        command_name: &str,
+        arguments: Vec<String>,
+    ) -> Result<Stage<'a>> {
+        // If the command is a function or resides in the Dispatcher (generally means it is a builtin) use it
+        if let Some(resolved) = self.resolve(shell, command_name) {
+            match resolved {
+                Resolved::Builtin(builtin) => Ok(Stage::Builtin(builtin, command, arguments)),
+                Resolved::Function(function) => Ok(Stage::Function(function, command, arguments)),
+            }
+        } else if let Some(builtin) = self.resolve_script(command_name) {
+            // Rhai scripts are run by the shell itself, rather than as executables
+            let arguments = std::iter::once(builtin.true_name.clone())
+                .chain(arguments)
+                .collect();
+            Ok(Stage::Builtin(builtin, command, arguments))
         } else {
             // If the command is not in the Dispatcher, try to run it as an executable from the PATH
             let path = Path::try_resolve_executable(command_name, shell.environment.PATH());
@@ -103,15 +949,418 @@ impl Dispatcher {
                     if permission_code & 0o111 == 0 {
                         Err(dispatch_err!(CommandNotExecutable: permission_code))
                     } else {
-                        Executable::new(path).run(shell, command_args)
+                        // * Assignments before an executable only apply to that executable's environment
+                        let expander = Expander::new(self, shell);
+                        let variables = command
+                            .assignments
+                            .iter()
+                            .map(|assignment| {
+                                expander
+                                    .expand_word(&assignment.value)
+                                    .map(|value| (assignment.name.clone(), value))
+                            })
+                            .collect::<Result<Vec<(String, String)>>>()?;
+                        let executable = Executable::new(path)
+                            .with_environment(&shell.environment)
+                            .with_variables(variables);
+                        Ok(Stage::Executable(executable, command, arguments))
                     }
                 } else {
                     // If the file cannot be read, return an error
                     Err(dispatch_err!(UnreadableExecutableMetadata: path))
                 }
             } else {
-                Err(dispatch_err!(UnknownCommand: command_name))
+                let suggestion = self.suggest_command(shell, command_name);
+                Err(dispatch_err!(UnknownCommand: command_name, suggestion))
             }
         }
     }
 }
+
+/// A command name which has been found in the shell itself, rather than on the PATH
+enum Resolved<'a> {
+    Builtin(&'a Builtin),
+    Function(Function),
+}
+
+/// A stage of a pipeline which has been resolved to something that can be run
+// * Commands are stored along with their expanded arguments
+enum Stage<'a> {
+    Builtin(&'a Builtin, &'a SimpleCommand, Vec<String>),
+    Function(Function, &'a SimpleCommand, Vec<String>),
+    Executable(Executable, &'a SimpleCommand, Vec<String>),
+    Subshell(&'a Subshell),
+    Compound(&'a CompoundCommand),
+    /// A function definition, which adds the function to the shell when it runs
+    Definition(&'a FunctionDefinition),
+    /// A command without a name, which only assigns variables and applies redirections
+    Assignments(&'a SimpleCommand),
+}
+
+impl Stage<'_> {
+    /// Whether the stage runs inside the shell process rather than as a child process
+    fn runs_in_shell(&self) -> bool {
+        !matches!(self, Self::Executable(..))
+    }
+}
+
+/// Adds a job which was started in the background to the job table, and announces it
+fn add_background_job(shell: &mut ShellState, job: Job) {
+    let pgid = job.pgid;
+    let id = shell.jobs.add(job);
+    shell.jobs.announce(format!("[{}] {}", id, pgid));
+}
+
+/// Writes the error of a stage to its own error stream if that was redirected, so that '2>' and
+/// '2>&1' apply to errors from the shell as well as to the command's own output
+// * Errors which go to the shell's own error stream are left for the caller to report, which may
+// * add where the command came from
+fn report_to_stage(shell: &ShellState, error: &mut RushError, stderr: &mut Output) {
+    if !matches!(stderr, Output::Stderr) {
+        shell.report_error_to(error, stderr);
+        error.mark_reported();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::state::{AliasTable, TestDirectory};
+
+    /// Runs a line in a shell state, returning its result and what it printed
+    fn run(shell: &mut ShellState, line: &str) -> (Result<()>, String) {
+        Dispatcher::default().eval_captured(shell, line).unwrap()
+    }
+
+    #[test]
+    fn collects_output_of_executables_into_builtins() {
+        let directory = TestDirectory::new();
+        let file = directory.join("numbers.txt");
+        // * This is far more than a pipe can hold, so it only finishes if the output is drained
+        let numbers: String = (0..100_000).map(|number| format!("{}\n", number)).collect();
+        fs_err::write(&file, &numbers).unwrap();
+
+        // * 'cat' is an alias of 'read-file', so 'sh' is used to run an executable in between
+        let line = format!("read-file {} | sh -c cat | read-file", file.display());
+        let (result, output) = run(&mut ShellState::for_tests(), &line);
+        assert!(result.is_ok());
+        assert_eq!(output, numbers);
+    }
+
+    #[test]
+    fn applies_assignments_before_builtins_for_one_command() {
+        let mut shell = ShellState::for_tests();
+        let line = "function remember() { set SEEN=$VALUE; }; VALUE=first remember";
+        assert!(run(&mut shell, line).0.is_ok());
+        assert_eq!(
+            shell.environment.get_variable("SEEN").as_deref(),
+            Some("first")
+        );
+        assert_eq!(shell.environment.get_variable("VALUE"), None);
+
+        let (result, output) = run(&mut shell, "VALUE=second sh -c 'echo $VALUE'");
+        assert!(result.is_ok());
+        assert_eq!(output, "second\n");
+    }
+
+    #[test]
+    fn restores_variables_assigned_before_builtins() {
+        let mut shell = ShellState::for_tests();
+        let line = "set VALUE=kept; VALUE=changed set OTHER=$VALUE";
+        assert!(run(&mut shell, line).0.is_ok());
+        assert_eq!(
+            shell.environment.get_variable("VALUE").as_deref(),
+            Some("kept")
+        );
+        assert_eq!(
+            shell.environment.get_variable("OTHER").as_deref(),
+            Some("kept")
+        );
+        assert!(!shell
+            .environment
+            .exported_variables()
+            .iter()
+            .any(|(name, _)| name == "VALUE"));
+    }
+
+    #[test]
+    fn writes_errors_to_redirected_error_streams() {
+        let mut shell = ShellState::for_tests();
+        shell.config.show_errors = true;
+        let directory = TestDirectory::new();
+        let file = directory.join("errors.txt");
+
+        let line = format!("rush-test-missing 2> {}", file.display());
+        let error = run(&mut shell, &line).0.err().unwrap();
+        assert!(error.is_reported());
+        let written = fs_err::read_to_string(&file).unwrap();
+        assert!(written.contains("'rush-test-missing' could not be found"));
+
+        let (result, output) = run(&mut shell, "change-directory /rush-test-missing 2>&1");
+        assert!(result.is_err());
+        assert!(output.contains("rush-test-missing"));
+
+        let (result, output) = run(&mut shell, "{ set 1X=2; } 2>&1 | read-file");
+        assert!(result.is_ok());
+        assert!(output.contains("1X"));
+    }
+
+    #[test]
+    fn runs_list_items_depending_on_the_previous_result() {
+        let mut shell = ShellState::for_tests();
+        let line = "false && echo skipped; false || echo recovered; true && echo continued; true || echo no";
+        let (result, output) = run(&mut shell, line);
+        assert!(result.is_ok());
+        assert_eq!(output, "recovered\ncontinued\n");
+        assert_eq!(shell.last_exit_code, 0);
+
+        let (result, output) = run(&mut shell, "echo first; false");
+        assert_eq!(output, "first\n");
+        assert_eq!(result.err().unwrap().exit_code(), 1);
+    }
+
+    #[test]
+    fn runs_background_pipelines_as_jobs() {
+        let mut shell = ShellState::for_tests();
+        assert!(run(&mut shell, "sh -c 'exit 3' &").0.is_ok());
+        assert_eq!(shell.jobs.jobs().len(), 1);
+        assert_eq!(shell.jobs.jobs()[0].command, "sh -c 'exit 3'");
+
+        let (result, _) = run(&mut shell, "wait-for-job %1");
+        assert_eq!(result.err().unwrap().exit_code(), 3);
+        assert!(shell.jobs.jobs().is_empty());
+    }
+
+    #[test]
+    fn gives_pipelines_the_status_of_their_last_stage() {
+        let mut shell = ShellState::for_tests();
+        assert!(run(&mut shell, "false | true").0.is_ok());
+
+        let (result, _) = run(&mut shell, "true | false");
+        assert_eq!(result.err().unwrap().exit_code(), 1);
+    }
+
+    /// Runs lines as a script in an existing shell state, returning the result of the last command
+    fn run_lines(shell: &mut ShellState, lines: &[&str]) -> Result<()> {
+        let lines = lines.iter().map(|line| line.to_string());
+        Dispatcher::default().eval_lines(shell, "script", lines, &mut Streams::default())
+    }
+
+    #[test]
+    fn runs_scripts_line_by_line() {
+        let mut shell = ShellState::for_tests();
+        let lines = [
+            "# Comments and blank lines are skipped",
+            "",
+            "set FIRST=1",
+            "for word in a b; do",
+            "  set LAST=$word",
+            "done",
+            "unknown-rush-test-command",
+            "set SECOND=2",
+        ];
+
+        assert!(run_lines(&mut shell, &lines).is_ok());
+        assert_eq!(shell.last_exit_code, 0);
+        let environment = &shell.environment;
+        assert_eq!(environment.get_variable("FIRST").as_deref(), Some("1"));
+        assert_eq!(environment.get_variable("LAST").as_deref(), Some("b"));
+        assert_eq!(environment.get_variable("SECOND").as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn reports_the_line_which_failed_commands_started_on() {
+        let mut shell = ShellState::for_tests();
+        let lines = [
+            "set A=1",
+            "",
+            "if true; then",
+            "  unknown-rush-test-command",
+            "fi",
+        ];
+
+        let error = run_lines(&mut shell, &lines).err().unwrap();
+        assert_eq!(error.name(), "UnknownCommand");
+        assert!(error.to_string().starts_with("script:3: "));
+        assert_eq!(shell.last_exit_code, 127);
+    }
+
+    #[test]
+    fn stops_scripts_once_they_exit() {
+        let mut shell = ShellState::for_tests();
+        let lines = ["set BEFORE=1", "false; exit 3", "set AFTER=1"];
+
+        assert!(run_lines(&mut shell, &lines).is_ok());
+        assert!(shell.should_exit);
+        assert_eq!(shell.exit_code, 3);
+        assert!(shell.environment.get_variable("BEFORE").is_some());
+        assert!(shell.environment.get_variable("AFTER").is_none());
+    }
+
+    #[test]
+    fn runs_the_first_branch_whose_condition_succeeds() {
+        let mut shell = ShellState::for_tests();
+        let line = "if false; then echo a; elif true; then echo b; else echo c; fi";
+        let (result, output) = run(&mut shell, line);
+        assert!(result.is_ok());
+        assert_eq!(output, "b\n");
+
+        let line = "if false; then echo a; elif false; then echo b; else echo c; fi";
+        assert_eq!(run(&mut shell, line).1, "c\n");
+    }
+
+    #[test]
+    fn repeats_loops_over_conditions_and_words() {
+        let mut shell = ShellState::for_tests();
+        let line = "while [ \"$X\" != xxx ]; do set X=${X}x; done";
+        assert!(run(&mut shell, line).0.is_ok());
+        let line = "until [ \"$Y\" = yy ]; do set Y=${Y}y; done";
+        assert!(run(&mut shell, line).0.is_ok());
+        assert_eq!(shell.environment.get_variable("X").as_deref(), Some("xxx"));
+        assert_eq!(shell.environment.get_variable("Y").as_deref(), Some("yy"));
+
+        let line = "for word in a {b,c} \"d e\"; do echo $word; done";
+        let (result, output) = run(&mut shell, line);
+        assert!(result.is_ok());
+        assert_eq!(output, "a\nb\nc\nd e\n");
+        assert_eq!(
+            shell.environment.get_variable("word").as_deref(),
+            Some("d e")
+        );
+    }
+
+    #[test]
+    fn calls_functions_with_their_own_arguments() {
+        let mut shell = ShellState::for_tests();
+        shell.arguments = ["rush", "outer"].map(String::from).to_vec();
+
+        let line = "function greet() { echo \"$1 $#\"; }; greet hello there; echo $1";
+        let (result, output) = run(&mut shell, line);
+        assert!(result.is_ok());
+        assert_eq!(output, "hello 2\nouter\n");
+        assert!(shell.functions.contains_key("greet"));
+
+        // * A function takes the status of the last command it ran
+        let (result, _) = run(&mut shell, "function fail() { true; false; }; fail");
+        assert!(result.is_err());
+        assert_eq!(shell.last_exit_code, 1);
+    }
+
+    #[test]
+    fn suggests_commands_with_similar_names() {
+        let mut shell = ShellState::for_tests();
+        let aliases = [
+            ("rush-test-alias", "ls"),
+            ("rush-test-missing", "rush-test-missing -a"),
+        ];
+        let aliases = aliases.map(|(name, command)| (name.to_owned(), command.to_owned()));
+        shell.aliases = AliasTable::new(&aliases.into(), &shell.paths.config_file);
+        let line = "function rush-test-function() { true; }";
+        assert!(run(&mut shell, line).0.is_ok());
+
+        let dispatcher = Dispatcher::default();
+        let suggest = |name| dispatcher.suggest_command(&shell, name);
+        assert_eq!(
+            suggest("chnage-directory").as_deref(),
+            Some("change-directory")
+        );
+        assert_eq!(suggest("lsit-jobs").as_deref(), Some("list-jobs"));
+        assert_eq!(
+            suggest("rush-test-alais").as_deref(),
+            Some("rush-test-alias")
+        );
+        assert_eq!(
+            suggest("rush-test-funtcion").as_deref(),
+            Some("rush-test-function")
+        );
+        assert_eq!(suggest("rush-test-unrelated"), None);
+        // * An alias which runs a missing command of the same name is not suggested for it
+        assert_eq!(suggest("rush-test-missing"), None);
+    }
+
+    #[test]
+    fn includes_suggestions_in_unknown_command_errors() {
+        let mut shell = ShellState::for_tests();
+        let error = run(&mut shell, "exitt 3").0.err().unwrap();
+        assert_eq!(error.name(), "UnknownCommand");
+        assert_eq!(
+            error.to_string(),
+            "Command 'exitt' could not be found, did you mean 'exit'?"
+        );
+        assert_eq!(shell.last_exit_code, 127);
+    }
+
+    #[test]
+    fn sets_the_status_of_failed_commands() {
+        let mut shell = ShellState::for_tests();
+        let directory = TestDirectory::new();
+        let file = directory.join("not-executable");
+        fs_err::write(&file, "").unwrap();
+
+        let lines = [
+            ("sh -c 'exit 42'".to_owned(), 42),
+            ("rush-test-unknown".to_owned(), 127),
+            (file.display().to_string(), 126),
+            ("list-directory --rush-test-unknown".to_owned(), 2),
+            ("set 1x=2".to_owned(), 1),
+            ("true".to_owned(), 0),
+        ];
+        for (line, status) in lines {
+            let (result, _) = run(&mut shell, &line);
+            assert_eq!(result.is_err(), status != 0, "{}", line);
+            assert_eq!(shell.last_exit_code, status, "{}", line);
+        }
+    }
+
+    #[test]
+    fn reports_the_file_and_line_of_errors_in_files() {
+        let mut shell = ShellState::for_tests();
+        let directory = TestDirectory::new();
+        let file = directory.join("script.rush");
+        fs_err::write(&file, "# Comment\ntrue\nrush-test-unknown\n").unwrap();
+
+        let dispatcher = Dispatcher::default();
+        let result = dispatcher.eval_file(&mut shell, &file, &mut Streams::default());
+        let error = result.err().unwrap();
+        assert_eq!(error.name(), "UnknownCommand");
+        let prefix = format!("{}:3: ", file.display());
+        assert!(error.to_string().starts_with(&prefix), "{}", error);
+
+        let missing = directory.join("missing.rush");
+        let result = dispatcher.eval_file(&mut shell, &missing, &mut Streams::default());
+        assert_eq!(result.err().unwrap().name(), "CouldNotReadFile");
+    }
+
+    #[test]
+    fn keeps_the_command_lines_of_aliases_as_they_were_written() {
+        let mut shell = ShellState::for_tests();
+        let line = "alias gs = echo \"a   b\" $HOME 'it'\\''s'; alias g2=echo $HOME";
+        assert!(run(&mut shell, line).0.is_ok());
+        assert_eq!(
+            shell.aliases.get("gs"),
+            Some("echo \"a   b\" $HOME 'it'\\''s'")
+        );
+        assert_eq!(shell.aliases.get("g2"), Some("echo $HOME"));
+        assert!(!shell.paths.config_file.exists());
+
+        // * Only the aliases which are saved are written to the config file
+        assert!(run(&mut shell, "alias --save g2").0.is_ok());
+        let config = fs_err::read_to_string(&shell.paths.config_file).unwrap();
+        assert_eq!(config, "[aliases]\ng2 = \"echo $HOME\"\n");
+
+        shell.environment.HOME = PathBuf::from("/rush-test-home");
+        let (result, output) = run(&mut shell, "gs; g2");
+        assert!(result.is_ok());
+        assert_eq!(output, "a   b /rush-test-home it's\n/rush-test-home\n");
+
+        // * Warnings are written without styles when they are not written to a terminal
+        let (result, output) = run(&mut shell, "alias list-jobs = echo 2>&1");
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            "Warning: alias 'list-jobs' shadows the builtin 'list-jobs'\n"
+        );
+    }
+}
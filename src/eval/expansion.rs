@@ -0,0 +1,912 @@
+use glob::{MatchOptions, Pattern};
+use nix::unistd::User;
+
+use super::ast::{Word, WordPart};
+use super::dispatcher::Dispatcher;
+use super::symbols::{
+    BACKSLASH, BACKTICK, DOLLAR, DOUBLE_QUOTE, LEFT_BRACE, RIGHT_BRACE, SINGLE_QUOTE,
+};
+use super::tokenizer::{is_substitution, substitution_length};
+use crate::errors::{Handle, Result};
+use crate::state::ShellState;
+
+/// The characters which make an unquoted argument a glob pattern
+const GLOB_CHARACTERS: [char; 3] = ['*', '?', '['];
+
+/// An argument produced by expansion, along with the glob pattern it should be matched against
+struct Field {
+    text: String,
+    /// The argument with any quoted text escaped, if it contains unquoted glob characters
+    pattern: Option<String>,
+}
+
+/// Collects the arguments produced by expanding one or more words
+#[derive(Default)]
+struct Fields {
+    fields: Vec<Field>,
+    current: String,
+    /// Whether the current argument has been started, even if it is still empty (as in `""`)
+    started: bool,
+    /// The current argument as a glob pattern, in which only unquoted characters are special
+    pattern: String,
+    is_pattern: bool,
+}
+
+impl Fields {
+    /// Adds text to the current argument without splitting it
+    fn push_str(&mut self, text: &str) {
+        self.current.push_str(text);
+        self.pattern.push_str(&Pattern::escape(text));
+        self.started = true;
+    }
+
+    /// Adds text to the current argument, starting a new argument at each run of whitespace
+    // * This is only done for the results of unquoted expansions, since the tokenizer has already
+    // * split any whitespace which was written directly into the line
+    fn push_split(&mut self, text: &str) {
+        for character in text.chars() {
+            if character.is_whitespace() {
+                self.finish_field();
+            } else {
+                self.current.push(character);
+                self.pattern.push(character);
+                self.is_pattern |= GLOB_CHARACTERS.contains(&character);
+                self.started = true;
+            }
+        }
+    }
+
+    /// Ends the current argument, if one has been started
+    fn finish_field(&mut self) {
+        let pattern = std::mem::take(&mut self.pattern);
+        if self.started {
+            self.fields.push(Field {
+                text: std::mem::take(&mut self.current),
+                pattern: self.is_pattern.then_some(pattern),
+            });
+            self.started = false;
+        }
+
+        self.is_pattern = false;
+    }
+}
+
+/// Expands variables and command substitutions in words, using the state of the shell
+// * Command substitutions are run by the dispatcher, in a copy of the shell state
+pub struct Expander<'a> {
+    dispatcher: &'a Dispatcher,
+    shell: &'a ShellState,
+}
+
+impl<'a> Expander<'a> {
+    pub fn new(dispatcher: &'a Dispatcher, shell: &'a ShellState) -> Self {
+        Self { dispatcher, shell }
+    }
+
+    /// Expands each word into the arguments it produces
+    // * The results of unquoted expansions are split into separate arguments, so one word may
+    // * produce several arguments, or none at all if it expands to nothing
+    // * Arguments containing unquoted glob characters are then replaced by the matching paths
+    pub fn expand_arguments(&self, words: &[Word]) -> Result<Vec<String>> {
+        let mut fields = Fields::default();
+        for word in words.iter().flat_map(expand_braces) {
+            for (index, part) in word.parts.iter().enumerate() {
+                match part {
+                    WordPart::Unquoted(text) => {
+                        let text = match index {
+                            0 => self.expand_tilde(text, word.parts.len() == 1, &mut fields),
+                            _ => text,
+                        };
+
+                        self.expand_text(text, &mut fields, true)?
+                    }
+                    WordPart::DoubleQuoted(text) => {
+                        // * Quotes always produce an argument, even if nothing inside them does
+                        fields.push_str("");
+                        self.expand_text(text, &mut fields, false)?
+                    }
+                    WordPart::Literal(text) => fields.push_str(text),
+                }
+            }
+
+            fields.finish_field();
+        }
+
+        let mut arguments = Vec::new();
+        for field in fields.fields {
+            match field.pattern {
+                Some(pattern) => arguments.extend(self.expand_glob(&pattern, field.text)?),
+                None => arguments.push(field.text),
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    /// Expands a word into a single string, without splitting it
+    // * This is used where exactly one value is expected, such as in assignments and redirections
+    pub fn expand_word(&self, word: &Word) -> Result<String> {
+        let mut fields = Fields::default();
+        for (index, part) in word.parts.iter().enumerate() {
+            match part {
+                WordPart::Unquoted(text) if index == 0 => {
+                    let text = self.expand_tilde(text, word.parts.len() == 1, &mut fields);
+                    self.expand_text(text, &mut fields, false)?
+                }
+                WordPart::Unquoted(text) | WordPart::DoubleQuoted(text) => {
+                    self.expand_text(text, &mut fields, false)?
+                }
+                WordPart::Literal(text) => fields.push_str(text),
+            }
+        }
+
+        // * Anything which produced several arguments, such as "$@", is joined back together
+        fields.finish_field();
+        let texts: Vec<String> = fields.fields.into_iter().map(|field| field.text).collect();
+        Ok(texts.join(" "))
+    }
+
+    /// Expands a '~' at the start of a word into a directory, returning the rest of the text
+    // * '~' is the home directory, '~user' is the home directory of another user, '~+' is the CWD,
+    // * and '~-' is the previous directory in the back history, as used by 'back'
+    // * The prefix runs up to the first '/', and nothing is expanded if any of it was quoted
+    fn expand_tilde<'t>(&self, text: &'t str, whole_word: bool, fields: &mut Fields) -> &'t str {
+        let Some(rest) = text.strip_prefix('~') else {
+            return text;
+        };
+
+        let (prefix, remaining) = match rest.find('/') {
+            Some(slash) => rest.split_at(slash),
+            None if whole_word => (rest, ""),
+            None => return text,
+        };
+
+        let environment = &self.shell.environment;
+        let directory = match prefix {
+            "" => Some(environment.HOME.clone()),
+            "+" => Some(environment.CWD().path().clone()),
+            "-" => environment.last_directory().map(|path| path.path().clone()),
+            user => User::from_name(user).ok().flatten().map(|user| user.dir),
+        };
+
+        // * A prefix which names no user, such as '~nosuchuser', is left as it is (POSIX 2.6.1)
+        match directory {
+            Some(directory) => {
+                fields.push_str(&directory.to_string_lossy());
+                remaining
+            }
+            None => text,
+        }
+    }
+
+    /// Expands any variables and command substitutions in a section of text,
+    /// adding the result to the arguments
+    fn expand_text(&self, text: &str, fields: &mut Fields, split: bool) -> Result<()> {
+        let mut remaining = text;
+        while let Some(character) = remaining.chars().next() {
+            if is_substitution(remaining) {
+                let length = substitution_length(remaining)
+                    .replace_err(|| dispatch_err!(InvalidExpansion: remaining))?;
+                let output = self.substitute(&remaining[..length])?;
+                push_expansion(fields, &output, split);
+                remaining = &remaining[length..];
+                continue;
+            }
+
+            remaining = &remaining[character.len_utf8()..];
+            // * Unquoted text only contains whitespace inside a braced expansion,
+            // * which is split as well
+            if character != DOLLAR {
+                push_expansion(fields, &character.to_string(), split);
+                continue;
+            }
+
+            match remaining.chars().next() {
+                Some('?') => {
+                    remaining = &remaining[1..];
+                    fields.push_str(&self.shell.last_exit_code.to_string());
+                }
+                Some(LEFT_BRACE) => {
+                    remaining = self.expand_braced_variable(&remaining[1..], fields, split)?;
+                }
+                Some(next) if next.is_ascii_digit() => {
+                    remaining = &remaining[1..];
+                    let value = self.variable(&next.to_string());
+                    push_expansion(fields, &value, split);
+                }
+                Some('#') => {
+                    remaining = &remaining[1..];
+                    let count = self.shell.arguments.len().saturating_sub(1);
+                    fields.push_str(&count.to_string());
+                }
+                Some(next @ ('@' | '*')) => {
+                    remaining = &remaining[1..];
+                    let arguments = self.shell.arguments.get(1..).unwrap_or_default();
+                    match (next, split) {
+                        // * Inside double quotes, "$@" produces one argument for each positional
+                        // * argument, while "$*" joins them into one
+                        ('@', false) => {
+                            for (index, argument) in arguments.iter().enumerate() {
+                                if index > 0 {
+                                    fields.finish_field();
+                                }
+
+                                fields.push_str(argument);
+                            }
+                        }
+                        _ => push_expansion(fields, &arguments.join(" "), split),
+                    }
+                }
+                Some(next) if is_name_start(next) => {
+                    let length = name_length(remaining);
+                    let value = self.variable(&remaining[..length]);
+                    push_expansion(fields, &value, split);
+                    remaining = &remaining[length..];
+                }
+                // * A '$' followed by a space, the end of the word or any other character which
+                // * cannot start an expansion is used literally
+                _ => fields.push_str(&character.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands a variable of the form `${NAME}` or `${NAME:-default}`, given the text after the
+    /// opening '${', and returns the text after the closing '}'
+    fn expand_braced_variable<'t>(
+        &self,
+        text: &'t str,
+        fields: &mut Fields,
+        split: bool,
+    ) -> Result<&'t str> {
+        // The whole expansion is found first, so that it can be reported if it is invalid
+        let mut depth = 0;
+        let closing = text.char_indices().find(|&(_, character)| {
+            match character {
+                LEFT_BRACE => depth += 1,
+                RIGHT_BRACE if depth == 0 => return true,
+                RIGHT_BRACE => depth -= 1,
+                _ => (),
+            }
+
+            false
+        });
+
+        let Some((closing, _)) = closing else {
+            return Err(dispatch_err!(InvalidExpansion: format!("${{{}", text)));
+        };
+
+        let body = &text[..closing];
+        let invalid = || dispatch_err!(InvalidExpansion: format!("${{{}}}", body));
+        let length = match body.chars().next() {
+            // * Positional arguments past '$9' can only be used in braces, as in '${10}'
+            Some(first) if first.is_ascii_digit() => body
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(body.len()),
+            Some(first) if is_name_start(first) => name_length(body),
+            _ => return Err(invalid()),
+        };
+
+        let (name, rest) = body.split_at(length);
+        let value = self.variable(name);
+        match rest.strip_prefix(":-") {
+            // * The default is only used if the variable is unset or empty
+            Some(default) if value.is_empty() => self.expand_default(default, fields, split)?,
+            Some(_) => push_expansion(fields, &value, split),
+            None if rest.is_empty() => push_expansion(fields, &value, split),
+            None => return Err(invalid()),
+        }
+
+        Ok(&text[closing + RIGHT_BRACE.len_utf8()..])
+    }
+
+    /// Expands the default value of a braced variable
+    // * Outside of double quotes, the default can contain quotes of its own, as in '${NAME:-"a b"}'
+    fn expand_default(&self, default: &str, fields: &mut Fields, split: bool) -> Result<()> {
+        if !split {
+            return self.expand_text(default, fields, false);
+        }
+
+        let mut characters = default.chars().peekable();
+        while let Some(&character) = characters.peek() {
+            match character {
+                SINGLE_QUOTE | DOUBLE_QUOTE => {
+                    characters.next();
+                    let quoted: String = characters
+                        .by_ref()
+                        .take_while(|&c| c != character)
+                        .collect();
+                    match character {
+                        SINGLE_QUOTE => fields.push_str(&quoted),
+                        _ => {
+                            fields.push_str("");
+                            self.expand_text(&quoted, fields, false)?
+                        }
+                    }
+                }
+                _ => {
+                    let mut unquoted = String::new();
+                    while let Some(c) =
+                        characters.next_if(|&c| c != SINGLE_QUOTE && c != DOUBLE_QUOTE)
+                    {
+                        unquoted.push(c);
+                    }
+
+                    self.expand_text(&unquoted, fields, true)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs a command substitution such as '$(pwd)' or '`pwd`', returning its output
+    fn substitute(&self, substitution: &str) -> Result<String> {
+        let command = match substitution.strip_prefix(BACKTICK) {
+            // * Inside backticks, a backslash escapes a backtick, '$', or another backslash
+            Some(inner) => unescape_backticks(&inner[..inner.len() - BACKTICK.len_utf8()]),
+            None => substitution[2..substitution.len() - 1].to_owned(),
+        };
+
+        let output = self.dispatcher.capture_output(self.shell, &command)?;
+        // * Every trailing newline is removed from the output, but newlines inside it are kept
+        // * (POSIX 2.6.3)
+        Ok(output.trim_end_matches('\n').to_owned())
+    }
+
+    /// Matches a glob pattern against the filesystem, relative to the CWD, returning the matching
+    /// paths in sorted order
+    // * If nothing matches, the argument is either used literally (as POSIX requires)
+    // * or reported as an error, depending on the configuration
+    fn expand_glob(&self, pattern: &str, text: String) -> Result<Vec<String>> {
+        let cwd = self.shell.environment.CWD().path();
+        let relative = !pattern.starts_with('/');
+        let full_pattern = match relative {
+            true => format!("{}/{}", Pattern::escape(&cwd.to_string_lossy()), pattern),
+            false => pattern.to_owned(),
+        };
+
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            // * Hidden files are only matched if the pattern starts with a '.' itself
+            require_literal_leading_dot: true,
+        };
+
+        // An invalid pattern, such as an unclosed '[', is not a pattern at all
+        let (Ok(matcher), Ok(paths)) = (
+            Pattern::new(&full_pattern),
+            // ? The directory walk skips every hidden file when told to require a literal '.',
+            // ? so the paths are checked against the pattern again below
+            glob::glob_with(
+                &full_pattern,
+                MatchOptions {
+                    require_literal_leading_dot: false,
+                    ..options
+                },
+            ),
+        ) else {
+            return Ok(vec![text]);
+        };
+
+        // Directories which cannot be read are skipped rather than reported
+        let mut matches: Vec<String> = paths
+            .filter_map(|path| path.ok())
+            .filter(|path| matcher.matches_path_with(path, options))
+            // * The '.' and '..' entries are never matched, even by '.*', as with bash's
+            // * 'globskipdots' option, which is on by default
+            .filter(|path| !matches!(path.to_string_lossy().rsplit('/').next(), Some("." | "..")))
+            .map(|path| match relative {
+                true => path.strip_prefix(cwd).unwrap_or(&path).to_path_buf(),
+                false => path,
+            })
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+
+        if matches.is_empty() {
+            if self.shell.config.no_match_error {
+                return Err(dispatch_err!(NoGlobMatches: text));
+            }
+
+            matches.push(text);
+        }
+
+        matches.sort();
+        Ok(matches)
+    }
+
+    /// Gets the value of a variable or positional argument, or an empty string if it is not set
+    fn variable(&self, name: &str) -> String {
+        if let Ok(index) = name.parse::<usize>() {
+            return self.shell.arguments.get(index).cloned().unwrap_or_default();
+        }
+
+        self.shell
+            .environment
+            .get_variable(name)
+            .unwrap_or_default()
+    }
+}
+
+/// Expands the first brace expression in a word, such as 'file.{rs,toml}' or '{1..10}', into one
+/// word for each of its alternatives, and then expands any others in those words
+// ? Only braces in unquoted text are expanded, so an expression cannot contain quoted text yet
+fn expand_braces(word: &Word) -> Vec<Word> {
+    for (index, part) in word.parts.iter().enumerate() {
+        let WordPart::Unquoted(text) = part else {
+            continue;
+        };
+
+        let Some((start, end, alternatives)) = find_brace_expression(text) else {
+            continue;
+        };
+
+        return alternatives
+            .iter()
+            .flat_map(|alternative| {
+                let mut parts = word.parts.clone();
+                let text = format!("{}{}{}", &text[..start], alternative, &text[end..]);
+                parts[index] = WordPart::Unquoted(text);
+                expand_braces(&Word {
+                    parts,
+                    span: word.span,
+                })
+            })
+            .collect();
+    }
+
+    vec![word.clone()]
+}
+
+/// Finds the first brace expression in the text, returning its start and end along with the
+/// alternatives it expands to
+// * Braces which do not form an expression, such as in '{a}' or '${NAME}', are used literally
+fn find_brace_expression(text: &str) -> Option<(usize, usize, Vec<String>)> {
+    let mut position = 0;
+    while let Some(character) = text[position..].chars().next() {
+        let remaining = &text[position..];
+        let skipped = match character {
+            LEFT_BRACE => match group_length(remaining) {
+                Some(length) => {
+                    if let Some(alternatives) = brace_alternatives(&remaining[1..length - 1]) {
+                        return Some((position, position + length, alternatives));
+                    }
+
+                    None
+                }
+                None => None,
+            },
+            _ => nested_length(remaining),
+        };
+
+        position += skipped.unwrap_or(character.len_utf8());
+    }
+
+    None
+}
+
+/// Splits the inside of a brace expression into its alternatives, or generates them from a
+/// sequence such as '1..10', returning `None` if it is not an expression
+fn brace_alternatives(body: &str) -> Option<Vec<String>> {
+    let mut alternatives = Vec::new();
+    let (mut start, mut position) = (0, 0);
+    while let Some(character) = body[position..].chars().next() {
+        let remaining = &body[position..];
+        let skipped = match character {
+            LEFT_BRACE => group_length(remaining),
+            ',' => {
+                alternatives.push(body[start..position].to_owned());
+                start = position + 1;
+                None
+            }
+            _ => nested_length(remaining),
+        };
+
+        position += skipped.unwrap_or(character.len_utf8());
+    }
+
+    if alternatives.is_empty() {
+        return brace_sequence(body);
+    }
+
+    alternatives.push(body[start..].to_owned());
+    Some(alternatives)
+}
+
+/// Generates the values of a sequence of integers or letters, such as '1..10', '01..10', 'a..e',
+/// or '10..0..2', where the optional third value is the increment
+fn brace_sequence(body: &str) -> Option<Vec<String>> {
+    let bounds: Vec<&str> = body.split("..").collect();
+    let (first, last, step) = match bounds[..] {
+        [first, last] => (first, last, 1),
+        [first, last, step] => (first, last, step.parse::<i64>().ok()?.unsigned_abs()),
+        _ => return None,
+    };
+
+    let step = usize::try_from(step).ok()?.max(1);
+    if let (Ok(start), Ok(end)) = (first.parse::<i64>(), last.parse::<i64>()) {
+        // * If either bound has leading zeros, every value is padded to the same width
+        let width = match is_zero_padded(first) || is_zero_padded(last) {
+            true => first.len().max(last.len()),
+            false => 0,
+        };
+
+        let values: Vec<i64> = match start <= end {
+            true => (start..=end).step_by(step).collect(),
+            false => (end..=start).rev().step_by(step).collect(),
+        };
+
+        return Some(
+            values
+                .iter()
+                .map(|value| format!("{:0width$}", value, width = width))
+                .collect(),
+        );
+    }
+
+    let (start, end) = (sequence_letter(first)?, sequence_letter(last)?);
+    let values: Vec<u8> = match start <= end {
+        true => (start..=end).step_by(step).collect(),
+        false => (end..=start).rev().step_by(step).collect(),
+    };
+
+    Some(
+        values
+            .iter()
+            .map(|&value| char::from(value).to_string())
+            .collect(),
+    )
+}
+
+/// Gets the letter used as a bound of a sequence, if the text is a single ASCII letter
+fn sequence_letter(text: &str) -> Option<u8> {
+    match text.as_bytes() {
+        &[letter] if letter.is_ascii_alphabetic() => Some(letter),
+        _ => None,
+    }
+}
+
+/// Whether an integer is written with leading zeros, as in '007' or '-01'
+fn is_zero_padded(text: &str) -> bool {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    digits.len() > 1 && digits.starts_with('0')
+}
+
+/// Gets the length of the braced group at the start of the text, up to and including its
+/// matching '}', or `None` if it is not closed
+fn group_length(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut position = 0;
+    while let Some(character) = text[position..].chars().next() {
+        if let Some(length) = nested_length(&text[position..]) {
+            position += length;
+            continue;
+        }
+
+        position += character.len_utf8();
+        match character {
+            LEFT_BRACE => depth += 1,
+            RIGHT_BRACE if depth == 1 => return Some(position),
+            RIGHT_BRACE => depth -= 1,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Gets the length of the command substitution or braced variable at the start of the text,
+/// since any braces or commas inside them are not part of a brace expression
+fn nested_length(text: &str) -> Option<usize> {
+    if is_substitution(text) {
+        return substitution_length(text);
+    }
+
+    let variable = text.strip_prefix(DOLLAR)?;
+    group_length(variable).map(|length| length + DOLLAR.len_utf8())
+}
+
+/// Adds the value of an expansion to the arguments, splitting it if it was not quoted
+fn push_expansion(fields: &mut Fields, value: &str, split: bool) {
+    match split {
+        true => fields.push_split(value),
+        false => fields.push_str(value),
+    }
+}
+
+/// Removes the backslashes which escape characters inside a backtick substitution
+fn unescape_backticks(text: &str) -> String {
+    let mut command = String::new();
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        match characters.peek() {
+            Some(&next @ (BACKTICK | DOLLAR | BACKSLASH)) if character == BACKSLASH => {
+                command.push(next);
+                characters.next();
+            }
+            _ => command.push(character),
+        }
+    }
+
+    command
+}
+
+/// Gets the length of the variable name at the start of the text, which is made up of letters,
+/// digits, and underscores
+fn name_length(text: &str) -> usize {
+    text.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(text.len())
+}
+
+/// Whether a character can start a variable name (which cannot start with a digit)
+fn is_name_start(character: char) -> bool {
+    character.is_ascii_alphabetic() || character == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::ast::Command;
+    use crate::eval::parser::parse_with_aliases;
+    use crate::state::{AliasTable, TestDirectory};
+
+    /// Expands the words of a command into the arguments which it would be run with
+    fn expand(shell: &ShellState, line: &str) -> Result<Vec<String>> {
+        let list = parse_with_aliases(line, &AliasTable::default())?;
+        let Command::Simple(command) = &list.items[0].pipeline.commands[0] else {
+            panic!("expected a simple command");
+        };
+
+        Expander::new(&Dispatcher::default(), shell).expand_arguments(&command.words)
+    }
+
+    #[test]
+    fn expands_variables_and_the_last_status() {
+        let mut shell = ShellState::for_tests();
+        shell.last_exit_code = 127;
+        let environment = &mut shell.environment;
+        environment.set_variable("WORDS", "a  b c").unwrap();
+        environment.set_variable("EMPTY", "").unwrap();
+
+        assert_eq!(
+            expand(&shell, "echo $? x$?y").unwrap(),
+            ["echo", "127", "x127y"]
+        );
+        assert_eq!(
+            expand(&shell, "echo $WORDS").unwrap(),
+            ["echo", "a", "b", "c"]
+        );
+        assert_eq!(
+            expand(&shell, "echo \"$WORDS\"").unwrap(),
+            ["echo", "a  b c"]
+        );
+        assert_eq!(expand(&shell, "echo '$WORDS'").unwrap(), ["echo", "$WORDS"]);
+        assert_eq!(
+            expand(&shell, "echo $EMPTY $RUSH_TEST_UNSET").unwrap(),
+            ["echo"]
+        );
+        assert_eq!(expand(&shell, "echo \"$EMPTY\"").unwrap(), ["echo", ""]);
+        assert_eq!(
+            expand(&shell, "echo ${WORDS}x").unwrap(),
+            ["echo", "a", "b", "cx"]
+        );
+        assert_eq!(expand(&shell, "echo $ 5$").unwrap(), ["echo", "$", "5$"]);
+    }
+
+    #[test]
+    fn uses_defaults_for_unset_and_empty_variables() {
+        let mut shell = ShellState::for_tests();
+        let environment = &mut shell.environment;
+        environment.set_variable("SET", "value").unwrap();
+        environment.set_variable("EMPTY", "").unwrap();
+
+        let arguments = expand(&shell, "echo ${SET:-d} ${EMPTY:-d} ${RUSH_TEST_UNSET:-d}");
+        assert_eq!(arguments.unwrap(), ["echo", "value", "d", "d"]);
+        let arguments = expand(&shell, "echo ${EMPTY:-\"a b\"} ${EMPTY:-a b}");
+        assert_eq!(arguments.unwrap(), ["echo", "a b", "a", "b"]);
+        let arguments = expand(&shell, "echo ${EMPTY:-${SET}}");
+        assert_eq!(arguments.unwrap(), ["echo", "value"]);
+
+        let error = expand(&shell, "echo ${SET:x}").err().unwrap();
+        assert_eq!(error.name(), "InvalidExpansion");
+        assert!(expand(&shell, "echo ${SET").is_err());
+    }
+
+    #[test]
+    fn expands_positional_arguments() {
+        let mut shell = ShellState::for_tests();
+        shell.arguments = ["script", "one", "two words"].map(String::from).to_vec();
+
+        assert_eq!(
+            expand(&shell, "echo $0 $1 $#").unwrap(),
+            ["echo", "script", "one", "2"]
+        );
+        let arguments = expand(&shell, "echo \"$@\"").unwrap();
+        assert_eq!(arguments, ["echo", "one", "two words"]);
+        let arguments = expand(&shell, "echo \"$*\"").unwrap();
+        assert_eq!(arguments, ["echo", "one two words"]);
+        assert_eq!(
+            expand(&shell, "echo $@").unwrap(),
+            ["echo", "one", "two", "words"]
+        );
+        assert_eq!(
+            expand(&shell, "echo ${2}").unwrap(),
+            ["echo", "two", "words"]
+        );
+    }
+
+    #[test]
+    fn substitutes_the_output_of_commands() {
+        let mut shell = ShellState::for_tests();
+        shell.environment.set_variable("NAME", "value").unwrap();
+
+        let arguments = expand(&shell, "echo $(echo 'a  b') \"$(echo 'a  b')\"").unwrap();
+        assert_eq!(arguments, ["echo", "a", "b", "a  b"]);
+        let arguments = expand(&shell, "echo `echo $NAME` x$(echo $(echo nested))y").unwrap();
+        assert_eq!(arguments, ["echo", "value", "xnestedy"]);
+        let arguments = expand(&shell, "echo `echo \\`echo inner\\``").unwrap();
+        assert_eq!(arguments, ["echo", "inner"]);
+        let arguments = expand(&shell, "echo '$(echo quoted)'").unwrap();
+        assert_eq!(arguments, ["echo", "$(echo quoted)"]);
+    }
+
+    #[test]
+    fn removes_trailing_newlines_from_substitutions() {
+        let shell = ShellState::for_tests();
+
+        let arguments = expand(&shell, "echo \"$(sh -c 'printf \"a\\n\\nb\\n\\n\\n\"')\"");
+        assert_eq!(arguments.unwrap(), ["echo", "a\n\nb"]);
+        let arguments = expand(&shell, "echo x$(sh -c 'printf \"\\n\"')y").unwrap();
+        assert_eq!(arguments, ["echo", "xy"]);
+    }
+
+    #[test]
+    fn expands_globs_into_sorted_paths() {
+        let mut shell = ShellState::for_tests();
+        let test_directory = TestDirectory::new();
+        fs_err::create_dir_all(test_directory.join("sub")).unwrap();
+        for file in ["b.rs", "a.rs", "c.toml", ".hidden.rs", "sub/d.rs"] {
+            fs_err::write(test_directory.join(file), "").unwrap();
+        }
+
+        let directory = test_directory.path().to_string_lossy().into_owned();
+        let path = |file: &str| format!("{directory}/{file}");
+        shell
+            .environment
+            .set_variable("DIRECTORY", &directory)
+            .unwrap();
+
+        let arguments = expand(&shell, "echo $DIRECTORY/*.rs").unwrap();
+        assert_eq!(arguments, ["echo", &path("a.rs"), &path("b.rs")]);
+        let arguments = expand(&shell, "echo $DIRECTORY/?.*").unwrap();
+        assert_eq!(
+            arguments,
+            ["echo", &path("a.rs"), &path("b.rs"), &path("c.toml")]
+        );
+        let arguments = expand(&shell, "echo $DIRECTORY/.*.rs $DIRECTORY/*/*").unwrap();
+        assert_eq!(arguments, ["echo", &path(".hidden.rs"), &path("sub/d.rs")]);
+
+        // Quoted glob characters are matched literally
+        let arguments = expand(&shell, "echo \"$DIRECTORY/*.rs\" $DIRECTORY/'*'.rs").unwrap();
+        assert_eq!(arguments, ["echo", &path("*.rs"), &path("*.rs")]);
+    }
+
+    #[test]
+    fn expands_recursive_globs_outside_hidden_directories() {
+        let mut shell = ShellState::for_tests();
+        let test_directory = TestDirectory::new();
+        fs_err::create_dir_all(test_directory.join("sub/nested")).unwrap();
+        fs_err::create_dir_all(test_directory.join(".hidden/nested")).unwrap();
+        for file in [
+            "a.rs",
+            "b.toml",
+            "sub/c.rs",
+            "sub/nested/d.rs",
+            "sub/nested/e.toml",
+            ".hidden/f.rs",
+            ".hidden/nested/g.rs",
+        ] {
+            fs_err::write(test_directory.join(file), "").unwrap();
+        }
+
+        let directory = test_directory.path().to_string_lossy().into_owned();
+        let path = |file: &str| format!("{directory}/{file}");
+        shell
+            .environment
+            .set_variable("DIRECTORY", &directory)
+            .unwrap();
+
+        let arguments = expand(&shell, "echo $DIRECTORY/**/*.rs").unwrap();
+        assert_eq!(
+            arguments,
+            [
+                "echo",
+                &path("a.rs"),
+                &path("sub/c.rs"),
+                &path("sub/nested/d.rs")
+            ]
+        );
+
+        // Hidden directories are only entered if the pattern names them
+        let arguments = expand(&shell, "echo $DIRECTORY/.hidden/**/*.rs").unwrap();
+        assert_eq!(
+            arguments,
+            ["echo", &path(".hidden/f.rs"), &path(".hidden/nested/g.rs")]
+        );
+    }
+
+    #[test]
+    fn keeps_or_reports_globs_without_matches() {
+        let mut shell = ShellState::for_tests();
+        let test_directory = TestDirectory::new();
+        let directory = test_directory.path().to_string_lossy().into_owned();
+        shell
+            .environment
+            .set_variable("DIRECTORY", &directory)
+            .unwrap();
+
+        let arguments = expand(&shell, "echo $DIRECTORY/*.none [").unwrap();
+        assert_eq!(arguments, ["echo", &format!("{directory}/*.none"), "["]);
+
+        shell.config.no_match_error = true;
+        let error = expand(&shell, "echo $DIRECTORY/*.none").err().unwrap();
+        assert_eq!(error.name(), "NoGlobMatches");
+    }
+
+    #[test]
+    fn expands_brace_alternatives_and_sequences() {
+        let shell = ShellState::for_tests();
+
+        let arguments = expand(&shell, "echo a{b,c}d x{1,{2,3}}").unwrap();
+        assert_eq!(arguments, ["echo", "abd", "acd", "x1", "x2", "x3"]);
+        let arguments = expand(&shell, "echo {1..3} {c..a} {08..10} {0..10..5}").unwrap();
+        let expected = [
+            "1", "2", "3", "c", "b", "a", "08", "09", "10", "0", "5", "10",
+        ];
+        assert_eq!(arguments[1..], expected);
+        let arguments = expand(&shell, "echo {a}{b,c} \"{x,y}\" {1..a} {,z}").unwrap();
+        assert_eq!(arguments, ["echo", "{a}b", "{a}c", "{x,y}", "{1..a}", "z"]);
+    }
+
+    #[test]
+    fn leaves_escaped_characters_unexpanded() {
+        let shell = ShellState::for_tests();
+        let test_directory = TestDirectory::new();
+        fs_err::write(test_directory.join("a.log"), "").unwrap();
+        let directory = test_directory.path().display();
+
+        let line = format!(r"echo \{{x,y\}} {directory}/\*.log \~ a\ b a\|cat");
+        let arguments = expand(&shell, &line).unwrap();
+        let glob = format!("{directory}/*.log");
+        assert_eq!(arguments, ["echo", "{x,y}", &glob, "~", "a b", "a|cat"]);
+    }
+
+    #[test]
+    fn expands_tildes_at_the_start_of_words() {
+        let shell = ShellState::for_tests();
+        let home = shell.environment.HOME.to_string_lossy().into_owned();
+        let cwd = shell
+            .environment
+            .CWD()
+            .path()
+            .to_string_lossy()
+            .into_owned();
+
+        let arguments = expand(&shell, "echo ~ ~/x ~+ a~ a~b").unwrap();
+        assert_eq!(
+            arguments,
+            ["echo", &home, &format!("{home}/x"), &cwd, "a~", "a~b"]
+        );
+        let arguments = expand(&shell, "echo \"~\" '~'/x ~rush-test-no-such-user/x").unwrap();
+        assert_eq!(arguments, ["echo", "~", "~/x", "~rush-test-no-such-user/x"]);
+        let arguments = expand(&shell, "echo ~root/x").unwrap();
+        let root = User::from_name("root").unwrap().unwrap().dir;
+        assert_eq!(arguments, ["echo", &format!("{}/x", root.display())]);
+        let arguments = expand(&shell, "echo ~/{a,b}").unwrap();
+        assert_eq!(
+            arguments,
+            ["echo", &format!("{home}/a"), &format!("{home}/b")]
+        );
+    }
+}
@@ -1,7 +1,14 @@
+mod ast;
 pub mod dispatcher;
+mod expansion;
+mod parser;
 pub mod readline;
+mod redirection;
+mod script;
 mod symbols;
 mod tokenizer;
 
+pub use ast::Function;
 pub use dispatcher::Dispatcher;
 pub use readline::LineEditor;
+pub use script::ScriptEngine;
@@ -0,0 +1,915 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use super::ast::{
+    Assignment, Branch, Command, CompoundCommand, CompoundKind, Connector, FunctionDefinition,
+    List, ListItem, Pipeline, SimpleCommand, Span, Subshell, Word, WordPart,
+};
+use super::redirection::{Redirection, RedirectionKind};
+use super::symbols::{
+    AMPERSAND, AND_IF, CLOSE_GROUP, DO, DONE, ELIF, ELSE, FI, FOR, FUNCTION, IF, IN, LEFT_PAREN,
+    NEWLINE, OPEN_GROUP, OR_IF, PIPE, RIGHT_PAREN, SEMICOLON, THEN, UNTIL, WHILE,
+};
+use super::tokenizer::{tokenize, Token, TokenKind};
+use crate::errors::Result;
+use crate::state::AliasTable;
+
+/// Parses a line of input into a list of commands, replacing any aliases used as command names
+/// with the command lines they expand to
+pub fn parse_with_aliases(input: &str, aliases: &AliasTable) -> Result<List> {
+    let tokens = expand_aliases(tokenize(input)?, aliases, &mut Vec::new())?;
+    let mut parser = Parser::new(tokens);
+    let list = parser.parse_list()?;
+
+    // * A list only stops early at a token it does not understand, such as an unmatched ')'
+    match parser.tokens.peek() {
+        Some(token) => Err(dispatch_err!(UnexpectedToken: token_text(token))),
+        None => Ok(list),
+    }
+}
+
+/// Whether the input ends inside a quote, block, or subshell which has not been closed yet,
+/// meaning that more lines are needed to complete it
+pub fn is_incomplete(input: &str) -> bool {
+    // * The only line which cannot be tokenized is one which ends inside a quote
+    let Ok(tokens) = tokenize(input) else {
+        return true;
+    };
+
+    let mut parser = Parser::new(tokens);
+    let _ = parser.parse_list();
+    parser.incomplete
+}
+
+/// Builds an AST from a sequence of tokens using recursive descent
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+    /// Whether the input ended while a block or subshell was still open
+    incomplete: bool,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens: tokens.into_iter().peekable(),
+            incomplete: false,
+        }
+    }
+
+    /// Parses pipelines joined by list operators, until the end of the input, a closing ')',
+    /// or a reserved word which ends a block
+    fn parse_list(&mut self) -> Result<List> {
+        let mut items = Vec::new();
+        let mut connector = Connector::Always;
+        let mut span: Option<Span> = None;
+
+        loop {
+            self.skip_newlines();
+            match self.tokens.peek() {
+                None => break,
+                Some(token) if is_operator(token, RIGHT_PAREN) || is_terminator(token) => break,
+                Some(token) if list_connector(token).is_some() || is_operator(token, AMPERSAND) => {
+                    // * A list operator can never start a command, so the command before it is missing
+                    return Err(dispatch_err!(MissingListCommand: token_text(token)));
+                }
+                Some(_) => {}
+            }
+
+            let pipeline = self.parse_pipeline()?;
+            span = Some(span.map_or(pipeline.span, |span| span.to(pipeline.span)));
+            let background = self
+                .tokens
+                .next_if(|token| is_operator(token, AMPERSAND))
+                .is_some();
+            items.push(ListItem {
+                connector,
+                pipeline,
+                background,
+            });
+
+            // * A '&' ends the pipeline before it just like ';' does, so the list may continue after it
+            if background {
+                connector = Connector::Always;
+                continue;
+            }
+
+            match self.tokens.next_if(|token| list_connector(token).is_some()) {
+                Some(token) => {
+                    connector = list_connector(&token).unwrap_or(Connector::Always);
+
+                    // A trailing ';' ends the last command without starting another one,
+                    // but '&&' and '||' must always be followed by a command
+                    self.skip_newlines();
+                    let at_end = self.tokens.peek().is_none_or(|token| {
+                        is_operator(token, RIGHT_PAREN) || is_terminator(token)
+                    });
+                    if at_end && connector != Connector::Always {
+                        return Err(dispatch_err!(MissingListCommand: token_text(&token)));
+                    }
+                }
+                None => break,
+            }
+        }
+
+        Ok(List {
+            items,
+            span: span.unwrap_or_default(),
+        })
+    }
+
+    /// Parses commands joined by pipes
+    fn parse_pipeline(&mut self) -> Result<Pipeline> {
+        let first = self.parse_command()?;
+        let mut span = command_span(&first);
+        let mut commands = vec![first];
+
+        while self
+            .tokens
+            .next_if(|token| is_operator(token, PIPE))
+            .is_some()
+        {
+            let command = self.parse_command()?;
+            span = span.to(command_span(&command));
+            commands.push(command);
+        }
+
+        Ok(Pipeline { commands, span })
+    }
+
+    /// Parses a single stage of a pipeline, which is a simple command, a subshell, a compound
+    /// command, or a function definition
+    fn parse_command(&mut self) -> Result<Command> {
+        match self.tokens.peek() {
+            Some(token) if is_operator(token, LEFT_PAREN) => {
+                self.parse_subshell().map(Command::Subshell)
+            }
+            Some(token) if is_keyword(token, FUNCTION) => {
+                let start = self.tokens.next().map_or(0, |token| token.span.start);
+                let name = match self.tokens.next() {
+                    Some(Token {
+                        kind: TokenKind::Word(word),
+                        ..
+                    }) => word.text(),
+                    Some(token) => return Err(dispatch_err!(UnexpectedToken: token_text(&token))),
+                    None => return Err(self.missing(OPEN_GROUP)),
+                };
+
+                // * The parentheses are optional after the 'function' keyword
+                if self
+                    .tokens
+                    .next_if(|token| is_operator(token, LEFT_PAREN))
+                    .is_some()
+                {
+                    self.expect_operator(RIGHT_PAREN)?;
+                }
+
+                self.parse_function(name, start).map(Command::Function)
+            }
+            Some(token) if is_compound_start(token) => self.parse_compound().map(Command::Compound),
+            _ => {
+                let command = self.parse_simple_command()?;
+
+                // A single word followed by '()' is the name of a function being defined
+                match self.tokens.peek() {
+                    Some(token) if is_operator(token, LEFT_PAREN) && is_function_name(&command) => {
+                        self.tokens.next();
+                        self.expect_operator(RIGHT_PAREN)?;
+                        let name = command.words[0].text();
+                        self.parse_function(name, command.span.start)
+                            .map(Command::Function)
+                    }
+                    _ => Ok(Command::Simple(command)),
+                }
+            }
+        }
+    }
+
+    /// Parses a parenthesized list, followed by any redirections which apply to all of it
+    fn parse_subshell(&mut self) -> Result<Subshell> {
+        let start = self.tokens.next().map_or(0, |token| token.span.start);
+        let body = self.parse_list()?;
+
+        let end = match self.tokens.next() {
+            Some(token) if is_operator(&token, RIGHT_PAREN) => token.span.end,
+            Some(token) => return Err(dispatch_err!(UnexpectedToken: token_text(&token))),
+            None => {
+                self.incomplete = true;
+                return Err(dispatch_err!(UnclosedSubshell));
+            }
+        };
+
+        if body.items.is_empty() {
+            return Err(dispatch_err!(UnexpectedToken: RIGHT_PAREN.to_string()));
+        }
+
+        let (redirections, span) = self.parse_redirections(Span::new(start, end))?;
+        Ok(Subshell {
+            body,
+            redirections,
+            span,
+        })
+    }
+
+    /// Parses a compound command, starting at its first reserved word,
+    /// followed by any redirections which apply to all of it
+    fn parse_compound(&mut self) -> Result<CompoundCommand> {
+        let Some(first) = self.tokens.next() else {
+            return Err(dispatch_err!(EmptyPipelineStage));
+        };
+
+        let keyword = token_text(&first);
+        let (kind, end) = match keyword.as_str() {
+            OPEN_GROUP => {
+                let (body, end) = self.parse_block(CLOSE_GROUP)?;
+                (CompoundKind::Group(body), end)
+            }
+            IF => {
+                let mut branches = Vec::new();
+                let mut otherwise = None;
+                let end = loop {
+                    let (condition, _) = self.parse_block(THEN)?;
+                    let (body, end) = self.parse_block_until(&[FI, ELIF, ELSE])?;
+                    branches.push(Branch { condition, body });
+                    match end.1.as_str() {
+                        ELIF => continue,
+                        ELSE => {
+                            let (body, end) = self.parse_block(FI)?;
+                            otherwise = Some(body);
+                            break end;
+                        }
+                        _ => break end.0,
+                    }
+                };
+
+                (
+                    CompoundKind::If {
+                        branches,
+                        otherwise,
+                    },
+                    end,
+                )
+            }
+            WHILE | UNTIL => {
+                let (condition, _) = self.parse_block(DO)?;
+                let (body, end) = self.parse_block(DONE)?;
+                let until = keyword == UNTIL;
+                (
+                    CompoundKind::While {
+                        condition,
+                        body,
+                        until,
+                    },
+                    end,
+                )
+            }
+            _ => {
+                let variable = match self.tokens.next() {
+                    Some(Token {
+                        kind: TokenKind::Word(word),
+                        ..
+                    }) if is_name(&word) => word.text(),
+                    Some(token) => return Err(dispatch_err!(UnexpectedToken: token_text(&token))),
+                    None => return Err(self.missing(DO)),
+                };
+
+                self.skip_newlines();
+                let mut words = None;
+                if self.tokens.next_if(|token| is_keyword(token, IN)).is_some() {
+                    let mut list = Vec::new();
+                    while let Some(Token {
+                        kind: TokenKind::Word(word),
+                        ..
+                    }) = self
+                        .tokens
+                        .next_if(|token| matches!(token.kind, TokenKind::Word(_)))
+                    {
+                        list.push(word);
+                    }
+
+                    words = Some(list);
+                }
+
+                // * The words end at a ';' or a line break, after which 'do' must come next
+                self.tokens
+                    .next_if(|token| is_operator(token, SEMICOLON) || is_operator(token, NEWLINE));
+                self.skip_newlines();
+                self.expect_keyword(DO)?;
+                let (body, end) = self.parse_block(DONE)?;
+                (
+                    CompoundKind::For {
+                        variable,
+                        words,
+                        body,
+                    },
+                    end,
+                )
+            }
+        };
+
+        let (redirections, span) = self.parse_redirections(Span::new(first.span.start, end))?;
+        Ok(CompoundCommand {
+            kind,
+            redirections,
+            span,
+        })
+    }
+
+    /// Parses the body of a function, which must be a compound command such as `{ ...; }`
+    fn parse_function(&mut self, name: String, start: usize) -> Result<FunctionDefinition> {
+        self.skip_newlines();
+        match self.tokens.peek() {
+            Some(token) if is_compound_start(token) => {
+                let body = self.parse_compound()?;
+                let span = Span::new(start, body.span.end);
+                Ok(FunctionDefinition { name, body, span })
+            }
+            Some(token) => Err(dispatch_err!(UnexpectedToken: token_text(token))),
+            None => Err(self.missing(OPEN_GROUP)),
+        }
+    }
+
+    /// Parses a list which must be followed by the given reserved word, returning the list and the
+    /// position after the reserved word
+    fn parse_block(&mut self, closing: &str) -> Result<(List, usize)> {
+        let (list, (end, _)) = self.parse_block_until(&[closing])?;
+        Ok((list, end))
+    }
+
+    /// Parses a list which must be followed by one of the given reserved words, returning the list,
+    /// the position after the reserved word, and which reserved word it was
+    fn parse_block_until(&mut self, closing: &[&str]) -> Result<(List, (usize, String))> {
+        let list = self.parse_list()?;
+        let token = match self.tokens.next() {
+            Some(token) if closing.iter().any(|keyword| is_keyword(&token, keyword)) => token,
+            Some(token) => return Err(dispatch_err!(UnexpectedToken: token_text(&token))),
+            None => return Err(self.missing(closing[0])),
+        };
+
+        // * An empty block such as '{ }' is a syntax error, since the POSIX grammar requires at
+        // * least one command in it
+        if list.items.is_empty() {
+            return Err(dispatch_err!(UnexpectedToken: token_text(&token)));
+        }
+
+        Ok((list, (token.span.end, token_text(&token))))
+    }
+
+    /// Consumes the given reserved word, which must come next
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        match self.tokens.next() {
+            Some(token) if is_keyword(&token, keyword) => Ok(()),
+            Some(token) => Err(dispatch_err!(UnexpectedToken: token_text(&token))),
+            None => Err(self.missing(keyword)),
+        }
+    }
+
+    /// Consumes the given operator, which must come next
+    fn expect_operator(&mut self, operator: char) -> Result<()> {
+        match self.tokens.next() {
+            Some(token) if is_operator(&token, operator) => Ok(()),
+            Some(token) => Err(dispatch_err!(UnexpectedToken: token_text(&token))),
+            None => Err(dispatch_err!(UnexpectedToken: operator.to_string())),
+        }
+    }
+
+    /// Creates the error for a reserved word which is missing at the end of the input,
+    /// noting that the input is incomplete
+    fn missing(&mut self, keyword: &str) -> crate::errors::RushError {
+        self.incomplete = true;
+        dispatch_err!(MissingKeyword: keyword)
+    }
+
+    /// Skips any line breaks, which are allowed between commands
+    fn skip_newlines(&mut self) {
+        while self
+            .tokens
+            .next_if(|token| is_operator(token, NEWLINE))
+            .is_some()
+        {}
+    }
+
+    /// Parses any redirections after a subshell or compound command, extending its span to cover them
+    fn parse_redirections(&mut self, mut span: Span) -> Result<(Vec<Redirection>, Span)> {
+        let mut redirections = Vec::new();
+        loop {
+            match self.tokens.peek() {
+                Some(token) if redirection_operator(token).is_some() => {
+                    let redirection = self.parse_redirection()?;
+                    span = span.to(redirection.span);
+                    redirections.push(redirection);
+                }
+                Some(Token {
+                    kind: TokenKind::Word(word),
+                    ..
+                }) => return Err(dispatch_err!(UnexpectedToken: word.text())),
+                _ => break,
+            }
+        }
+
+        Ok((redirections, span))
+    }
+
+    /// Parses assignments, words, and redirections until the next operator
+    fn parse_simple_command(&mut self) -> Result<SimpleCommand> {
+        let mut assignments = Vec::new();
+        let mut words = Vec::new();
+        let mut redirections = Vec::new();
+        let mut span: Option<Span> = None;
+
+        loop {
+            let token_span = match self.tokens.peek() {
+                Some(token) if redirection_operator(token).is_some() => {
+                    let redirection = self.parse_redirection()?;
+                    let token_span = redirection.span;
+                    redirections.push(redirection);
+                    token_span
+                }
+                Some(Token {
+                    kind: TokenKind::Word(_),
+                    span: token_span,
+                }) => {
+                    let token_span = *token_span;
+                    if let Some(Token {
+                        kind: TokenKind::Word(word),
+                        ..
+                    }) = self.tokens.next()
+                    {
+                        // * Assignments are only recognized before the command name
+                        match split_assignment(&word) {
+                            Some(assignment) if words.is_empty() => assignments.push(assignment),
+                            _ => words.push(word),
+                        }
+                    }
+
+                    token_span
+                }
+                _ => break,
+            };
+
+            span = Some(span.map_or(token_span, |span| span.to(token_span)));
+        }
+
+        match span {
+            Some(span) => Ok(SimpleCommand {
+                assignments,
+                words,
+                redirections,
+                span,
+            }),
+            None => match self.tokens.peek() {
+                // * Reaching the end of a pipeline here means that a pipe had nothing after it
+                Some(token)
+                    if !is_operator(token, PIPE)
+                        && !is_operator(token, RIGHT_PAREN)
+                        && list_connector(token).is_none() =>
+                {
+                    Err(dispatch_err!(UnexpectedToken: token_text(token)))
+                }
+                _ => Err(dispatch_err!(EmptyPipelineStage)),
+            },
+        }
+    }
+
+    /// Parses a redirection operator and the word after it
+    fn parse_redirection(&mut self) -> Result<Redirection> {
+        let Some(operator) = self.tokens.next() else {
+            return Err(dispatch_err!(EmptyPipelineStage));
+        };
+
+        let Some((fd, kind)) = redirection_operator(&operator) else {
+            return Err(dispatch_err!(UnexpectedToken: token_text(&operator)));
+        };
+
+        match self
+            .tokens
+            .next_if(|token| matches!(token.kind, TokenKind::Word(_)))
+        {
+            Some(Token {
+                kind: TokenKind::Word(target),
+                span,
+            }) => Ok(Redirection::new(fd, kind, target, operator.span.to(span))),
+            _ => Err(dispatch_err!(MissingRedirectionTarget: token_text(&operator))),
+        }
+    }
+}
+
+/// Gets the text of a token, regardless of what kind of token it is
+fn token_text(token: &Token) -> String {
+    match &token.kind {
+        TokenKind::Word(word) => word.text(),
+        TokenKind::Operator(operator) => operator.clone(),
+    }
+}
+
+/// Whether a token is the given operator
+fn is_operator(token: &Token, operator: char) -> bool {
+    let mut buffer = [0; 4];
+    matches!(&token.kind, TokenKind::Operator(text) if text == operator.encode_utf8(&mut buffer))
+}
+
+/// Gets the list connector represented by a token, if it is a list operator
+fn list_connector(token: &Token) -> Option<Connector> {
+    match &token.kind {
+        TokenKind::Operator(operator) if operator == AND_IF => Some(Connector::IfSucceeded),
+        TokenKind::Operator(operator) if operator == OR_IF => Some(Connector::IfFailed),
+        TokenKind::Operator(_) if is_operator(token, SEMICOLON) || is_operator(token, NEWLINE) => {
+            Some(Connector::Always)
+        }
+        _ => None,
+    }
+}
+
+/// Replaces each unquoted command name which is an alias with the tokens of its command line
+// * An alias is not expanded again inside its own expansion (POSIX 2.3.1),
+// * so an alias such as 'ls = ls -a' does not expand forever
+fn expand_aliases(
+    tokens: Vec<Token>,
+    aliases: &AliasTable,
+    expanding: &mut Vec<String>,
+) -> Result<Vec<Token>> {
+    let mut expanded = Vec::new();
+    let mut command_position = true;
+    let mut after_redirection = false;
+    for token in tokens {
+        if std::mem::take(&mut after_redirection) {
+            expanded.push(token);
+            continue;
+        }
+
+        let TokenKind::Word(word) = &token.kind else {
+            // * The word after a redirection operator is its target, and the command name may
+            // * still come after that
+            after_redirection = redirection_operator(&token).is_some();
+            if !after_redirection {
+                command_position = starts_command(&token);
+            }
+            expanded.push(token);
+            continue;
+        };
+
+        let alias = match word.parts.as_slice() {
+            [WordPart::Unquoted(name)] if command_position && !expanding.contains(name) => {
+                aliases.get(name).map(|command| (name.clone(), command))
+            }
+            _ => None,
+        };
+
+        match alias {
+            Some((name, command)) => {
+                // * The tokens of the alias all take the span of its name, since that is where
+                // * they appear in the line
+                let alias_tokens = tokenize(command)?
+                    .into_iter()
+                    .map(|alias_token| Token {
+                        span: token.span,
+                        ..alias_token
+                    })
+                    .collect();
+
+                expanding.push(name);
+                expanded.extend(expand_aliases(alias_tokens, aliases, expanding)?);
+                expanding.pop();
+                command_position = expanded.last().is_some_and(starts_command);
+            }
+            None => {
+                // * Assignments can come before the command name, so it may still follow them
+                command_position = (command_position && split_assignment(word).is_some())
+                    || starts_command(&token);
+                expanded.push(token);
+            }
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Whether the word after a token is the name of a command, such as after '|' or 'then'
+fn starts_command(token: &Token) -> bool {
+    match &token.kind {
+        TokenKind::Operator(_) => {
+            list_connector(token).is_some()
+                || [AMPERSAND, PIPE, LEFT_PAREN]
+                    .iter()
+                    .any(|operator| is_operator(token, *operator))
+        }
+        TokenKind::Word(_) => [IF, THEN, ELIF, ELSE, WHILE, UNTIL, DO, OPEN_GROUP]
+            .iter()
+            .any(|keyword| is_keyword(token, keyword)),
+    }
+}
+
+/// Whether a token is the given reserved word, which must not be quoted
+fn is_keyword(token: &Token, keyword: &str) -> bool {
+    match &token.kind {
+        TokenKind::Word(word) => {
+            matches!(word.parts.as_slice(), [WordPart::Unquoted(text)] if text == keyword)
+        }
+        TokenKind::Operator(_) => false,
+    }
+}
+
+/// Whether a token is a reserved word which ends a block, such as 'fi' or 'done'
+fn is_terminator(token: &Token) -> bool {
+    [THEN, ELIF, ELSE, FI, DO, DONE, CLOSE_GROUP]
+        .iter()
+        .any(|keyword| is_keyword(token, keyword))
+}
+
+/// Whether a token is a reserved word which starts a compound command, such as 'if' or 'while'
+fn is_compound_start(token: &Token) -> bool {
+    [IF, WHILE, UNTIL, FOR, OPEN_GROUP]
+        .iter()
+        .any(|keyword| is_keyword(token, keyword))
+}
+
+/// Whether a word is an unquoted, valid variable name, as is needed after 'for'
+fn is_name(word: &Word) -> bool {
+    matches!(word.parts.as_slice(), [WordPart::Unquoted(name)] if is_valid_name(name))
+}
+
+/// Whether a variable name starts with a letter or '_', and contains only letters, digits, and '_'
+fn is_valid_name(name: &str) -> bool {
+    let mut characters = name.chars();
+    characters
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && characters.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether a simple command is only a single unquoted word, which could be the name of a function
+fn is_function_name(command: &SimpleCommand) -> bool {
+    command.assignments.is_empty()
+        && command.redirections.is_empty()
+        && matches!(command.words.as_slice(), [word] if matches!(word.parts.as_slice(), [WordPart::Unquoted(_)]))
+}
+
+/// Gets the file descriptor and kind of a redirection, if the token is a redirection operator
+fn redirection_operator(token: &Token) -> Option<(Option<u32>, RedirectionKind)> {
+    match &token.kind {
+        TokenKind::Operator(operator) => Redirection::parse_operator(operator),
+        TokenKind::Word(_) => None,
+    }
+}
+
+/// Gets the span of a pipeline stage
+fn command_span(command: &Command) -> Span {
+    match command {
+        Command::Simple(command) => command.span,
+        Command::Subshell(subshell) => subshell.span,
+        Command::Compound(compound) => compound.span,
+        Command::Function(function) => function.span,
+    }
+}
+
+/// Splits a word of the form `NAME=value` into an assignment
+// * The name and the '=' must not be quoted, otherwise the word is an ordinary argument
+fn split_assignment(word: &Word) -> Option<Assignment> {
+    let Some(WordPart::Unquoted(first)) = word.parts.first() else {
+        return None;
+    };
+
+    let (name, value) = first.split_once('=')?;
+    if !is_valid_name(name) {
+        return None;
+    }
+
+    let mut parts = Vec::new();
+    if !value.is_empty() {
+        parts.push(WordPart::Unquoted(value.to_owned()));
+    }
+    parts.extend(word.parts[1..].iter().cloned());
+
+    let value_start = word.span.start + name.len() + 1;
+    Some(Assignment {
+        name: name.to_owned(),
+        value: Word {
+            parts,
+            span: Span::new(value_start, word.span.end),
+        },
+        span: word.span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a line without any aliases
+    fn parse(input: &str) -> Result<List> {
+        parse_with_aliases(input, &AliasTable::default())
+    }
+
+    /// Gets the only simple command in a parsed line
+    fn simple_command(list: &List) -> &SimpleCommand {
+        match &list.items[0].pipeline.commands[0] {
+            Command::Simple(command) => command,
+            _ => panic!("expected a simple command"),
+        }
+    }
+
+    #[test]
+    fn parses_lists_and_pipelines_with_spans() {
+        let list = parse("make && cat log | grep error; pwd").unwrap();
+        let connectors: Vec<Connector> = list.items.iter().map(|item| item.connector).collect();
+        assert_eq!(
+            connectors,
+            vec![Connector::Always, Connector::IfSucceeded, Connector::Always]
+        );
+        assert_eq!(list.items[1].pipeline.commands.len(), 2);
+        assert_eq!(list.items[1].pipeline.span, Span::new(8, 28));
+        assert_eq!(list.span, Span::new(0, 33));
+    }
+
+    #[test]
+    fn keeps_quoting_of_word_parts() {
+        let list = parse(r#"echo a'b c'"$d"\$"#).unwrap();
+        let command = simple_command(&list);
+        assert_eq!(
+            command.words[1].parts,
+            vec![
+                WordPart::Unquoted("a".to_owned()),
+                WordPart::Literal("b c".to_owned()),
+                WordPart::DoubleQuoted("$d".to_owned()),
+                WordPart::Literal("$".to_owned()),
+            ]
+        );
+        assert_eq!(command.words[1].span, Span::new(5, 17));
+    }
+
+    #[test]
+    fn keeps_characters_escaped_outside_of_quotes() {
+        let list = parse("echo a\\ b a\\|cat \\~ line\\\nend").unwrap();
+        let command = simple_command(&list);
+        assert_eq!(
+            command.words[1].parts,
+            vec![
+                WordPart::Unquoted("a".to_owned()),
+                WordPart::Literal(" ".to_owned()),
+                WordPart::Unquoted("b".to_owned()),
+            ]
+        );
+        assert_eq!(
+            command.words[2].parts,
+            vec![
+                WordPart::Unquoted("a".to_owned()),
+                WordPart::Literal("|".to_owned()),
+                WordPart::Unquoted("cat".to_owned()),
+            ]
+        );
+        assert_eq!(
+            command.words[3].parts,
+            vec![WordPart::Literal("~".to_owned())]
+        );
+        assert_eq!(
+            command.words[4].parts,
+            vec![WordPart::Unquoted("lineend".to_owned())]
+        );
+        assert_eq!(command.words.len(), 5);
+
+        // * Inside double quotes, only some characters can be escaped
+        let list = parse(r#"echo "a\ b\$""#).unwrap();
+        let command = simple_command(&list);
+        assert_eq!(
+            command.words[1].parts[0],
+            WordPart::DoubleQuoted(r"a\ b".to_owned())
+        );
+    }
+
+    #[test]
+    fn keeps_braced_expansions_in_one_word() {
+        let list = parse("echo ${NAME:-a b; c} d").unwrap();
+        let command = simple_command(&list);
+        assert_eq!(command.words.len(), 3);
+        assert_eq!(command.words[1].text(), "${NAME:-a b; c}");
+    }
+
+    #[test]
+    fn keeps_command_substitutions_in_one_word() {
+        let list = parse(r#"cd "$(git rev-parse --show-toplevel)"/src `echo a | b`"#).unwrap();
+        let command = simple_command(&list);
+        assert_eq!(list.items.len(), 1);
+        assert_eq!(command.words.len(), 3);
+        assert_eq!(
+            command.words[1].parts,
+            vec![
+                WordPart::DoubleQuoted("$(git rev-parse --show-toplevel)".to_owned()),
+                WordPart::Unquoted("/src".to_owned()),
+            ]
+        );
+        assert_eq!(command.words[2].text(), "`echo a | b`");
+    }
+
+    #[test]
+    fn parses_assignments_and_redirections() {
+        let list = parse("LOG=1 build 'X=2' 2> err").unwrap();
+        let command = simple_command(&list);
+        assert_eq!(command.assignments[0].name, "LOG");
+        assert_eq!(command.assignments[0].value.text(), "1");
+        assert_eq!(command.words.len(), 2);
+        assert_eq!(command.redirections[0].fd, 2);
+        assert_eq!(command.redirections[0].target.text(), "err");
+        assert_eq!(command.redirections[0].span, Span::new(18, 24));
+    }
+
+    #[test]
+    fn parses_subshells() {
+        let list = parse("(cd src; ls) > out | wc").unwrap();
+        match &list.items[0].pipeline.commands[0] {
+            Command::Subshell(subshell) => {
+                assert_eq!(subshell.body.items.len(), 2);
+                assert_eq!(subshell.redirections.len(), 1);
+                assert_eq!(subshell.span, Span::new(0, 18));
+            }
+            _ => panic!("expected a subshell"),
+        }
+    }
+
+    #[test]
+    fn ignores_comments() {
+        let list = parse("echo a#b '#c' # d; e").unwrap();
+        let command = simple_command(&list);
+        let words: Vec<String> = command.words.iter().map(Word::text).collect();
+        assert_eq!(words, ["echo", "a#b", "#c"]);
+        assert!(parse("#!/usr/bin/env rush").unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn parses_control_flow_and_functions() {
+        let list =
+            parse("if a; then b\nelif c; then d; else e; fi; for x in 1 2; do f; done").unwrap();
+        let Command::Compound(compound) = &list.items[0].pipeline.commands[0] else {
+            panic!("expected an if command");
+        };
+        let CompoundKind::If {
+            branches,
+            otherwise,
+        } = &compound.kind
+        else {
+            panic!("expected an if command");
+        };
+        assert_eq!(branches.len(), 2);
+        assert!(otherwise.is_some());
+
+        let Command::Compound(compound) = &list.items[1].pipeline.commands[0] else {
+            panic!("expected a for command");
+        };
+        assert!(
+            matches!(&compound.kind, CompoundKind::For { words: Some(words), .. } if words.len() == 2)
+        );
+
+        let list = parse("greet() { echo hi; } > out").unwrap();
+        let Command::Function(function) = &list.items[0].pipeline.commands[0] else {
+            panic!("expected a function definition");
+        };
+        assert_eq!(function.name, "greet");
+        assert_eq!(function.body.redirections.len(), 1);
+
+        assert!(is_incomplete("while true; do\n  echo a"));
+        assert!(!is_incomplete("while true; do echo a; done"));
+        for line in [
+            "if a; fi",
+            "while a; do; done",
+            "for 1 in a; do b; done",
+            "{ }",
+        ] {
+            assert!(parse(line).is_err(), "'{}' should not parse", line);
+        }
+    }
+
+    #[test]
+    fn expands_aliases_in_command_position() {
+        let mut aliases = AliasTable::default();
+        aliases.set("ll", "ls -la").unwrap();
+        aliases.set("ls", "ls --color").unwrap();
+        let list = parse_with_aliases("ll src | X=1 ll; echo ll", &aliases).unwrap();
+        let words = |index: usize, stage: usize| -> Vec<String> {
+            match &list.items[index].pipeline.commands[stage] {
+                Command::Simple(command) => command.words.iter().map(Word::text).collect(),
+                _ => panic!("expected a simple command"),
+            }
+        };
+        assert_eq!(words(0, 0), ["ls", "--color", "-la", "src"]);
+        assert_eq!(words(0, 1), ["ls", "--color", "-la"]);
+        assert_eq!(words(1, 0), ["echo", "ll"]);
+    }
+
+    #[test]
+    fn rejects_unclosed_quotes() {
+        for line in [r#"echo "abc"#, "echo 'abc", r#"echo "it's"' a"#] {
+            let error = parse(line).expect_err("an unclosed quote should not parse");
+            assert_eq!(error.name(), "UnclosedQuote");
+            assert_eq!(error.exit_code(), 2);
+            assert!(is_incomplete(line), "'{}' should need more lines", line);
+        }
+
+        assert!(!is_incomplete(r#"echo "a b" 'c'"#));
+        assert!(parse("echo \"a\nb\"").is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in ["ls |", "| ls", "ls &&", "&& ls", "ls >", "(ls", "ls)", "()"] {
+            assert!(parse(line).is_err(), "'{}' should not parse", line);
+        }
+    }
+}
@@ -1,13 +1,17 @@
+use std::path::{Path, PathBuf};
+
 use rustyline::completion::FilenameCompleter;
 use rustyline::error::ReadlineError;
 use rustyline::highlight::MatchingBracketHighlighter;
 use rustyline::hint::HistoryHinter;
 use rustyline::history::DefaultHistory;
-use rustyline::validate::MatchingBracketValidator;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
 use rustyline::{
     Completer, CompletionType, Config, Editor, Helper, Highlighter, Hinter, Validator,
 };
 
+use super::parser::is_incomplete;
+
 use crate::errors::{Handle, Result};
 use crate::state::ShellState;
 
@@ -19,7 +23,7 @@ struct LineEditorHelper {
     #[rustyline(Highlighter)]
     highlighter: MatchingBracketHighlighter,
     #[rustyline(Validator)]
-    validator: MatchingBracketValidator,
+    validator: BlockValidator,
     #[rustyline(Hinter)]
     hinter: HistoryHinter,
 }
@@ -29,20 +33,33 @@ impl LineEditorHelper {
         Self {
             completer: FilenameCompleter::new(),
             highlighter: MatchingBracketHighlighter::new(),
-            validator: MatchingBracketValidator::new(),
+            validator: BlockValidator,
             hinter: HistoryHinter {},
         }
     }
 }
 
+/// Validator which keeps reading lines until every block and subshell in the input is closed
+struct BlockValidator;
+
+impl Validator for BlockValidator {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match is_incomplete(ctx.input()) {
+            true => Ok(ValidationResult::Incomplete),
+            false => Ok(ValidationResult::Valid(None)),
+        }
+    }
+}
+
 /// Editor for reading lines of input from the user
 pub struct LineEditor {
     editor: Editor<LineEditorHelper, DefaultHistory>,
+    history_file: PathBuf,
 }
 
 impl LineEditor {
     /// Creates a `LineEditor` with the default configuration and given history file
-    pub fn new(history_file: &str) -> Result<Self> {
+    pub fn new(history_file: &Path) -> Result<Self> {
         let config = Config::builder()
             .history_ignore_space(true)
             .completion_type(CompletionType::Fuzzy)
@@ -65,11 +82,15 @@ impl LineEditor {
             }
         }
 
-        Ok(Self { editor })
+        Ok(Self {
+            editor,
+            history_file: history_file.to_path_buf(),
+        })
     }
 
     /// Prints the shell prompt and reads a line of input from the user
-    pub fn prompt_and_read_line(&mut self, shell: &ShellState) -> String {
+    /// Returns nothing once the input has ended, such as when Ctrl-D is pressed
+    pub fn prompt_and_read_line(&mut self, shell: &ShellState) -> Option<String> {
         loop {
             let input = self.editor.readline(&shell.generate_prompt());
             match input {
@@ -79,20 +100,20 @@ impl LineEditor {
                         // * a line that is already in the history, or if the history is full
                         // * None of these require any special handling
                         let _ = self.editor.add_history_entry(&line);
-                        if self.editor.save_history("./config/history.rush").is_err() {
+                        if self.editor.save_history(&self.history_file).is_err() {
                             println!("Failed to save history file.");
                         }
 
-                        return line;
+                        return Some(line);
                     } else {
                         // TODO: Do not reprompt on a blank line
                         continue;
                     }
                 }
                 Err(e) => match e {
-                    // TODO: Propagate error?
-                    ReadlineError::Interrupted => std::process::exit(1),
-                    ReadlineError::Eof => std::process::exit(0),
+                    // * Ctrl-C only discards the line being edited, rather than exiting the shell
+                    ReadlineError::Interrupted => continue,
+                    ReadlineError::Eof => return None,
                     _ => {
                         println!("Unhandled error occurred while line-editing: {}", e);
                         std::process::exit(2);
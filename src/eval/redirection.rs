@@ -0,0 +1,338 @@
+use std::fs::{File, OpenOptions};
+use std::path::PathBuf;
+
+use super::ast::{Span, Word};
+use super::symbols::{
+    ANDDGREAT, ANDGREAT, CLOBBER, DGREAT, DLESS, GREATAND, GREATER_THAN, LESSAND, LESSGREAT,
+    LESS_THAN,
+};
+use crate::errors::{Handle, Result};
+use crate::exec::{Input, Output, Streams};
+
+/// The file descriptors which redirections are able to target
+const STDIN: u32 = 0;
+const STDOUT: u32 = 1;
+const STDERR: u32 = 2;
+
+/// Represents the different kinds of redirection operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectionKind {
+    /// `<`: Reads input from a file
+    Read,
+    /// `>`: Writes output to a file, replacing its contents unless no-clobber is enabled
+    Write,
+    /// `>>`: Writes output to the end of a file
+    Append,
+    /// `>|`: Writes output to a file, replacing its contents even if no-clobber is enabled
+    Clobber,
+    /// `<>`: Opens a file for both reading and writing as input
+    ReadWrite,
+    /// `<&`: Reads input from another file descriptor
+    DuplicateInput,
+    /// `>&`: Writes output to another file descriptor
+    DuplicateOutput,
+    /// `&>`: Writes both output and errors to a file
+    WriteAll,
+    /// `&>>`: Writes both output and errors to the end of a file
+    AppendAll,
+    /// `<<`: Reads input from the following lines (a here-document)
+    HereDocument,
+}
+
+/// A redirection of one of a command's streams to or from a file or another stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirection {
+    /// The file descriptor being redirected
+    pub fd: u32,
+    pub kind: RedirectionKind,
+    /// The file name or file descriptor which the stream is redirected to or from
+    pub target: Word,
+    pub span: Span,
+}
+
+impl RedirectionKind {
+    /// Converts an operator (without any file descriptor prefix) into a redirection kind
+    pub fn from_operator(operator: &str) -> Option<Self> {
+        use RedirectionKind::*;
+        let kind = match operator {
+            DGREAT => Append,
+            CLOBBER => Clobber,
+            LESSGREAT => ReadWrite,
+            LESSAND => DuplicateInput,
+            GREATAND => DuplicateOutput,
+            ANDGREAT => WriteAll,
+            ANDDGREAT => AppendAll,
+            DLESS => HereDocument,
+            _ if operator == LESS_THAN.to_string() => Read,
+            _ if operator == GREATER_THAN.to_string() => Write,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    /// Gets the file descriptor which is redirected when the operator has no number before it
+    fn default_fd(&self) -> u32 {
+        use RedirectionKind::*;
+        match self {
+            Read | ReadWrite | DuplicateInput | HereDocument => STDIN,
+            _ => STDOUT,
+        }
+    }
+}
+
+impl Redirection {
+    /// Attempts to interpret an operator token, which may start with a file descriptor number
+    /// (such as `2>`), as a redirection operator
+    pub fn parse_operator(operator: &str) -> Option<(Option<u32>, RedirectionKind)> {
+        let digits = operator.chars().take_while(|c| c.is_ascii_digit()).count();
+        let (fd, operator) = operator.split_at(digits);
+        let kind = RedirectionKind::from_operator(operator)?;
+        if fd.is_empty() {
+            Some((None, kind))
+        } else {
+            Some((Some(fd.parse().ok()?), kind))
+        }
+    }
+
+    pub fn new(fd: Option<u32>, kind: RedirectionKind, target: Word, span: Span) -> Self {
+        Self {
+            fd: fd.unwrap_or(kind.default_fd()),
+            kind,
+            target,
+            span,
+        }
+    }
+
+    /// Applies the redirection to a command's streams, opening any files which are needed
+    // * The target is passed in separately because it may need to be expanded before it is used
+    pub fn apply(&self, target: &str, streams: &mut Streams, no_clobber: bool) -> Result<()> {
+        use RedirectionKind::*;
+        match self.kind {
+            Read => {
+                let file = Self::open(target, OpenOptions::new().read(true))?;
+                self.set_input(streams, Input::File(file))
+            }
+            ReadWrite => {
+                let file = Self::open(
+                    target,
+                    OpenOptions::new().read(true).write(true).create(true),
+                )?;
+                self.set_input(streams, Input::File(file))
+            }
+            Write => {
+                Self::check_clobber(target, no_clobber)?;
+                let file = Self::open(
+                    target,
+                    OpenOptions::new().write(true).create(true).truncate(true),
+                )?;
+                self.set_output(streams, Output::File(file))
+            }
+            Clobber => {
+                let file = Self::open(
+                    target,
+                    OpenOptions::new().write(true).create(true).truncate(true),
+                )?;
+                self.set_output(streams, Output::File(file))
+            }
+            Append => {
+                let file = Self::open(target, OpenOptions::new().append(true).create(true))?;
+                self.set_output(streams, Output::File(file))
+            }
+            WriteAll | AppendAll => {
+                let file = match self.kind {
+                    WriteAll => {
+                        Self::check_clobber(target, no_clobber)?;
+                        Self::open(
+                            target,
+                            OpenOptions::new().write(true).create(true).truncate(true),
+                        )?
+                    }
+                    _ => Self::open(target, OpenOptions::new().append(true).create(true))?,
+                };
+
+                let copy = file
+                    .try_clone()
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?;
+                streams.stdout = Output::File(file);
+                streams.stderr = Output::File(copy);
+                Ok(())
+            }
+            DuplicateOutput => {
+                let source = match target.parse::<u32>() {
+                    Ok(STDOUT) => &streams.stdout,
+                    Ok(STDERR) => &streams.stderr,
+                    _ => return Err(dispatch_err!(InvalidFileDescriptor: target)),
+                };
+
+                let copy = source
+                    .try_clone()
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?;
+                self.set_output(streams, copy)
+            }
+            DuplicateInput => match target.parse::<u32>() {
+                // * Standard input is the only readable stream, so it can only be duplicated onto itself
+                Ok(STDIN) if self.fd == STDIN => Ok(()),
+                _ => Err(dispatch_err!(InvalidFileDescriptor: target)),
+            },
+            HereDocument => Err(dispatch_err!(UnsupportedRedirection: DLESS)),
+        }
+    }
+
+    /// Opens the target file with the given options
+    fn open(target: &str, options: &OpenOptions) -> Result<File> {
+        options
+            .open(target)
+            .replace_err(|| file_err!(CouldNotOpenFile: PathBuf::from(target)))
+    }
+
+    /// Returns an error if the target file would be overwritten while no-clobber is enabled
+    fn check_clobber(target: &str, no_clobber: bool) -> Result<()> {
+        let path = PathBuf::from(target);
+        if no_clobber && path.is_file() {
+            Err(file_err!(WouldClobberFile: path))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Replaces the input stream being redirected
+    fn set_input(&self, streams: &mut Streams, input: Input) -> Result<()> {
+        match self.fd {
+            STDIN => streams.stdin = input,
+            _ => return Err(dispatch_err!(InvalidFileDescriptor: self.fd.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the output stream being redirected
+    fn set_output(&self, streams: &mut Streams, output: Output) -> Result<()> {
+        match self.fd {
+            STDOUT => streams.stdout = output,
+            STDERR => streams.stderr = output,
+            _ => return Err(dispatch_err!(InvalidFileDescriptor: self.fd.to_string())),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::state::TestDirectory;
+
+    /// Creates a redirection from an operator such as `2>>`
+    fn redirection(operator: &str) -> Redirection {
+        let (fd, kind) = Redirection::parse_operator(operator).unwrap();
+        let target = Word {
+            parts: Vec::new(),
+            span: Span::new(0, 0),
+        };
+        Redirection::new(fd, kind, target, Span::new(0, 0))
+    }
+
+    /// Applies redirections to a new set of streams, each with its target
+    fn apply(redirections: &[(&str, &str)], no_clobber: bool) -> Result<Streams> {
+        let mut streams = Streams::default();
+        for (operator, target) in redirections {
+            redirection(operator).apply(target, &mut streams, no_clobber)?;
+        }
+
+        Ok(streams)
+    }
+
+    #[test]
+    fn parses_operators_with_file_descriptors() {
+        use RedirectionKind::*;
+        assert_eq!(Redirection::parse_operator(">"), Some((None, Write)));
+        assert_eq!(Redirection::parse_operator("2>>"), Some((Some(2), Append)));
+        assert_eq!(Redirection::parse_operator("&>"), Some((None, WriteAll)));
+        assert_eq!(
+            Redirection::parse_operator("2>&"),
+            Some((Some(2), DuplicateOutput))
+        );
+        assert_eq!(Redirection::parse_operator("<"), Some((None, Read)));
+        assert_eq!(Redirection::parse_operator("=>"), None);
+
+        assert_eq!(redirection(">").fd, STDOUT);
+        assert_eq!(redirection("<>").fd, STDIN);
+        assert_eq!(redirection("2>").fd, STDERR);
+    }
+
+    #[test]
+    fn writes_and_appends_to_files() {
+        let directory = TestDirectory::new();
+        let file = directory.join("output.txt");
+        let target = file.to_str().unwrap();
+
+        let mut streams = apply(&[(">", target)], false).unwrap();
+        writeln!(streams.stdout, "first").unwrap();
+        let mut streams = apply(&[(">>", target)], false).unwrap();
+        writeln!(streams.stdout, "second").unwrap();
+        assert_eq!(fs_err::read_to_string(&file).unwrap(), "first\nsecond\n");
+
+        let mut streams = apply(&[(">", target)], false).unwrap();
+        writeln!(streams.stdout, "replaced").unwrap();
+        assert_eq!(fs_err::read_to_string(&file).unwrap(), "replaced\n");
+    }
+
+    #[test]
+    fn refuses_to_overwrite_files_with_no_clobber() {
+        let directory = TestDirectory::new();
+        let file = directory.join("output.txt");
+        let target = file.to_str().unwrap();
+        fs_err::write(&file, "kept\n").unwrap();
+
+        let error = apply(&[(">", target)], true).err().unwrap();
+        assert_eq!(error.name(), "WouldClobberFile");
+        assert!(apply(&[("&>", target)], true).is_err());
+        assert_eq!(fs_err::read_to_string(&file).unwrap(), "kept\n");
+
+        assert!(apply(&[(">>", target)], true).is_ok());
+        let mut streams = apply(&[(">|", target)], true).unwrap();
+        writeln!(streams.stdout, "forced").unwrap();
+        assert_eq!(fs_err::read_to_string(&file).unwrap(), "forced\n");
+    }
+
+    #[test]
+    fn sends_errors_where_output_goes() {
+        let directory = TestDirectory::new();
+        let file = directory.join("output.txt");
+        let target = file.to_str().unwrap();
+
+        // * Redirections are applied from left to right, so '2>&1' copies wherever the output goes at
+        // * that point
+        let mut streams = apply(&[(">", target), ("2>&", "1")], false).unwrap();
+        write!(streams.stdout, "output ").unwrap();
+        write!(streams.stderr, "error").unwrap();
+        assert_eq!(fs_err::read_to_string(&file).unwrap(), "output error");
+
+        let streams = apply(&[("2>&", "1"), (">", target)], false).unwrap();
+        assert!(matches!(streams.stderr, Output::Stdout));
+
+        let mut streams = apply(&[("&>", target)], false).unwrap();
+        write!(streams.stderr, "both").unwrap();
+        assert!(matches!(streams.stdout, Output::File(_)));
+        assert_eq!(fs_err::read_to_string(&file).unwrap(), "both");
+    }
+
+    #[test]
+    fn rejects_unsupported_file_descriptors() {
+        let directory = TestDirectory::new();
+        let file = directory.join("output.txt");
+        let target = file.to_str().unwrap();
+
+        let error = apply(&[("3>", target)], false).err().unwrap();
+        assert_eq!(error.name(), "InvalidFileDescriptor");
+        assert!(apply(&[(">&", "5")], false).is_err());
+        assert!(apply(&[("<&", "1")], false).is_err());
+        assert!(apply(&[("<&", "0")], false).is_ok());
+
+        let error = apply(&[("<<", "EOF")], false).err().unwrap();
+        assert_eq!(error.name(), "UnsupportedRedirection");
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::Write;
+use std::path::Path as StdPath;
+use std::sync::{Arc, Mutex};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, INT};
+
+use super::dispatcher::Dispatcher;
+use crate::errors::{Handle, Result, RushError};
+use crate::exec::Output;
+use crate::state::ShellState;
+
+/// The result of a function available to scripts, which becomes a Rhai error if it fails
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// The parts of the shell which the functions available to scripts use while a script runs
+// * The shell state is moved in here for the duration of a script, and taken out again whenever a
+// * function runs a command, so that the command can run scripts of its own
+#[derive(Default)]
+struct ScriptContext {
+    shell: Option<ShellState>,
+    /// Where the script's 'print' output is written, which may be a pipe or a file
+    output: Option<Output>,
+}
+
+/// Runs Rhai scripts, which can call builtins and executables, and use the variables and CWD of the
+/// shell
+// * The engine does not change once it has been created, so copies of the shell state share it
+#[derive(Clone)]
+pub struct ScriptEngine {
+    engine: Arc<Engine>,
+    context: Arc<Mutex<ScriptContext>>,
+}
+
+impl Default for ScriptEngine {
+    /// Creates an engine with functions for running commands and using the state of the shell
+    fn default() -> Self {
+        let context = Arc::new(Mutex::new(ScriptContext::default()));
+        let mut engine = Engine::new();
+
+        // Every builtin can be called by name or alias, with any '-' replaced by '_',
+        // as in 'list_directory(["-a"])'
+        for name in Dispatcher::default().builtin_names() {
+            let function = name.replace('-', "_");
+            let (command, shared) = (name.to_owned(), context.clone());
+            engine.register_fn(&function, move || {
+                run_command(&shared, &command, Array::new())
+            });
+            let (command, shared) = (name.to_owned(), context.clone());
+            engine.register_fn(&function, move |arguments: Array| {
+                run_command(&shared, &command, arguments)
+            });
+        }
+
+        // Executables are called with 'command', and whole lines of shell syntax with 'shell'
+        let shared = context.clone();
+        engine.register_fn("command", move |name: &str| {
+            run_command(&shared, name, Array::new())
+        });
+        let shared = context.clone();
+        engine.register_fn("command", move |name: &str, arguments: Array| {
+            run_command(&shared, name, arguments)
+        });
+        let shared = context.clone();
+        engine.register_fn("shell", move |line: &str| run_line(&shared, line));
+
+        let shared = context.clone();
+        engine.register_fn("get_var", move |name: &str| {
+            with_shell(&shared, |shell| {
+                shell
+                    .environment
+                    .get_variable(name)
+                    .map_or(Dynamic::UNIT, Dynamic::from)
+            })
+        });
+        let shared = context.clone();
+        engine.register_fn("set_var", move |name: &str, value: Dynamic| {
+            with_shell(&shared, |shell| {
+                shell.environment.set_variable(name, &value.to_string())
+            })?
+            .map_err(to_script_error)
+        });
+        let shared = context.clone();
+        engine.register_fn("export_var", move |name: &str, value: Dynamic| {
+            with_shell(&shared, |shell| {
+                shell.environment.set_variable(name, &value.to_string())?;
+                shell.environment.export_variable(name)
+            })?
+            .map_err(to_script_error)
+        });
+        let shared = context.clone();
+        engine.register_fn("unset_var", move |name: &str| {
+            with_shell(&shared, |shell| shell.environment.unset_variable(name))?
+                .map_err(to_script_error)
+        });
+
+        let shared = context.clone();
+        engine.register_fn("cwd", move || {
+            with_shell(&shared, |shell| shell.CWD().to_string())
+        });
+        let shared = context.clone();
+        engine.register_fn("set_cwd", move |path: &str| {
+            with_shell(&shared, |shell| {
+                let history_limit = shell.config.history_limit;
+                shell.environment.set_CWD(StdPath::new(path), history_limit)
+            })?
+            .map_err(to_script_error)
+        });
+
+        let shared = context.clone();
+        engine.on_print(move |text| print_output(&shared, text));
+
+        Self {
+            engine: Arc::new(engine),
+            context,
+        }
+    }
+}
+
+impl ScriptEngine {
+    /// Runs a script in the shell, passing it the given arguments as 'ARGS'
+    // * The engine belongs to the shell state, so this takes the whole state rather than `&self`
+    pub fn run(
+        shell: &mut ShellState,
+        script: &str,
+        arguments: Vec<String>,
+        output: Output,
+    ) -> Result<()> {
+        // * A shebang line is removed, keeping its line break so that errors report the right line
+        let script = match script.starts_with("#!") {
+            true => script.find('\n').map_or("", |end| &script[end..]),
+            false => script,
+        };
+
+        let scripts = shell.scripts.clone();
+        let mut scope = Scope::new();
+        let arguments: Array = arguments.into_iter().map(Dynamic::from).collect();
+        scope.push_constant("ARGS", arguments);
+
+        // The state is moved into the context while the script runs, leaving a copy in its place
+        // * If a script is already running, its context is put back afterwards
+        let copy = shell.clone();
+        let context = ScriptContext {
+            shell: Some(std::mem::replace(shell, copy)),
+            output: Some(output),
+        };
+
+        let previous = std::mem::replace(&mut *lock_context(&scripts.context)?, context);
+        let result = scripts.engine.run_with_scope(&mut scope, script);
+        let finished = std::mem::replace(&mut *lock_context(&scripts.context)?, previous);
+        if let Some(state) = finished.shell {
+            *shell = state;
+        }
+
+        result.map_err(|error| builtin_err!(ScriptFailed: error.to_string()))
+    }
+}
+
+/// Locks the context of the script engine
+fn lock_context(
+    context: &Mutex<ScriptContext>,
+) -> Result<std::sync::MutexGuard<'_, ScriptContext>> {
+    context
+        .lock()
+        .replace_err(|| builtin_err!(ScriptFailed: "The script engine is unavailable"))
+}
+
+/// Calls a function with the shell state of the script which is running
+// * The context is not locked while the function runs, since it may run another script
+fn with_shell<T>(
+    context: &Mutex<ScriptContext>,
+    function: impl FnOnce(&mut ShellState) -> T,
+) -> ScriptResult<T> {
+    let shell = context
+        .lock()
+        .ok()
+        .and_then(|mut context| context.shell.take());
+    let Some(mut shell) = shell else {
+        return Err("The shell can only be used while a script is running".into());
+    };
+
+    let value = function(&mut shell);
+    if let Ok(mut context) = context.lock() {
+        context.shell = Some(shell);
+    }
+
+    Ok(value)
+}
+
+/// Runs a builtin or executable with the given arguments, which are passed exactly as they are
+fn run_command(context: &Mutex<ScriptContext>, name: &str, arguments: Array) -> ScriptResult<Map> {
+    let mut line = quote(name);
+    for argument in arguments {
+        line.push(' ');
+        line.push_str(&quote(&argument.to_string()));
+    }
+
+    run_line(context, &line)
+}
+
+/// Runs a line of shell syntax, returning its exit status and output as '#{ status, output }'
+// * As with a command substitution, trailing newlines are removed from the output
+fn run_line(context: &Mutex<ScriptContext>, line: &str) -> ScriptResult<Map> {
+    let (status, output) = with_shell(context, |shell| {
+        let dispatcher = shell.dispatcher.clone();
+        let (result, output) = dispatcher.eval_captured(shell, line)?;
+        shell.set_status(&result);
+        if let Err(error) = &result {
+            shell.report_error(error);
+        }
+
+        Ok((shell.last_exit_code, output))
+    })?
+    .map_err(to_script_error)?;
+
+    let mut map = Map::new();
+    map.insert("status".into(), Dynamic::from(INT::from(status)));
+    map.insert("output".into(), output.trim_end_matches('\n').into());
+    Ok(map)
+}
+
+/// Writes text printed by a script to the output of the script
+fn print_output(context: &Mutex<ScriptContext>, text: &str) {
+    let mut context = context.lock().ok();
+    match context.as_mut().and_then(|context| context.output.as_mut()) {
+        // * Output which cannot be written has nowhere else to go, so it is discarded
+        Some(output) => {
+            let _ = writeln!(output, "{}", text);
+        }
+        None => println!("{}", text),
+    }
+}
+
+/// Quotes an argument so that it is passed to a command exactly as it is
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Converts an error from the shell into an error in the script
+fn to_script_error(error: RushError) -> Box<EvalAltResult> {
+    error.to_string().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a script in a shell state, returning its result along with what it printed
+    fn run(shell: &mut ShellState, script: &str, arguments: &[&str]) -> (Result<()>, String) {
+        fs_err::create_dir_all(&shell.paths.data_dir).unwrap();
+        let file = shell.paths.data_dir.join("output.txt");
+        let output = Output::File(fs_err::File::create(&file).unwrap().into());
+        let arguments = arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect();
+
+        let result = ScriptEngine::run(shell, script, arguments, output);
+        (result, fs_err::read_to_string(&file).unwrap())
+    }
+
+    #[test]
+    fn runs_scripts_with_the_state_of_the_shell() {
+        let mut shell = ShellState::for_tests();
+        let script =
+            "#!/usr/bin/env rush\nset_var(\"NAME\", ARGS[0] + \"!\");\nprint(get_var(\"NAME\"));";
+
+        let (result, output) = run(&mut shell, script, &["value"]);
+        assert!(result.is_ok());
+        assert_eq!(output, "value!\n");
+        assert_eq!(
+            shell.environment.get_variable("NAME").as_deref(),
+            Some("value!")
+        );
+    }
+
+    #[test]
+    fn runs_commands_and_lines_from_scripts() {
+        let mut shell = ShellState::for_tests();
+        let script = r#"
+            let result = shell("echo a b | sh -c cat");
+            print(result.output + " " + result.status);
+            print(command("false").status);
+            print(command("echo", ["it's", "$NAME"]).output);
+            shell("rhai -e 'set_var(\"INNER\", 1)'");
+        "#;
+
+        let (result, output) = run(&mut shell, script, &[]);
+        assert!(result.is_ok());
+        assert_eq!(output, "a b 0\n1\nit's $NAME\n");
+        assert_eq!(
+            shell.environment.get_variable("INNER").as_deref(),
+            Some("1")
+        );
+        assert_eq!(shell.last_exit_code, 0);
+    }
+
+    #[test]
+    fn reports_scripts_which_fail() {
+        let mut shell = ShellState::for_tests();
+
+        let (result, _) = run(
+            &mut shell,
+            "set_var(\"BEFORE\", 1);\nthrow \"failed\";",
+            &[],
+        );
+        assert_eq!(result.err().unwrap().name(), "ScriptFailed");
+        assert!(shell.environment.get_variable("BEFORE").is_some());
+        let (result, _) = run(&mut shell, "let x = ;", &[]);
+        assert_eq!(result.err().unwrap().name(), "ScriptFailed");
+    }
+}
@@ -5,10 +5,33 @@ pub const AMPERSAND: char = '&';
 pub const GREATER_THAN: char = '>';
 pub const LESS_THAN: char = '<';
 pub const PIPE: char = '|';
+pub const LEFT_PAREN: char = '(';
+pub const RIGHT_PAREN: char = ')';
+pub const LEFT_BRACE: char = '{';
+pub const RIGHT_BRACE: char = '}';
 pub const SINGLE_QUOTE: char = '\'';
 pub const DOUBLE_QUOTE: char = '"';
 pub const BACKSLASH: char = '\\';
 pub const DOLLAR: char = '$';
+pub const BACKTICK: char = '`';
+pub const HASH: char = '#';
+pub const NEWLINE: char = '\n';
+
+// Reserved words, which are only recognized as the first word of a command
+pub const IF: &str = "if";
+pub const THEN: &str = "then";
+pub const ELIF: &str = "elif";
+pub const ELSE: &str = "else";
+pub const FI: &str = "fi";
+pub const WHILE: &str = "while";
+pub const UNTIL: &str = "until";
+pub const FOR: &str = "for";
+pub const IN: &str = "in";
+pub const DO: &str = "do";
+pub const DONE: &str = "done";
+pub const FUNCTION: &str = "function";
+pub const OPEN_GROUP: &str = "{";
+pub const CLOSE_GROUP: &str = "}";
 
 // Operator tokens
 pub const AND_IF: &str = "&&";
@@ -20,10 +43,11 @@ pub const LESSAND: &str = "<&";
 pub const GREATAND: &str = ">&";
 pub const LESSGREAT: &str = "<>";
 pub const CLOBBER: &str = ">|";
+pub const ANDGREAT: &str = "&>";
+pub const ANDDGREAT: &str = "&>>";
 
 pub struct Symbols<'a> {
     pub operators: Vec<&'a str>,
-    pub separators: Vec<char>,
 }
 
 impl Symbols<'_> {
@@ -32,11 +56,6 @@ impl Symbols<'_> {
             AND_IF, OR_IF, DSEMI, DLESS, DGREAT, LESSAND, GREATAND, LESSGREAT, CLOBBER,
         ];
 
-        let separators = vec![WHITESPACE, AMPERSAND, SEMICOLON];
-
-        Symbols {
-            operators,
-            separators,
-        }
+        Symbols { operators }
     }
 }
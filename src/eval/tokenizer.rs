@@ -1,16 +1,101 @@
+use super::ast::{Span, Word, WordPart};
 use super::symbols::{
-    Symbols, AMPERSAND, BACKSLASH, DOLLAR, DOUBLE_QUOTE, GREATER_THAN, LESS_THAN, PIPE, SEMICOLON,
-    SINGLE_QUOTE, WHITESPACE,
+    Symbols, AMPERSAND, ANDDGREAT, ANDGREAT, BACKSLASH, BACKTICK, DOLLAR, DOUBLE_QUOTE,
+    GREATER_THAN, HASH, LEFT_BRACE, LEFT_PAREN, LESS_THAN, NEWLINE, PIPE, RIGHT_BRACE, RIGHT_PAREN,
+    SEMICOLON, SINGLE_QUOTE, WHITESPACE,
 };
+use crate::errors::Result;
 
-/// Separates a line of input into tokens, such as arguments, separators, and operators
-pub fn tokenize(input: &str) -> Vec<String> {
+/// Represents a single unit of input produced by the tokenizer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Represents the different kinds of tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A command name, argument, or assignment, with its quoting preserved
+    Word(Word),
+    /// A control or redirection operator, which is never quoted
+    Operator(String),
+}
+
+/// Represents the way in which a character was quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    Unquoted,
+    Literal,
+    Double,
+}
+
+/// Collects the characters of the word currently being tokenized
+#[derive(Default)]
+struct WordBuilder {
+    parts: Vec<WordPart>,
+    span: Option<Span>,
+}
+
+impl WordBuilder {
+    /// Adds a character to the word, merging it into the last part if it was quoted the same way
+    fn push(&mut self, character: char, quoting: Quoting, position: usize) {
+        let same_quoting = matches!(
+            (self.parts.last(), quoting),
+            (Some(WordPart::Unquoted(_)), Quoting::Unquoted)
+                | (Some(WordPart::Literal(_)), Quoting::Literal)
+                | (Some(WordPart::DoubleQuoted(_)), Quoting::Double)
+        );
+
+        if !same_quoting {
+            self.start_part(quoting, position);
+        }
+
+        if let Some(WordPart::Unquoted(text))
+        | Some(WordPart::Literal(text))
+        | Some(WordPart::DoubleQuoted(text)) = self.parts.last_mut()
+        {
+            text.push(character);
+        }
+
+        self.extend(position + character.len_utf8());
+    }
+
+    /// Starts a new, empty part of the word
+    // * Quotes start a part even if nothing is inside them, so that '' produces an empty argument
+    fn start_part(&mut self, quoting: Quoting, position: usize) {
+        self.parts.push(match quoting {
+            Quoting::Unquoted => WordPart::Unquoted(String::new()),
+            Quoting::Literal => WordPart::Literal(String::new()),
+            Quoting::Double => WordPart::DoubleQuoted(String::new()),
+        });
+
+        self.extend(position);
+    }
+
+    /// Extends the span of the word up to the given position
+    fn extend(&mut self, end: usize) {
+        let span = self.span.get_or_insert(Span::new(end, end));
+        span.end = span.end.max(end);
+    }
+
+    /// Whether the word consists only of unquoted digits, meaning it could be a file descriptor
+    fn is_io_number(&self) -> bool {
+        match self.parts.as_slice() {
+            [WordPart::Unquoted(text)] => text.chars().all(|c| c.is_ascii_digit()),
+            _ => false,
+        }
+    }
+}
+
+/// Separates a line of input into tokens, such as words and operators
+pub fn tokenize(input: &str) -> Result<Vec<Token>> {
     let symbols = Symbols::new();
 
-    let mut curr_token = String::new();
-    let mut tokens: Vec<String> = Vec::new();
+    let mut curr_token = WordBuilder::default();
+    let mut tokens: Vec<Token> = Vec::new();
 
-    let mut characters = input.trim().chars().peekable();
+    let mut characters = input.char_indices().peekable();
     let mut in_double_quotes = false;
     let mut in_single_quotes = false;
 
@@ -18,126 +103,286 @@ pub fn tokenize(input: &str) -> Vec<String> {
         let character = characters.next();
 
         match character {
-            Some(v) => {
+            Some((position, v)) => {
+                let quoting = match (in_single_quotes, in_double_quotes) {
+                    (true, _) => Quoting::Literal,
+                    (_, true) => Quoting::Double,
+                    _ => Quoting::Unquoted,
+                };
+
                 match v {
                     WHITESPACE | AMPERSAND | SEMICOLON => {
                         if in_single_quotes || in_double_quotes {
-                            curr_token.push(v);
+                            curr_token.push(v, quoting, position);
                             continue;
                         }
 
+                        delimit_token(&mut tokens, &mut curr_token);
                         match characters.peek() {
-                            Some(peeked_char) => {
-                                if (peeked_char == &SEMICOLON && v == SEMICOLON)
-                                    || (peeked_char == &AMPERSAND && v == AMPERSAND)
-                                {
-                                    // clear token, push the operator into the token and advance to the next character
-                                    delimit_token(&mut tokens, &mut curr_token);
-
-                                    curr_token.push(v);
-                                    curr_token.push(*peeked_char);
-                                    characters.next();
-
-                                    delimit_token(&mut tokens, &mut curr_token);
+                            Some(&(_, peeked_char))
+                                if (peeked_char == SEMICOLON && v == SEMICOLON)
+                                    || (peeked_char == AMPERSAND && v == AMPERSAND) =>
+                            {
+                                // push the operator and advance to the next character
+                                let operator = format!("{v}{peeked_char}");
+                                push_operator(&mut tokens, &operator, position);
+                                characters.next();
+                            }
+                            Some(&(_, GREATER_THAN)) if v == AMPERSAND => {
+                                // '&>' and '&>>' redirect both output and errors
+                                characters.next();
+                                if characters.next_if(|&(_, c)| c == GREATER_THAN).is_some() {
+                                    push_operator(&mut tokens, ANDDGREAT, position);
                                 } else {
-                                    delimit_token(&mut tokens, &mut curr_token);
+                                    push_operator(&mut tokens, ANDGREAT, position);
                                 }
                             }
-                            None => {
-                                continue;
+                            _ if v != WHITESPACE => {
+                                push_operator(&mut tokens, &v.to_string(), position)
                             }
+                            _ => {}
                         }
                     }
-                    PIPE | LESS_THAN | GREATER_THAN => {
+                    PIPE | LESS_THAN | GREATER_THAN | LEFT_PAREN | RIGHT_PAREN => {
                         if in_single_quotes || in_double_quotes {
-                            curr_token.push(v);
+                            curr_token.push(v, quoting, position);
                             continue;
                         }
 
+                        // A number directly before a redirection operator is the file descriptor
+                        // being redirected, so it becomes part of the operator
+                        let (prefix, start) = match curr_token.span {
+                            Some(span)
+                                if (v == LESS_THAN || v == GREATER_THAN)
+                                    && curr_token.is_io_number() =>
+                            {
+                                let word = std::mem::take(&mut curr_token);
+                                let prefix = word.parts.iter().map(WordPart::text).collect();
+                                (prefix, span.start)
+                            }
+                            _ => {
+                                delimit_token(&mut tokens, &mut curr_token);
+                                (String::new(), position)
+                            }
+                        };
+
                         match characters.peek() {
-                            Some(peeked_char) => {
+                            Some(&(_, peeked_char))
                                 if symbols
                                     .operators
                                     .iter()
-                                    .any(|&i| i == format!("{v}{peeked_char}"))
-                                {
-                                    // clear token, push the operator into the token and advance to the next character
-                                    delimit_token(&mut tokens, &mut curr_token);
-
-                                    curr_token.push(v);
-                                    curr_token.push(*peeked_char);
-                                    characters.next();
-
-                                    delimit_token(&mut tokens, &mut curr_token);
-                                }
-                            }
-                            None => {
-                                continue;
+                                    .any(|&i| i == format!("{v}{peeked_char}")) =>
+                            {
+                                // push the operator and advance to the next character
+                                let operator = format!("{prefix}{v}{peeked_char}");
+                                push_operator(&mut tokens, &operator, start);
+                                characters.next();
                             }
+                            _ => push_operator(&mut tokens, &format!("{prefix}{v}"), start),
                         }
                     }
                     SINGLE_QUOTE => {
                         if !in_double_quotes {
                             in_single_quotes = !in_single_quotes;
+                            if in_single_quotes {
+                                curr_token.start_part(Quoting::Literal, position);
+                            }
+
+                            curr_token.extend(position + v.len_utf8());
                         } else {
-                            curr_token.push(v);
+                            curr_token.push(v, quoting, position);
                         }
                     }
                     DOUBLE_QUOTE => {
                         if !in_single_quotes {
                             in_double_quotes = !in_double_quotes;
+                            if in_double_quotes {
+                                curr_token.start_part(Quoting::Double, position);
+                            }
+
+                            curr_token.extend(position + v.len_utf8());
                         } else {
-                            curr_token.push(v);
+                            curr_token.push(v, quoting, position);
+                        }
+                    }
+                    HASH if quoting == Quoting::Unquoted && curr_token.span.is_none() => {
+                        // * A '#' at the start of a word begins a comment, which runs to the end
+                        // * of the line, so that scripts can have comments and shebang lines
+                        while characters.next_if(|&(_, c)| c != '\n').is_some() {}
+                    }
+                    DOLLAR | BACKTICK
+                        if !in_single_quotes && is_substitution(&input[position..]) =>
+                    {
+                        // * A command substitution is kept in one piece, including any quotes or
+                        // * operators inside it, and is run when the outer command is expanded
+                        // * If it is never closed, the rest of the line is kept so that the error
+                        // * can be reported then
+                        let length = substitution_length(&input[position..])
+                            .unwrap_or(input.len() - position);
+                        let end = position + length;
+                        curr_token.push(v, quoting, position);
+                        while let Some((position, character)) =
+                            characters.next_if(|&(position, _)| position < end)
+                        {
+                            curr_token.push(character, quoting, position);
+                        }
+                    }
+                    DOLLAR
+                        if !in_single_quotes
+                            && characters.peek().is_some_and(|&(_, c)| c == LEFT_BRACE) =>
+                    {
+                        // * A braced expansion such as '${NAME:-some default}' is kept in one piece,
+                        // * even if it contains characters which would otherwise end the word
+                        // * It is left as text here, and expanded when the command is run
+                        curr_token.push(v, quoting, position);
+                        let mut depth = 0;
+                        for (position, character) in characters.by_ref() {
+                            curr_token.push(character, quoting, position);
+                            match character {
+                                LEFT_BRACE => depth += 1,
+                                RIGHT_BRACE => depth -= 1,
+                                _ => (),
+                            }
+
+                            if depth == 0 {
+                                break;
+                            }
                         }
                     }
                     BACKSLASH => {
                         if in_single_quotes {
-                            curr_token.push(v);
+                            curr_token.push(v, quoting, position);
                             continue;
                         }
 
                         'dont_skip: {
-                            if let Some(peeked_char) = characters.peek() {
-                                match *peeked_char {
-                                    'n' => curr_token.push('\n'),
-                                    't' => curr_token.push('\t'),
-                                    'r' => curr_token.push('\r'),
-                                    '0' => curr_token.push('\0'),
-                                    'a' => curr_token.push('\x07'),
-                                    'b' => curr_token.push('\x08'),
-                                    'v' => curr_token.push('\x0b'),
-                                    'f' => curr_token.push('\x0c'),
-                                    'e' => curr_token.push('\x1b'),
-                                    SINGLE_QUOTE | DOUBLE_QUOTE | DOLLAR | BACKSLASH => {
-                                        curr_token.push(*peeked_char)
-                                    }
+                            if let Some(&(escaped_position, peeked_char)) = characters.peek() {
+                                // * A backslash before a line break joins the two lines together,
+                                // * so both are left out of the word
+                                if peeked_char == NEWLINE {
+                                    characters.next();
+                                    break 'dont_skip;
+                                }
+
+                                let escaped = match peeked_char {
+                                    'n' => '\n',
+                                    't' => '\t',
+                                    'r' => '\r',
+                                    '0' => '\0',
+                                    'a' => '\x07',
+                                    'b' => '\x08',
+                                    'v' => '\x0b',
+                                    'f' => '\x0c',
+                                    'e' => '\x1b',
+                                    SINGLE_QUOTE | DOUBLE_QUOTE | DOLLAR | BACKSLASH => peeked_char,
+                                    // * Outside of quotes, a backslash keeps any character as it is
+                                    // * (POSIX 2.2.1), such as a space, '*', '~', '{' or '|'
+                                    _ if quoting == Quoting::Unquoted => peeked_char,
                                     _ => {
-                                        curr_token.push(v);
+                                        curr_token.push(v, quoting, position);
                                         break 'dont_skip;
                                     }
-                                }
+                                };
+
+                                // * Escaped characters are never treated specially afterwards,
+                                // * just as if they had been single-quoted
+                                curr_token.push(escaped, Quoting::Literal, position);
+                                curr_token.extend(escaped_position + peeked_char.len_utf8());
                             }
 
                             characters.next();
                         }
                     }
-                    _ => curr_token.push(v),
+                    // * A line break ends a command just like ';', so that blocks can span lines
+                    NEWLINE if quoting == Quoting::Unquoted => {
+                        delimit_token(&mut tokens, &mut curr_token);
+                        push_operator(&mut tokens, &v.to_string(), position);
+                    }
+                    // Other whitespace, such as tabs, separates words the same way spaces do
+                    _ if v.is_whitespace() && quoting == Quoting::Unquoted => {
+                        delimit_token(&mut tokens, &mut curr_token);
+                    }
+                    _ => curr_token.push(v, quoting, position),
                 }
             }
             None => {
+                // * A quote which is never closed would otherwise swallow the rest of the line
+                // * without any sign that it was cut short, so the line is rejected instead
+                match (in_single_quotes, in_double_quotes) {
+                    (true, _) => return Err(dispatch_err!(UnclosedQuote: SINGLE_QUOTE)),
+                    (_, true) => return Err(dispatch_err!(UnclosedQuote: DOUBLE_QUOTE)),
+                    _ => (),
+                }
+
                 delimit_token(&mut tokens, &mut curr_token);
                 break;
             }
         }
     }
 
-    tokens
+    Ok(tokens)
 }
 
-/// Pushes the given token to the tokenized list and clears the token buffer
-fn delimit_token(tokens: &mut Vec<String>, curr_token: &mut String) {
-    if !curr_token.is_empty() {
-        tokens.push(curr_token.clone());
-        curr_token.clear();
+/// Whether the text starts with a command substitution, such as '$(pwd)' or '`pwd`'
+pub fn is_substitution(text: &str) -> bool {
+    text.starts_with("$(") || text.starts_with(BACKTICK)
+}
+
+/// Finds the length in bytes of the command substitution at the start of the text, including its
+/// delimiters, or `None` if it is not closed
+pub fn substitution_length(text: &str) -> Option<usize> {
+    let backtick = text.starts_with(BACKTICK);
+    let mut characters = text.char_indices();
+    // Skip the opening delimiter
+    characters.next();
+    if !backtick {
+        characters.next();
     }
+
+    // * Quotes are tracked so that a ')' or '`' inside them does not close the substitution
+    let mut quote = None;
+    let mut depth = 0;
+    while let Some((position, character)) = characters.next() {
+        match (quote, character) {
+            (Some(SINGLE_QUOTE), SINGLE_QUOTE) => quote = None,
+            (Some(SINGLE_QUOTE), _) => (),
+            (_, BACKSLASH) => {
+                characters.next();
+            }
+            (_, BACKTICK) if backtick => return Some(position + character.len_utf8()),
+            (Some(_), DOUBLE_QUOTE) => quote = None,
+            (Some(_), _) => (),
+            (None, SINGLE_QUOTE | DOUBLE_QUOTE) => quote = Some(character),
+            (None, LEFT_PAREN) if !backtick => depth += 1,
+            (None, RIGHT_PAREN) if !backtick && depth == 0 => {
+                return Some(position + character.len_utf8())
+            }
+            (None, RIGHT_PAREN) if !backtick => depth -= 1,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Pushes the word being built to the tokenized list and clears the word buffer
+fn delimit_token(tokens: &mut Vec<Token>, curr_token: &mut WordBuilder) {
+    let word = std::mem::take(curr_token);
+    if let Some(span) = word.span {
+        tokens.push(Token {
+            kind: TokenKind::Word(Word {
+                parts: word.parts,
+                span,
+            }),
+            span,
+        });
+    }
+}
+
+/// Pushes the given operator to the tokenized list
+fn push_operator(tokens: &mut Vec<Token>, operator: &str, start: usize) {
+    tokens.push(Token {
+        kind: TokenKind::Operator(operator.to_owned()),
+        span: Span::new(start, start + operator.len()),
+    });
 }
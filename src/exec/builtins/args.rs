@@ -15,7 +15,10 @@ const FALSE_ARGS: [&str; 9] = [
 pub struct TestArgs {}
 
 #[derive(Parser, Debug)]
-pub struct ExitArgs {}
+pub struct ExitArgs {
+    #[arg(help = "The status to exit with, which defaults to that of the last command")]
+    pub status: Option<i32>,
+}
 
 #[derive(Parser, Debug)]
 pub struct WorkingDirectoryArgs {}
@@ -95,8 +98,8 @@ pub struct DeleteFileArgs {
 
 #[derive(Parser, Debug)]
 pub struct ReadFileArgs {
-    #[arg(help = "The path of the file to read")]
-    pub path: PathBuf,
+    #[arg(help = "The path of the file to read, or nothing to read from the input stream")]
+    pub path: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -107,6 +110,146 @@ pub struct RunExecutableArgs {
     pub arguments: Vec<String>,
 }
 
+#[derive(Parser, Debug)]
+pub struct ListJobsArgs {
+    #[arg(
+        short = 'l',
+        long = "long",
+        help = "Show the process group ID of each job"
+    )]
+    pub long_view: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ForegroundJobArgs {
+    #[arg(help = "The job to bring to the foreground, such as '%1' (defaults to the latest job)")]
+    pub job: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BackgroundJobArgs {
+    #[arg(help = "The stopped job to continue in the background (defaults to the latest job)")]
+    pub job: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct WaitForJobArgs {
+    #[arg(help = "The jobs to wait for (defaults to all jobs)")]
+    pub jobs: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SendSignalArgs {
+    #[arg(
+        short = 's',
+        long = "signal",
+        default_value = "TERM",
+        help = "The signal to send, by name (such as 'KILL') or number"
+    )]
+    pub signal: String,
+    #[arg(
+        required = true,
+        help = "The jobs (such as '%1') or process IDs to send the signal to"
+    )]
+    pub targets: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SetVariableArgs {
+    #[arg(
+        short = 'x',
+        long = "export",
+        help = "Also pass the variables to commands run by the shell"
+    )]
+    pub export: bool,
+    #[arg(required = true, help = "The variables to set, in the form NAME=value")]
+    pub assignments: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct UnsetVariableArgs {
+    #[arg(required = true, help = "The names of the variables to remove")]
+    pub names: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportVariableArgs {
+    #[arg(
+        required = true,
+        help = "The variables to pass to commands run by the shell, in the form NAME or NAME=value"
+    )]
+    pub variables: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ListVariablesArgs {
+    #[arg(
+        short = 'x',
+        long = "exported",
+        help = "Only show the variables which are passed to commands run by the shell"
+    )]
+    pub exported: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct EvaluateScriptArgs {
+    #[arg(
+        short = 'e',
+        long = "eval",
+        help = "Rhai code to run instead of a script file"
+    )]
+    pub code: Option<String>,
+    #[arg(
+        required_unless_present = "code",
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "The path of the Rhai script to run (unless using '-e'), followed by the arguments to pass to it as ARGS"
+    )]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct EditAliasesArgs {
+    #[arg(
+        short = 'r',
+        long = "remove",
+        help = "Remove the named aliases instead of defining one"
+    )]
+    pub remove: bool,
+    #[arg(
+        short = 's',
+        long = "save",
+        help = "Write the alias defined, removed, or named to the config file, so it is kept for new shells (every alias changed in this session if none are named)"
+    )]
+    pub save: bool,
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "The alias to define, in the form 'NAME = command line', or the names of the aliases to show (all of them if none are given)"
+    )]
+    pub definition: Vec<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExplainErrorArgs {
+    #[arg(
+        help = "The name of the error to explain, such as 'unknown-command' (the most recent error if not given)"
+    )]
+    pub name: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SourceFileArgs {
+    #[arg(help = "The path of the file of commands to run in the current shell")]
+    pub path: PathBuf,
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "The arguments to pass to the commands as '$1', '$2', and so on, instead of the shell's own"
+    )]
+    pub arguments: Vec<String>,
+}
+
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
 pub struct ConfigureArgs {
@@ -127,6 +270,53 @@ pub struct ConfigureArgs {
     pub multiline_prompt: Option<Bool>,
     #[arg(long = "show-errors", help = "Whether to display error messages")]
     pub show_errors: Option<Bool>,
+    #[arg(
+        long = "verbose-errors",
+        help = "Whether to explain error messages and show what caused them"
+    )]
+    pub verbose_errors: Option<Bool>,
+    #[arg(
+        long = "no-clobber",
+        help = "Whether to prevent '>' from overwriting existing files"
+    )]
+    pub no_clobber: Option<Bool>,
+    #[arg(
+        long = "no-match-error",
+        help = "Whether a glob pattern which matches no files is an error"
+    )]
+    pub no_match_error: Option<Bool>,
+    #[arg(
+        long = "save",
+        global = true,
+        help = "Write any changed settings to the config file, so they are kept for new shells"
+    )]
+    pub save: bool,
+    #[clap(subcommand)]
+    pub subcommand: Option<ConfigureSubcommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigureSubcommand {
+    #[clap(
+        about = "Show every setting, its value, and whether it is a default, from the config file, or changed in this session"
+    )]
+    Show,
+    #[clap(about = "Print the value of a setting, such as 'prompt.truncation'")]
+    Get(GetSettingCommand),
+    #[clap(about = "Change settings back to their defaults (all of them if none are given)")]
+    Reset(ResetSettingsCommand),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct GetSettingCommand {
+    #[arg(help = "The name of the setting to print")]
+    pub key: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ResetSettingsCommand {
+    #[arg(help = "The names of the settings to reset")]
+    pub keys: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -1,5 +1,6 @@
 use crate::errors::Result;
 use crate::exec::runnable::{Aliases, Runnable};
+use crate::exec::streams::Streams;
 use crate::state::ShellState;
 
 /// Represents a builtin function, its name and its aliases
@@ -7,15 +8,14 @@ pub struct Builtin {
     pub true_name: String,
     pub aliases: Aliases,
     #[allow(clippy::type_complexity)]
-    function: Box<dyn Fn(&mut ShellState, Vec<&str>) -> Result<()>>,
+    function: Box<dyn Fn(&mut ShellState, Vec<&str>, &mut Streams) -> Result<()> + Send + Sync>,
 }
 
 impl Builtin {
-    pub fn new<F: Fn(&mut ShellState, Vec<&str>) -> Result<()> + 'static>(
-        true_name: &str,
-        aliases: Vec<&str>,
-        function: F,
-    ) -> Self {
+    pub fn new<F>(true_name: &str, aliases: Vec<&str>, function: F) -> Self
+    where
+        F: Fn(&mut ShellState, Vec<&str>, &mut Streams) -> Result<()> + Send + Sync + 'static,
+    {
         let true_name = true_name.to_string();
         let aliases = Aliases::from(aliases);
         let function = Box::new(function);
@@ -29,7 +29,12 @@ impl Builtin {
 }
 
 impl Runnable for Builtin {
-    fn run(&self, shell: &mut ShellState, arguments: Vec<&str>) -> Result<()> {
-        (self.function)(shell, arguments)
+    fn run(
+        &self,
+        shell: &mut ShellState,
+        arguments: Vec<&str>,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        (self.function)(shell, arguments, streams)
     }
 }
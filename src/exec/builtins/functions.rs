@@ -8,7 +8,7 @@ Users are free to create their own builtins if they wish to modify the source co
 An executable will only have access to its arguments and environment variables, but not the shell's state, mostly for security reasons.
  */
 
-use std::io::{stderr, BufRead, BufReader};
+use std::io::{self, stderr, BufRead, BufReader, Write};
 use std::os::unix::fs::MetadataExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
@@ -21,20 +21,26 @@ use crossterm::execute;
 use crossterm::style::Stylize;
 use crossterm::terminal::{self, Clear, ClearType};
 use file_owner::PathExt;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
 use size::Size;
 
 use super::args::{
-    ChangeDirectoryArgs, ClearTerminalArgs, ConfigureArgs, DeleteFileArgs, EditPathArgs,
-    EditPathSubcommand, EnvironmentVariableArgs, ExitArgs, ListDirectoryArgs, MakeDirectoryArgs,
-    MakeFileArgs, NextDirectoryArgs, PreviousDirectoryArgs, ReadFileArgs, RunExecutableArgs,
-    WorkingDirectoryArgs,
+    BackgroundJobArgs, ChangeDirectoryArgs, ClearTerminalArgs, ConfigureArgs, ConfigureSubcommand,
+    DeleteFileArgs, EditAliasesArgs, EditPathArgs, EditPathSubcommand, EnvironmentVariableArgs,
+    EvaluateScriptArgs, ExitArgs, ExplainErrorArgs, ExportVariableArgs, ForegroundJobArgs,
+    GetSettingCommand, ListDirectoryArgs, ListJobsArgs, ListVariablesArgs, MakeDirectoryArgs,
+    MakeFileArgs, NextDirectoryArgs, PreviousDirectoryArgs, ReadFileArgs, ResetSettingsCommand,
+    RunExecutableArgs, SendSignalArgs, SetVariableArgs, SourceFileArgs, UnsetVariableArgs,
+    WaitForJobArgs, WorkingDirectoryArgs,
 };
-use crate::errors::{Handle, Result};
+use crate::errors::{explain, Handle, Result};
+use crate::eval::ScriptEngine;
 use crate::exec::builtins::args::{
     AppendPathCommand, DeletePathCommand, InsertPathCommand, PrependPathCommand, TestArgs,
 };
-use crate::exec::{Executable, Runnable};
-use crate::state::{EnvVariable, Path, ShellState};
+use crate::exec::{strip_styles, Executable, Output, Runnable, Streams};
+use crate::state::{EnvVariable, JobStatus, Path, SettingSource, ShellState, SETTINGS};
 
 enum DirectoryListPermissionMode {
     Octal,
@@ -49,24 +55,34 @@ enum DirectoryListTimestampMode {
     Hidden,
 }
 
-pub fn test(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn test(_shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
     clap_handle!(TestArgs::try_parse_from(args));
-    println!("{}", "Test command!".yellow());
-    Ok(())
+    writeln!(streams.stdout, "{}", "Test command!".yellow())
+        .replace_err(|| builtin_err!(CouldNotWriteOutput))
 }
 
-pub fn exit(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
-    clap_handle!(ExitArgs::try_parse_from(args));
-    std::process::exit(0);
+pub fn exit(shell: &mut ShellState, args: Vec<&str>, _streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(ExitArgs::try_parse_from(args));
+    // * The shell exits once the current line has finished running
+    shell.exit_code = arguments.status.unwrap_or(shell.last_exit_code);
+    shell.should_exit = true;
+    Ok(())
 }
 
-pub fn working_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn working_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
     clap_handle!(WorkingDirectoryArgs::try_parse_from(args));
-    println!("{}", shell.CWD());
-    Ok(())
+    writeln!(streams.stdout, "{}", shell.CWD()).replace_err(|| builtin_err!(CouldNotWriteOutput))
 }
 
-pub fn change_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn change_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
     let arguments = clap_handle!(ChangeDirectoryArgs::try_parse_from(args));
     let history_limit = shell.config.history_limit;
     shell
@@ -77,7 +93,11 @@ pub fn change_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
     Ok(())
 }
 
-pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn list_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
     let arguments = clap_handle!(ListDirectoryArgs::try_parse_from(&args));
     let show_hidden = arguments.show_hidden;
     let long_view = arguments.long_view;
@@ -90,7 +110,9 @@ pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
     let hide_permissions = arguments.hide_permissions;
     let hide_user = arguments.hide_user;
     let hide_file_sizes = arguments.hide_file_sizes;
-    let path_to_read = arguments.path.unwrap_or(shell.CWD().path().to_path_buf());
+    let path_to_read = arguments
+        .path
+        .unwrap_or_else(|| shell.CWD().path().to_path_buf());
 
     let read_dir_result =
         fs_err::read_dir(&path_to_read).replace_err(|| file_err!(UnknownPath: path_to_read))?;
@@ -124,25 +146,26 @@ pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
     files.sort();
 
     if !long_view {
+        let mut listing = String::new();
         for i in &directories {
             if i.starts_with('.') {
-                print!("{}  ", i.clone().dark_green())
+                listing.push_str(&format!("{}  ", i.clone().dark_green()))
             } else {
-                print!("{}  ", i.clone().green())
+                listing.push_str(&format!("{}  ", i.clone().green()))
             }
         }
 
         for i in &files {
             if i.starts_with('.') {
-                print!("{}  ", i.clone().grey())
+                listing.push_str(&format!("{}  ", i.clone().grey()))
             } else {
-                print!("{}  ", i.clone().white())
+                listing.push_str(&format!("{}  ", i.clone().white()))
             }
         }
 
-        println!();
-
-        return Ok(());
+        let listing = strip_styles(listing, &streams.stdout);
+        return writeln!(streams.stdout, "{}", listing)
+            .replace_err(|| builtin_err!(CouldNotWriteOutput));
     }
 
     directories.append(&mut files);
@@ -186,6 +209,7 @@ pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
     };
 
     list_directory_long(
+        &mut streams.stdout,
         directories,
         permission_view,
         path_to_read.clone(),
@@ -193,12 +217,12 @@ pub fn list_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
         timestamp,
         hide_user,
         hide_file_sizes,
-    );
-
-    Ok(())
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 fn list_directory_long(
+    output: &mut Output,
     item: Vec<String>,
     permission_format: DirectoryListPermissionMode,
     cwd: PathBuf,
@@ -208,18 +232,20 @@ fn list_directory_long(
     hide_file_sizes: bool,
 ) -> Result<()> {
     let path_to_read = cwd;
+    // * The widths are measured before anything is written, and files can change in the meantime
+    // * (such as a file which the listing is being written into), so the padding saturates at zero
     let mut file_size_len: usize = 0;
-    let mut file_size_len_last: usize = 0;
     let mut username_len: usize = 0;
-    let mut username_len_last: usize = 0;
 
     if !hide_file_sizes {
         for i in &item {
             let path = path_to_read.join(i);
-            let file_size = fs_err::metadata(path.clone()).replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.size();
+            let file_size = fs_err::metadata(path.clone())
+                .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                .size();
             let formatted_fsize = Size::from_bytes(file_size).to_string();
 
-            file_size_len_last = formatted_fsize.len();
+            let file_size_len_last = formatted_fsize.len();
 
             if file_size_len_last > file_size_len {
                 file_size_len = file_size_len_last;
@@ -231,7 +257,11 @@ fn list_directory_long(
         for i in &item {
             let path = path_to_read.join(i);
 
-            username_len_last = path.owner().replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.to_string().len();
+            let username_len_last = path
+                .owner()
+                .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                .to_string()
+                .len();
 
             if username_len_last > username_len {
                 username_len = username_len_last;
@@ -264,7 +294,7 @@ fn list_directory_long(
                 let w = "w".white();
                 let x = "x".white();
 
-                let mapping = vec![
+                let mapping = [
                     format!("{dash}{dash}{dash}"),
                     format!("{dash}{dash}{x}"),
                     format!("{dash}{w}{dash}"),
@@ -300,13 +330,18 @@ fn list_directory_long(
             DirectoryListPermissionMode::Hidden => "".to_string().white(),
         };
 
-        let file_size = fs_err::metadata(path.clone()).replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.size();
+        let file_size = fs_err::metadata(path.clone())
+            .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+            .size();
         let formatted_fsize = Size::from_bytes(file_size).to_string();
         let timestamp = match timestamp {
             DirectoryListTimestampMode::Modified => format!(
                 "{}",
                 DateTime::<Local>::from(
-                    fs_err::metadata(path.clone()).replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.modified().replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                    fs_err::metadata(path.clone())
+                        .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                        .modified()
+                        .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
                 )
                 .format("%b %d %Y %T")
             )
@@ -314,7 +349,10 @@ fn list_directory_long(
             DirectoryListTimestampMode::Created => format!(
                 "{}",
                 DateTime::<Local>::from(
-                    fs_err::metadata(path.clone()).replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.created().replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                    fs_err::metadata(path.clone())
+                        .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                        .created()
+                        .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
                 )
                 .format("%b %d %Y %T")
             )
@@ -322,7 +360,10 @@ fn list_directory_long(
             DirectoryListTimestampMode::Accessed => format!(
                 "{}",
                 DateTime::<Local>::from(
-                    fs_err::metadata(path.clone()).replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.accessed().replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                    fs_err::metadata(path.clone())
+                        .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                        .accessed()
+                        .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
                 )
                 .format("%b %d %Y %T")
             )
@@ -330,8 +371,8 @@ fn list_directory_long(
             DirectoryListTimestampMode::Hidden => "".to_string().white(),
         };
 
-        println!(
-            "{4}{3}{2}{1}{0}",
+        let line = format!(
+            "{4}{3}{2} {1} {0}",
             if i.starts_with('.') {
                 i.as_str().dark_grey()
             } else if i.ends_with('/') {
@@ -343,11 +384,18 @@ fn list_directory_long(
             } else {
                 i.as_str().white()
             },
-            format!(" {timestamp} "),
+            timestamp,
             if !hide_user {
                 format!(
                     " {}{}",
-                    " ".repeat(username_len - path.owner().replace_err(|| file_err!(UnreadableMetadata: path_to_read))?.to_string().len()),
+                    " ".repeat(
+                        username_len.saturating_sub(
+                            path.owner()
+                                .replace_err(|| file_err!(UnreadableMetadata: path_to_read))?
+                                .to_string()
+                                .len()
+                        )
+                    ),
                     path.owner().unwrap().to_string().yellow()
                 )
             } else {
@@ -355,13 +403,13 @@ fn list_directory_long(
             },
             if !hide_file_sizes {
                 if i.ends_with('/') {
-                    let spacing = " ".repeat(file_size_len - "-".len());
+                    let spacing = " ".repeat(file_size_len.saturating_sub("-".len()));
                     format!(" {}{}", spacing, "-".to_string().dark_grey()).white()
                 } else {
                     format!(
                         " {}{}",
                         formatted_fsize,
-                        " ".repeat(file_size_len - formatted_fsize.len())
+                        " ".repeat(file_size_len.saturating_sub(formatted_fsize.len()))
                     )
                     .green()
                 }
@@ -370,11 +418,18 @@ fn list_directory_long(
             },
             permissions
         );
+
+        let line = strip_styles(line, output);
+        writeln!(output, "{}", line).replace_err(|| builtin_err!(CouldNotWriteOutput))?;
     }
     Ok(())
 }
 
-pub fn previous_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn previous_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
     clap_handle!(PreviousDirectoryArgs::try_parse_from(args));
     shell
         .environment
@@ -382,7 +437,11 @@ pub fn previous_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()>
         .replace_err(|| state_err!(NoPreviousDirectory))
 }
 
-pub fn next_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn next_directory(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
     clap_handle!(NextDirectoryArgs::try_parse_from(args));
     shell
         .environment
@@ -390,7 +449,11 @@ pub fn next_directory(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
         .replace_err(|| state_err!(NoNextDirectory))
 }
 
-pub fn clear_terminal(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn clear_terminal(
+    _shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
     clap_handle!(ClearTerminalArgs::try_parse_from(args));
     let y_size = terminal::size()
         .replace_err_with_msg(
@@ -411,106 +474,645 @@ pub fn clear_terminal(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
 }
 
 // TODO: Add prompt to confirm file overwrite
-pub fn make_file(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn make_file(_shell: &mut ShellState, args: Vec<&str>, _streams: &mut Streams) -> Result<()> {
     let arguments = clap_handle!(MakeFileArgs::try_parse_from(args));
     fs_err::File::create(&arguments.path)
         .replace_err(|| file_err!(CouldNotCreateFile: arguments.path))?;
     Ok(())
 }
 
-pub fn make_directory(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn make_directory(
+    _shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
     let arguments = clap_handle!(MakeDirectoryArgs::try_parse_from(args));
     fs_err::create_dir(&arguments.path)
         .replace_err(|| file_err!(CouldNotCreateDirectory: arguments.path))
 }
 
-pub fn delete_file(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn delete_file(_shell: &mut ShellState, args: Vec<&str>, _streams: &mut Streams) -> Result<()> {
     let arguments = clap_handle!(DeleteFileArgs::try_parse_from(args));
     fs_err::remove_file(&arguments.path)
         .replace_err(|| file_err!(CouldNotDeleteFile: arguments.path))
 }
 
-pub fn read_file(_shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn read_file(_shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
     let arguments = clap_handle!(ReadFileArgs::try_parse_from(args));
-    let file_name = arguments.path;
+
+    // If no file is provided, read from the input stream instead so the builtin can be piped into
+    let Some(file_name) = arguments.path else {
+        return io::copy(&mut streams.stdin, &mut streams.stdout)
+            .map(|_| ())
+            .replace_err(|| builtin_err!(CouldNotWriteOutput));
+    };
+
     let file =
         fs_err::File::open(&file_name).replace_err(|| file_err!(CouldNotOpenFile: file_name))?;
 
     let reader = BufReader::new(file);
     for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        println!("{}", &line);
+        let line = line.replace_err(|| file_err!(CouldNotReadFile: file_name))?;
+        writeln!(streams.stdout, "{}", &line).replace_err(|| builtin_err!(CouldNotWriteOutput))?;
     }
 
     Ok(())
 }
 
-pub fn run_executable(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn run_executable(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
     let arguments = clap_handle!(RunExecutableArgs::try_parse_from(&args));
     let executable_name = arguments.path;
-    let executable_path = Path::try_from_path(&executable_name, Some(&shell.environment.HOME))
-        .replace_err_with_msg(
-            || file_err!(UnknownPath: executable_name),
-            &format!("Could not find executable '{}'", executable_name.display()),
-        )?;
+    let executable_path = Path::try_from_path(&executable_name).replace_err_with_msg(
+        || file_err!(UnknownPath: executable_name),
+        &format!("Could not find executable '{}'", executable_name.display()),
+    )?;
 
     // TODO: Fix the usage of args and arg parsing here
-    Executable::new(executable_path).run(shell, args)
+    Executable::new(executable_path)
+        .with_environment(&shell.environment)
+        .run(shell, args, streams)
 }
 
-pub fn configure(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn configure(shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
     let arguments = clap_handle!(ConfigureArgs::try_parse_from(args));
+    let config = &mut shell.config;
 
     if let Some(truncation) = arguments.truncation {
-        shell.config.truncation = truncation.into();
+        config.truncation = truncation.into();
+        config.mark("prompt.truncation", SettingSource::Session);
     }
 
     if let Some(history_limit) = arguments.history_limit {
-        shell.config.history_limit = history_limit.into();
+        config.history_limit = history_limit.into();
+        config.mark("history.directory-limit", SettingSource::Session);
     }
 
     if let Some(multiline_prompt) = arguments.multiline_prompt {
-        shell.config.multiline_prompt = multiline_prompt.into();
+        config.multiline_prompt = multiline_prompt.into();
+        config.mark("prompt.multiline", SettingSource::Session);
     }
 
     if let Some(show_errors) = arguments.show_errors {
-        shell.config.show_errors = show_errors.into();
+        config.show_errors = show_errors.into();
+        config.mark("errors.show", SettingSource::Session);
     }
 
-    Ok(())
-}
+    if let Some(verbose_errors) = arguments.verbose_errors {
+        config.verbose_errors = verbose_errors.into();
+        config.mark("errors.verbose", SettingSource::Session);
+    }
 
-pub fn environment_variable(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
-    let arguments = clap_handle!(EnvironmentVariableArgs::try_parse_from(args));
-    use EnvVariable::*;
-    match arguments.variable {
-        USER => println!("{}", shell.environment.USER),
-        HOME => println!("{}", shell.environment.HOME.display()),
-        CWD => println!("{}", shell.CWD()),
-        PATH => {
-            for (i, path) in shell.environment.PATH().iter().enumerate() {
-                println!("[{i}]: {path}");
+    if let Some(no_clobber) = arguments.no_clobber {
+        config.no_clobber = no_clobber.into();
+        config.mark("files.no-clobber", SettingSource::Session);
+    }
+
+    if let Some(no_match_error) = arguments.no_match_error {
+        config.no_match_error = no_match_error.into();
+        config.mark("files.no-match-error", SettingSource::Session);
+    }
+
+    match arguments.subcommand {
+        Some(ConfigureSubcommand::Show) => {
+            for key in SETTINGS {
+                let value = config.get(key).map(|value| value.to_string());
+                let line = format!(
+                    "{:<24} {:<8} {}",
+                    key,
+                    value.unwrap_or_default().trim(),
+                    config.source(key).to_string().dark_grey()
+                );
+                writeln!(streams.stdout, "{}", strip_styles(line, &streams.stdout))
+                    .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+            }
+        }
+        Some(ConfigureSubcommand::Get(GetSettingCommand { key })) => {
+            let value = config
+                .get(&key)
+                .replace_err(|| state_err!(UnknownSetting: key))?;
+            writeln!(streams.stdout, "{}", value.to_string().trim())
+                .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+        }
+        Some(ConfigureSubcommand::Reset(ResetSettingsCommand { keys })) => {
+            let keys = match keys.is_empty() {
+                true => SETTINGS.map(str::to_owned).to_vec(),
+                false => keys,
+            };
+
+            for key in &keys {
+                config.reset(key)?;
             }
         }
+        None => (),
+    }
+
+    // * Only settings changed in this session are saved, so the rest of the file is left alone
+    if arguments.save {
+        config.save(&shell.paths.config_file)?;
     }
 
     Ok(())
 }
 
-pub fn edit_path(shell: &mut ShellState, args: Vec<&str>) -> Result<()> {
+pub fn environment_variable(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(EnvironmentVariableArgs::try_parse_from(args));
+    use EnvVariable::*;
+    let value = match arguments.variable {
+        USER => shell.environment.USER.clone(),
+        HOME => shell.environment.HOME.display().to_string(),
+        CWD => shell.CWD().to_string(),
+        PATH => shell
+            .environment
+            .PATH()
+            .iter()
+            .enumerate()
+            .map(|(i, path)| format!("[{i}]: {path}"))
+            .collect::<Vec<String>>()
+            .join("\n"),
+    };
+
+    writeln!(streams.stdout, "{}", value).replace_err(|| builtin_err!(CouldNotWriteOutput))
+}
+
+pub fn edit_path(shell: &mut ShellState, args: Vec<&str>, _streams: &mut Streams) -> Result<()> {
     let arguments = clap_handle!(EditPathArgs::try_parse_from(args));
     use EditPathSubcommand::*;
     match arguments.subcommand {
-        Append(AppendPathCommand { path }) => shell
-            .environment
-            .PATH_append(Path::try_from_path(&path, Some(&shell.environment.HOME))?),
-        Prepend(PrependPathCommand { path }) => shell
+        Append(AppendPathCommand { path }) => {
+            shell.environment.PATH_append(Path::try_from_path(&path)?)
+        }
+        Prepend(PrependPathCommand { path }) => {
+            shell.environment.PATH_prepend(Path::try_from_path(&path)?)
+        }
+        Insert(InsertPathCommand { index, path }) => shell
             .environment
-            .PATH_prepend(Path::try_from_path(&path, Some(&shell.environment.HOME))?),
-        Insert(InsertPathCommand { index, path }) => shell.environment.PATH_insert(
-            index,
-            Path::try_from_path(&path, Some(&shell.environment.HOME))?,
-        ),
+            .PATH_insert(index, Path::try_from_path(&path)?),
         Delete(DeletePathCommand { index }) => shell.environment.PATH_delete(index),
     }
 }
+
+pub fn list_jobs(shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(ListJobsArgs::try_parse_from(args));
+    shell.jobs.update()?;
+
+    for job in shell.jobs.jobs() {
+        if arguments.long_view {
+            writeln!(
+                streams.stdout,
+                "[{}] {} {:<8} {}",
+                job.id,
+                job.pgid,
+                job.status(),
+                job.command
+            )
+        } else {
+            writeln!(
+                streams.stdout,
+                "[{}] {:<8} {}",
+                job.id,
+                job.status(),
+                job.command
+            )
+        }
+        .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+    }
+
+    // Finished jobs have been reported here, so they do not need to be reported again
+    shell.jobs.take_finished()?;
+    Ok(())
+}
+
+pub fn foreground_job(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(ForegroundJobArgs::try_parse_from(args));
+    let id = shell.jobs.resolve(arguments.job.as_deref())?;
+    let job = shell
+        .jobs
+        .get_mut(id)
+        .replace_err(|| state_err!(UnknownJob: id.to_string()))?;
+
+    writeln!(streams.stdout, "{}", job.command)
+        .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+    job.resume()?;
+
+    // * The job is taken out of the table while it runs, and put back if it is stopped again
+    let job = shell
+        .jobs
+        .remove(id)
+        .replace_err(|| state_err!(UnknownJob: id.to_string()))?;
+    let statuses = shell.jobs.wait_in_foreground(job)?;
+    statuses
+        .into_iter()
+        .last()
+        .map_or(Ok(()), JobStatus::into_result)
+}
+
+pub fn background_job(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(BackgroundJobArgs::try_parse_from(args));
+    let id = shell.jobs.resolve(arguments.job.as_deref())?;
+    let job = shell
+        .jobs
+        .get_mut(id)
+        .replace_err(|| state_err!(UnknownJob: id.to_string()))?;
+
+    job.resume()?;
+    writeln!(streams.stdout, "[{}] {} &", job.id, job.command)
+        .replace_err(|| builtin_err!(CouldNotWriteOutput))
+}
+
+pub fn wait_for_job(shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(WaitForJobArgs::try_parse_from(args));
+    let ids = match arguments.jobs.is_empty() {
+        true => shell.jobs.jobs().iter().map(|job| job.id).collect(),
+        false => arguments
+            .jobs
+            .iter()
+            .map(|job| shell.jobs.resolve(Some(job)))
+            .collect::<Result<Vec<usize>>>()?,
+    };
+
+    // * As with pipelines, the result is the result of the last job waited for
+    let mut result = Ok(());
+    for id in ids {
+        if let Some(job) = shell.jobs.get_mut(id) {
+            let status = job.wait()?;
+            result = finish_job(shell, id, status, streams);
+        }
+    }
+
+    result
+}
+
+pub fn send_signal(shell: &mut ShellState, args: Vec<&str>, _streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(SendSignalArgs::try_parse_from(args));
+    let signal = parse_signal(&arguments.signal)?;
+
+    for target in &arguments.targets {
+        if target.starts_with('%') {
+            let id = shell.jobs.resolve(Some(target))?;
+            let job = shell
+                .jobs
+                .get_mut(id)
+                .replace_err(|| state_err!(UnknownJob: target))?;
+
+            job.signal(signal)?;
+            // A stopped job has to be continued before it can act on a request to terminate
+            if job.status() == JobStatus::Stopped
+                && matches!(signal, Signal::SIGTERM | Signal::SIGHUP)
+            {
+                job.resume()?;
+            }
+        } else {
+            let pid = target
+                .parse::<i32>()
+                .replace_err(|| state_err!(UnknownJob: target))?;
+            signal::kill(Pid::from_raw(pid), signal)
+                .replace_err(|| state_err!(CouldNotSignalJob: target))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn set_variable(shell: &mut ShellState, args: Vec<&str>, _streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(SetVariableArgs::try_parse_from(args));
+    for assignment in &arguments.assignments {
+        let Some((name, value)) = assignment.split_once('=') else {
+            return Err(state_err!(InvalidVariableName: assignment));
+        };
+
+        shell.environment.set_variable(name, value)?;
+        if arguments.export {
+            shell.environment.export_variable(name)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn unset_variable(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(UnsetVariableArgs::try_parse_from(args));
+    for name in &arguments.names {
+        shell.environment.unset_variable(name)?;
+    }
+
+    Ok(())
+}
+
+pub fn export_variable(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    _streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(ExportVariableArgs::try_parse_from(args));
+    for variable in &arguments.variables {
+        // * POSIX 'export name' marks the variable for export even if it has no value yet
+        match variable.split_once('=') {
+            Some((name, value)) => {
+                shell.environment.set_variable(name, value)?;
+                shell.environment.export_variable(name)?;
+            }
+            None => shell.environment.export_variable(variable)?,
+        }
+    }
+
+    Ok(())
+}
+
+pub fn list_variables(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(ListVariablesArgs::try_parse_from(args));
+    for (name, value, exported) in shell.environment.custom_variables() {
+        if arguments.exported && !exported {
+            continue;
+        }
+
+        let prefix = match exported {
+            true => "export ",
+            false => "",
+        };
+
+        writeln!(streams.stdout, "{}{}={}", prefix, name, value)
+            .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+    }
+
+    Ok(())
+}
+
+pub fn edit_aliases(shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(EditAliasesArgs::try_parse_from(args));
+    if arguments.remove {
+        for name in &arguments.definition {
+            shell.aliases.remove(name)?;
+        }
+
+        return save_aliases(shell, arguments.save, &arguments.definition);
+    }
+
+    // * The definition may be split into several arguments, as in 'alias gs = git status -sb',
+    // * or be a single one, as in "alias gs='git status -sb'"
+    // * The arguments after the one with the '=' are given as they were written, quotes and all
+    let definition = arguments.definition.join(" ");
+    if let Some((name, command)) = definition.split_once('=') {
+        let name = name.trim();
+        // Aliases are expanded before builtins are resolved, so an alias hides a builtin of the same name
+        if let Some(builtin) = shell.dispatcher.resolve_builtin(name) {
+            let warning = format!(
+                "Warning: alias '{}' shadows the builtin '{}'",
+                name, builtin.true_name
+            );
+            let warning = strip_styles(warning.yellow().to_string(), &streams.stderr);
+            writeln!(streams.stderr, "{}", warning)
+                .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+        }
+
+        shell.aliases.set(name, command)?;
+        return save_aliases(shell, arguments.save, &[name.to_owned()]);
+    }
+
+    let names: Vec<String> = match arguments.definition.is_empty() {
+        true => shell.aliases.iter().map(|(name, _)| name.clone()).collect(),
+        false => arguments.definition.clone(),
+    };
+
+    for name in &names {
+        let command = shell
+            .aliases
+            .get(name)
+            .replace_err(|| state_err!(UnknownAlias: name))?;
+        writeln!(streams.stdout, "{} = {}", name, command)
+            .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+    }
+
+    save_aliases(shell, arguments.save, &arguments.definition)
+}
+
+/// Writes the named aliases to the config file if asked to, or every alias changed in this
+/// session if none are named
+// * Aliases only last for the session unless they are saved, so that aliases defined by the rc
+// * script or a one-off command do not pile up in the config file
+fn save_aliases(shell: &mut ShellState, save: bool, names: &[String]) -> Result<()> {
+    match save {
+        true => shell.aliases.save(names),
+        false => Ok(()),
+    }
+}
+
+pub fn explain_error(shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(ExplainErrorArgs::try_parse_from(args));
+    let name = match arguments.name.or_else(|| shell.last_error.clone()) {
+        Some(name) => name,
+        None => {
+            writeln!(streams.stdout, "No errors have occurred yet")
+                .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+            return Ok(());
+        }
+    };
+
+    let (name, explanation) = explain(&name).replace_err(|| builtin_err!(UnknownError: name))?;
+    let text = strip_styles(format!("{}\n{}", name.bold(), explanation), &streams.stdout);
+    writeln!(streams.stdout, "{}", text).replace_err(|| builtin_err!(CouldNotWriteOutput))
+}
+
+/// Removes a job from the job table once it has finished, or reports that it has been stopped
+fn finish_job(
+    shell: &mut ShellState,
+    id: usize,
+    status: JobStatus,
+    streams: &mut Streams,
+) -> Result<()> {
+    if status.is_finished() {
+        shell.jobs.remove(id);
+        return status.into_result();
+    }
+
+    writeln!(streams.stdout, "[{}] {}", id, status)
+        .replace_err(|| builtin_err!(CouldNotWriteOutput))
+}
+
+/// Converts a signal name (with or without the 'SIG' prefix) or number into a signal
+fn parse_signal(name: &str) -> Result<Signal> {
+    if let Ok(number) = name.parse::<i32>() {
+        return Signal::try_from(number).replace_err(|| builtin_err!(InvalidSignal: name));
+    }
+
+    let name = name.to_uppercase();
+    let name = match name.starts_with("SIG") {
+        true => name,
+        false => format!("SIG{}", name),
+    };
+
+    name.parse::<Signal>()
+        .replace_err(|| builtin_err!(InvalidSignal: name))
+}
+
+pub fn source_file(shell: &mut ShellState, args: Vec<&str>, streams: &mut Streams) -> Result<()> {
+    let arguments = clap_handle!(SourceFileArgs::try_parse_from(args));
+
+    // * Without any arguments, the commands see the shell's own positional arguments
+    let previous = match arguments.arguments.is_empty() {
+        true => None,
+        false => {
+            let name = shell.arguments.first().cloned().unwrap_or_default();
+            let positional = std::iter::once(name).chain(arguments.arguments).collect();
+            Some(std::mem::replace(&mut shell.arguments, positional))
+        }
+    };
+
+    let dispatcher = shell.dispatcher.clone();
+    let result = dispatcher.eval_file(shell, &arguments.path, streams);
+    if let Some(previous) = previous {
+        shell.arguments = previous;
+    }
+
+    result
+}
+
+pub fn evaluate_script(
+    shell: &mut ShellState,
+    args: Vec<&str>,
+    streams: &mut Streams,
+) -> Result<()> {
+    let arguments = clap_handle!(EvaluateScriptArgs::try_parse_from(args));
+    let (script, script_arguments) = match arguments.code {
+        Some(code) => (code, arguments.arguments),
+        None => {
+            let (path, script_arguments) = arguments
+                .arguments
+                .split_first()
+                .replace_err(|| builtin_err!(CouldNotParseArgs))?;
+            let script =
+                fs_err::read_to_string(path).replace_err(|| file_err!(CouldNotReadFile: path))?;
+            (script, script_arguments.to_vec())
+        }
+    };
+
+    // * Anything the script prints is written to the builtin's output, so it can be piped
+    let output = streams
+        .stdout
+        .try_clone()
+        .replace_err(|| builtin_err!(CouldNotWriteOutput))?;
+    ScriptEngine::run(shell, &script, script_arguments, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::exec::Input;
+    use crate::state::TestDirectory;
+
+    /// A builtin function, as it is called by the dispatcher
+    type BuiltinFn = fn(&mut ShellState, Vec<&str>, &mut Streams) -> Result<()>;
+
+    /// Runs a builtin with its output collected, returning its result and what it printed
+    fn run(builtin: BuiltinFn, shell: &mut ShellState, args: &[&str]) -> (Result<()>, String) {
+        let (mut reader, writer) = io::pipe().unwrap();
+        let mut streams = Streams::new(Input::Inherit, Output::Pipe(writer));
+        let result = builtin(shell, args.to_vec(), &mut streams);
+        // * The output only ends once every copy of the writer has been closed
+        drop(streams);
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        (result, output)
+    }
+
+    #[test]
+    fn saves_configured_settings_when_asked() {
+        let mut shell = ShellState::for_tests();
+        fs_err::create_dir_all(&shell.paths.config_dir).unwrap();
+        let contents = "# Settings\n[errors]\nshow = true\n";
+        fs_err::write(&shell.paths.config_file, contents).unwrap();
+
+        let arguments = ["configure", "--show-errors", "false"];
+        assert!(run(configure, &mut shell, &arguments).0.is_ok());
+        assert!(!shell.config.show_errors);
+        let arguments = [
+            "configure",
+            "--no-clobber",
+            "true",
+            "--save",
+            "get",
+            "files.no-clobber",
+        ];
+        let (result, output) = run(configure, &mut shell, &arguments);
+        assert!(result.is_ok());
+        assert_eq!(output, "true\n");
+
+        // * Both settings changed in the session are saved, not just the one changed with '--save'
+        let contents = fs_err::read_to_string(&shell.paths.config_file).unwrap();
+        assert!(contents.starts_with("# Settings\n[errors]\nshow = false\n"));
+        assert!(contents.contains("no-clobber = true"));
+    }
+
+    #[test]
+    fn sources_files_in_the_current_shell() {
+        let mut shell = ShellState::for_tests();
+        shell.arguments = ["rush", "outer"].map(String::from).to_vec();
+        let directory = TestDirectory::new();
+        let file = directory.join("script.rush");
+        let contents = "set SOURCED=$1\nfunction sourced() { true; }\necho \"$0 $@\"\n";
+        fs_err::write(&file, contents).unwrap();
+        let path = file.to_str().unwrap();
+
+        let (result, output) = run(source_file, &mut shell, &["source", path, "a", "b c"]);
+        assert!(result.is_ok());
+        assert_eq!(output, "rush a b c\n");
+        assert_eq!(
+            shell.environment.get_variable("SOURCED").as_deref(),
+            Some("a")
+        );
+        assert!(shell.functions.contains_key("sourced"));
+        assert_eq!(shell.arguments, ["rush", "outer"]);
+
+        // * Without arguments, the file sees the shell's own positional arguments
+        let (_, output) = run(source_file, &mut shell, &[".", path]);
+        assert_eq!(output, "rush outer\n");
+    }
+
+    #[test]
+    fn lists_directories_into_files_inside_them() {
+        let mut shell = ShellState::for_tests();
+        let directory = TestDirectory::new();
+        fs_err::write(directory.join("a.txt"), "a").unwrap();
+        let file = directory.join("output.txt");
+        let output = Output::File(fs_err::File::create(&file).unwrap().into());
+        let mut streams = Streams::new(Input::Inherit, output);
+
+        let path = directory.path().to_str().unwrap();
+        let arguments = vec!["list-directory", "-l", path];
+        assert!(list_directory(&mut shell, arguments, &mut streams).is_ok());
+        drop(streams);
+
+        let listing = fs_err::read_to_string(&file).unwrap();
+        let names: Vec<_> = listing
+            .lines()
+            .filter_map(|line| line.split(' ').next_back())
+            .collect();
+        assert_eq!(names, ["a.txt", "output.txt"]);
+    }
+}
@@ -1,12 +1,23 @@
-use std::process::Command as Process;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command as Process};
+use std::thread;
 
+use nix::libc::ENOEXEC;
+
+use super::streams::{Input, Streams};
 use super::Runnable;
 use crate::errors::{Handle, Result};
-use crate::state::{Path, ShellState};
+use crate::state::{Environment, Job, JobStatus, Path, ShellState};
 
 /// Represents an executable (external command)
 pub struct Executable {
     path: Path,
+    /// Variables which are added to the environment of the process, on top of the shell's own
+    variables: Vec<(String, String)>,
+    /// Variables which are left out of the environment of the process
+    removed_variables: Vec<String>,
 }
 
 impl Executable {
@@ -15,36 +26,158 @@ impl Executable {
     // * 2. When the user invokes an external binary that is in the PATH without using the run-executable builtin (implicit invocation)
     // * The Path wrapper type must be validated before construction, so it can be assumed that the path is valid
     pub fn new(path: Path) -> Self {
-        Self { path }
+        Self {
+            path,
+            variables: Vec::new(),
+            removed_variables: Vec::new(),
+        }
     }
-}
 
-impl Runnable for Executable {
-    // * Executables do not have access to the shell state, but the context argument is required by the Runnable trait
-    fn run(&self, _shell: &mut ShellState, arguments: Vec<&str>) -> Result<()> {
+    /// Passes the variables exported by the shell to the process, leaving out any which were unset
+    pub fn with_environment(mut self, environment: &Environment) -> Self {
+        self.variables.extend(environment.exported_variables());
+        self.removed_variables = environment.removed_variables();
+        self
+    }
+
+    /// Adds variables to the environment of the process, such as those from `NAME=value cmd`
+    // * Variables added later take priority over those added earlier
+    pub fn with_variables(mut self, variables: Vec<(String, String)>) -> Self {
+        self.variables.extend(variables);
+        self
+    }
+
+    /// Starts the executable as a child process connected to the given streams, without waiting for it
+    // * A process group of 0 puts the process in a new group of its own
+    pub fn spawn(
+        &self,
+        arguments: Vec<&str>,
+        streams: &mut Streams,
+        process_group: Option<i32>,
+    ) -> Result<Child> {
         // Create the Process, pass the provided arguments to it, and execute it
         // * Executable name has to be removed because `std::process::Command`
         // * automatically adds the executable name as the first argument
-        let mut process = Process::new(self.path.path())
+        let mut process = Process::new(self.path.path());
+        if let Some(process_group) = process_group {
+            process.process_group(process_group);
+        }
+
+        for name in &self.removed_variables {
+            process.env_remove(name);
+        }
+
+        let process = process
             .args(&arguments[1..])
-            .spawn()
-            .replace_err(|| executable_err!(PathNoLongerExists: self.path))?;
-
-        let status = process
-            .wait()
-            .replace_err(|| executable_err!(CouldNotWait))?;
-
-        match status.success() {
-            true => Ok(()),
-            false => {
-                // * 126 is a special exit code that means that the command was found but could not be executed
-                // * as per https://tldp.org/LDP/abs/html/exitcodes.html
-                // * It can be assumed that the command was found here because the Executable path must have been validated already
-                // * Otherwise it could be a 127 for "command not found"
-                Err(executable_err!(FailedToExecute:
-                    status.code().unwrap_or(126) as isize
-                ))
+            .envs(self.variables.iter().map(|(name, value)| (name, value)))
+            .stdin(
+                streams
+                    .stdin
+                    .to_stdio()
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?,
+            )
+            .stdout(
+                streams
+                    .stdout
+                    .to_stdio()
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?,
+            )
+            .stderr(
+                streams
+                    .stderr
+                    .to_stdio()
+                    .replace_err(|| executable_err!(CouldNotConnectStreams))?,
+            )
+            .spawn();
+
+        // * POSIX gives a command which is found but cannot be run the status 126, such as a file
+        // * without permission to execute it or in a format which is not executable, and one which
+        // * is not found the status 127
+        let mut process = match process {
+            Ok(process) => process,
+            Err(error)
+                if error.kind() == io::ErrorKind::PermissionDenied
+                    || error.raw_os_error() == Some(ENOEXEC) =>
+            {
+                let permission_code = fs_err::metadata(self.path.path())
+                    .map(|metadata| metadata.permissions().mode())
+                    .unwrap_or_default();
+                return Err(error)
+                    .replace_err(|| dispatch_err!(CommandNotExecutable: permission_code));
+            }
+            Err(error) => {
+                return Err(error).replace_err(|| executable_err!(PathNoLongerExists: self.path))
             }
+        };
+
+        // Buffered input has to be fed to the process by the shell
+        // * This is done on a separate thread so that a process which fills its output before
+        // * consuming all of its input cannot block the shell
+        if let (Input::Buffer(buffer), Some(mut child_stdin)) =
+            (&mut streams.stdin, process.stdin.take())
+        {
+            let data = std::mem::take(buffer.get_mut());
+            thread::spawn(move || {
+                // * The process is allowed to exit without reading all of its input
+                let _ = child_stdin.write_all(&data);
+            });
         }
+
+        Ok(process)
+    }
+}
+
+impl Runnable for Executable {
+    /// Runs the executable as a foreground job, waiting for it to finish or be stopped
+    fn run(
+        &self,
+        shell: &mut ShellState,
+        arguments: Vec<&str>,
+        streams: &mut Streams,
+    ) -> Result<()> {
+        let command = arguments.join(" ");
+        let process_group = shell.jobs.has_job_control().then_some(0);
+        let process = self.spawn(arguments, streams, process_group)?;
+
+        let job = Job::new(vec![process.id()], &command);
+        let statuses = shell.jobs.wait_in_foreground(job)?;
+        statuses
+            .into_iter()
+            .last()
+            .map_or(Ok(()), JobStatus::into_result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TestDirectory;
+
+    /// Gets the exit status of the error from running a file as an executable
+    fn spawn_status(file: &std::path::Path) -> i32 {
+        let executable = Executable::new(Path::try_from_path(file).unwrap());
+        let path = file.to_str().unwrap();
+        let result = executable.spawn(vec![path], &mut Streams::default(), None);
+        result.err().unwrap().exit_code()
+    }
+
+    #[test]
+    fn gives_the_status_of_files_which_cannot_be_run() {
+        let directory = TestDirectory::new();
+        let file = directory.join("not-executable");
+        fs_err::write(&file, "true\n").unwrap();
+        assert_eq!(spawn_status(&file), 126);
+
+        // * A file which may be executed, but is not in a format which the system can run
+        fs_err::write(&file, [0x7f, b'E', b'L', b'F', 0, 0]).unwrap();
+        let permissions = std::fs::Permissions::from_mode(0o755);
+        fs_err::set_permissions(&file, permissions).unwrap();
+        assert_eq!(spawn_status(&file), 126);
+
+        let executable = Executable::new(Path::try_from_path(&file).unwrap());
+        fs_err::remove_file(&file).unwrap();
+        let path = file.to_str().unwrap();
+        let result = executable.spawn(vec![path], &mut Streams::default(), None);
+        assert_eq!(result.err().unwrap().exit_code(), 127);
     }
 }
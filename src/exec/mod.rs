@@ -1,8 +1,10 @@
 mod builtins;
 mod executable;
 mod runnable;
+mod streams;
 
 pub use builtins::command::Builtin;
 pub use builtins::functions as builtin_funcs;
 pub use executable::Executable;
 pub use runnable::Runnable;
+pub use streams::{strip_styles, Input, Output, Streams};
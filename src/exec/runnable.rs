@@ -1,10 +1,16 @@
+use super::streams::Streams;
 use crate::errors::Result;
 use crate::state::ShellState;
 
 /// Represents either a builtin (internal command) or an executable (external command)
 /// A `Runnable` may be executed by calling its `.run()` method
 pub trait Runnable {
-    fn run(&self, shell: &mut ShellState, arguments: Vec<&str>) -> Result<()>;
+    fn run(
+        &self,
+        shell: &mut ShellState,
+        arguments: Vec<&str>,
+        streams: &mut Streams,
+    ) -> Result<()>;
 }
 
 /// Wrapper type that makes it easier to read code related to builtins
@@ -25,4 +31,8 @@ impl Aliases {
     pub fn contains(&self, alias: &str) -> bool {
         self.aliases.contains(&alias.to_string())
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.aliases.iter()
+    }
 }
@@ -0,0 +1,174 @@
+use std::fs::File;
+use std::io::{self, Cursor, IsTerminal, PipeReader, PipeWriter, Read, Write};
+use std::os::fd::AsFd;
+use std::process::Stdio;
+
+use nix::libc::STDIN_FILENO;
+use nix::unistd;
+
+/// Represents the source which a command reads its input from
+pub enum Input {
+    /// Input is read from the shell's own standard input (usually the terminal)
+    Inherit,
+    /// Input is read from the output of another command through an OS pipe
+    Pipe(PipeReader),
+    /// Input is read from a file which was redirected into the command
+    File(File),
+    /// Input is read from output which was previously captured in memory
+    Buffer(Cursor<Vec<u8>>),
+}
+
+/// Represents the destination which a command writes its output to
+pub enum Output {
+    /// Output is written to the shell's own standard output
+    Stdout,
+    /// Output is written to the shell's own standard error
+    Stderr,
+    /// Output is written to the input of another command through an OS pipe
+    Pipe(PipeWriter),
+    /// Output is written to a file which the command was redirected into
+    File(File),
+}
+
+/// The set of standard streams available to a command while it runs
+// * Builtins write to these streams instead of printing directly, which allows their output to be
+// * piped into other commands the same way the output of an executable would be
+pub struct Streams {
+    pub stdin: Input,
+    pub stdout: Output,
+    pub stderr: Output,
+}
+
+impl Default for Streams {
+    /// Creates a set of streams which are all inherited from the shell
+    fn default() -> Self {
+        Self::new(Input::Inherit, Output::Stdout)
+    }
+}
+
+impl Streams {
+    /// Creates a set of streams with the given input and output, and standard error inherited
+    pub fn new(stdin: Input, stdout: Output) -> Self {
+        Self {
+            stdin,
+            stdout,
+            stderr: Output::Stderr,
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            // * The shell's own input is read without a buffer, so that nothing is taken from it
+            // * which belongs to a later command, such as the rest of a script piped into the shell
+            Self::Inherit => unistd::read(STDIN_FILENO, buf).map_err(io::Error::from),
+            Self::Pipe(reader) => reader.read(buf),
+            Self::File(file) => file.read(buf),
+            Self::Buffer(buffer) => buffer.read(buf),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout => io::stdout().write(buf),
+            Self::Stderr => io::stderr().write(buf),
+            Self::Pipe(writer) => writer.write(buf),
+            Self::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout => io::stdout().flush(),
+            Self::Stderr => io::stderr().flush(),
+            Self::Pipe(writer) => writer.flush(),
+            Self::File(file) => file.flush(),
+        }
+    }
+}
+
+impl Input {
+    /// Converts the input into a form which can be handed to a child process
+    // * Buffered input cannot be handed over directly, so the child is given a pipe instead,
+    // * and the caller is responsible for writing the buffer into it after spawning the child
+    pub fn to_stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Self::Inherit => Stdio::inherit(),
+            Self::Pipe(reader) => reader.try_clone()?.into(),
+            Self::File(file) => file.try_clone()?.into(),
+            Self::Buffer(_) => Stdio::piped(),
+        })
+    }
+}
+
+impl Input {
+    /// Creates another handle to the same source, which is needed when several commands share an input
+    // * Buffered input can only be read once, so its contents are moved into the new handle
+    pub fn share(&mut self) -> io::Result<Self> {
+        Ok(match self {
+            Self::Inherit => Self::Inherit,
+            Self::Pipe(reader) => Self::Pipe(reader.try_clone()?),
+            Self::File(file) => Self::File(file.try_clone()?),
+            Self::Buffer(buffer) => Self::Buffer(std::mem::take(buffer)),
+        })
+    }
+}
+
+impl Output {
+    /// Converts the output into a form which can be handed to a child process
+    pub fn to_stdio(&self) -> io::Result<Stdio> {
+        Ok(match self {
+            Self::Stdout => io::stdout().as_fd().try_clone_to_owned()?.into(),
+            Self::Stderr => io::stderr().as_fd().try_clone_to_owned()?.into(),
+            Self::Pipe(writer) => writer.try_clone()?.into(),
+            Self::File(file) => file.try_clone()?.into(),
+        })
+    }
+
+    /// Creates another handle to the same destination, which is needed for redirections like `2>&1`
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Self::Stdout => Self::Stdout,
+            Self::Stderr => Self::Stderr,
+            Self::Pipe(writer) => Self::Pipe(writer.try_clone()?),
+            Self::File(file) => Self::File(file.try_clone()?),
+        })
+    }
+
+    /// Whether the output is displayed directly in a terminal
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Self::Stdout => io::stdout().is_terminal(),
+            Self::Stderr => io::stderr().is_terminal(),
+            Self::Pipe(_) | Self::File(_) => false,
+        }
+    }
+}
+
+/// Removes color and style escape sequences from text which is not being written to a terminal,
+/// so that redirecting a listing into a file or another command produces plain text
+pub fn strip_styles(text: String, output: &Output) -> String {
+    if output.is_terminal() {
+        return text;
+    }
+
+    let mut plain = String::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character == '\x1b' {
+            // * Style sequences take the form 'ESC [ <parameters> <letter>'
+            for c in characters.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(character);
+        }
+    }
+
+    plain
+}
@@ -6,52 +6,392 @@ mod exec;
 mod plugins;
 mod state;
 
-use errors::{Result, RushError};
-use eval::{Dispatcher, LineEditor};
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use clap::Parser;
+
+use errors::{Handle, Result, RushError};
+use eval::{Dispatcher, LineEditor, ScriptEngine};
+use exec::{Input, Output, Streams};
 #[cfg(feature = "plugin")]
 use plugins::host::PluginHost;
-use state::ShellState;
+use state::{PathOverrides, ShellPaths, ShellState};
+
+/// A shell written in Rust
+#[derive(Parser, Debug)]
+#[command(name = "rush")]
+struct CommandLineArgs {
+    #[arg(
+        short = 'c',
+        value_name = "COMMANDS",
+        help = "Run the given commands instead of reading them from a script or the input stream"
+    )]
+    command: Option<String>,
+    // * Everything after the script is passed to it, even if it looks like an option of the shell
+    #[arg(
+        value_name = "SCRIPT",
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        help = "The script file to run, followed by the arguments to pass to it as '$1', '$2', \
+                and so on (with '-c', the first of these is used as '$0' instead)"
+    )]
+    arguments: Vec<String>,
+    #[arg(
+        short = 'l',
+        long = "login",
+        help = "Act as a login shell, running the profile script on start and the logout script on exit"
+    )]
+    login: bool,
+    #[arg(
+        long = "norc",
+        help = "Do not run the rc script when starting an interactive shell"
+    )]
+    no_rc: bool,
+    #[arg(
+        long = "noprofile",
+        help = "Do not run the profile script when starting a login shell"
+    )]
+    no_profile: bool,
+    #[arg(
+        long = "config",
+        value_name = "FILE",
+        help = "The configuration file to use, instead of the one in the configuration directory \
+                (can also be set with RUSH_CONFIG)"
+    )]
+    config_file: Option<PathBuf>,
+    #[arg(
+        long = "config-dir",
+        value_name = "DIR",
+        help = "The directory for configuration files, instead of '$XDG_CONFIG_HOME/rush'"
+    )]
+    config_dir: Option<PathBuf>,
+    #[arg(
+        long = "data-dir",
+        value_name = "DIR",
+        help = "The directory for data files such as plugins, instead of '$XDG_DATA_HOME/rush'"
+    )]
+    data_dir: Option<PathBuf>,
+    #[arg(
+        long = "state-dir",
+        value_name = "DIR",
+        help = "The directory for files such as history, instead of '$XDG_STATE_HOME/rush'"
+    )]
+    state_dir: Option<PathBuf>,
+}
 
 fn main() {
+    let args = CommandLineArgs::parse();
+
     // The ShellState type stores all of the state for the shell, including its configuration,
     // its environment, and other miscellaneous data like command history
-    let shell = match ShellState::new() {
+    let paths = ShellPaths::new(PathOverrides {
+        config_file: args.config_file.clone(),
+        config_dir: args.config_dir.clone(),
+        data_dir: args.data_dir.clone(),
+        state_dir: args.state_dir.clone(),
+    });
+
+    // Default files are only created on the first run of an interactive shell, so that scripts,
+    // '-c', and piped input leave nothing behind
+    // * The shell still starts if its files cannot be created, such as with a read-only home
+    let interactive =
+        args.command.is_none() && args.arguments.is_empty() && io::stdin().is_terminal();
+    if interactive {
+        if let Err(error) = paths.create_defaults() {
+            eprintln!("{}", error);
+        }
+    }
+
+    let shell = match ShellState::new(paths) {
         Ok(shell) => shell,
         Err(err) => crash_with_error(err),
     };
 
+    // * The first argument is '$0', which is the script itself, or with '-c', the first argument
+    // * after the commands, as in other shells, so 'rush -c 'echo $0 $1' a b' prints 'a b'
+    if !args.arguments.is_empty() {
+        shell.write().unwrap().arguments = args.arguments.clone();
+    }
+
     #[cfg(feature = "plugin")]
-    let _plugins = PluginHost::new(shell.clone());
+    let mut _plugins = PluginHost::new(shell.clone());
+
+    // The Dispatcher type is responsible for resolving command names to actual function calls,
+    // or executables if needed, and then invoking them with the given arguments
+    let dispatcher = shell.read().unwrap().dispatcher.clone();
+
+    // A login shell is one started with '-l', or by a program such as 'login' which starts the name
+    // of the shell with '-'
+    let login = args.login
+        || std::env::args()
+            .next()
+            .is_some_and(|name| name.starts_with('-'));
+    if login && !args.no_profile {
+        let profile_file = shell.read().unwrap().paths.profile_file();
+        run_script_file(&dispatcher, &shell, &profile_file);
+        if shell.read().unwrap().should_exit {
+            let status = shell.read().unwrap().exit_code;
+            exit_shell(&dispatcher, &shell, login, status);
+        }
+    }
+
+    // Commands from '-c', a script, or a non-terminal input stream are run without a prompt,
+    // and the shell exits with the status of the last command
+    if let Some(command) = &args.command {
+        let lines = command.lines().map(str::to_owned);
+        let status = run_script(&dispatcher, &shell, "-c", lines);
+        exit_shell(&dispatcher, &shell, login, status);
+    }
+
+    if let Some(script) = args.arguments.first() {
+        let contents = match fs_err::read_to_string(script)
+            .replace_err(|| file_err!(CouldNotReadFile: script))
+        {
+            Ok(contents) => contents,
+            Err(err) => crash_with_error(err),
+        };
+
+        // Rhai scripts are run by the script engine instead of line by line
+        if script.ends_with(".rhai") {
+            let arguments = args.arguments.iter().skip(1).cloned().collect();
+            let status = run_rhai_script(&shell, &contents, arguments);
+            exit_shell(&dispatcher, &shell, login, status);
+        }
+
+        let lines = contents.lines().map(str::to_owned);
+        let status = run_script(&dispatcher, &shell, script, lines);
+        exit_shell(&dispatcher, &shell, login, status);
+    }
+
+    if !io::stdin().is_terminal() {
+        let status = run_script(&dispatcher, &shell, "stdin", stdin_lines());
+        exit_shell(&dispatcher, &shell, login, status);
+    }
+
+    // Job control hands the terminal to foreground commands, so that Ctrl-C and Ctrl-Z affect them
+    // rather than the shell itself
+    if let Err(err) = shell.write().unwrap().jobs.enable_job_control() {
+        eprintln!("{}", err);
+    }
+
+    // The rc script is where PATH entries, aliases and variables for interactive use are set up
+    if !args.no_rc {
+        let rc_file = shell.read().unwrap().paths.rc_file();
+        run_script_file(&dispatcher, &shell, &rc_file);
+        if shell.read().unwrap().should_exit {
+            let status = shell.read().unwrap().exit_code;
+            exit_shell(&dispatcher, &shell, login, status);
+        }
+    }
 
     // The LineEditor type is responsible for reading lines of input from the user, storing history,
     // providing tab completion and other line-editing features
-    let mut line_editor = match LineEditor::new("./config/history.rush") {
+    let history_file = shell.read().unwrap().paths.history_file();
+    let mut line_editor = match LineEditor::new(&history_file) {
         Ok(editor) => editor,
         Err(err) => crash_with_error(err),
     };
 
-    // The Dispatcher type is responsible for resolving command names to actual function calls,
-    // or executables if needed, and then invoking them with the given arguments
-    let dispatcher = Dispatcher::default();
-
     loop {
-        let line = line_editor.prompt_and_read_line(&shell.read().unwrap());
+        report_finished_jobs(&mut shell.write().unwrap());
+
+        // The config file is checked for changes before each prompt, so that edits to it take
+        // effect, including to the prompt itself, without restarting the shell
+        #[cfg(feature = "plugin")]
+        let plugin_paths = shell.read().unwrap().config.plugin_paths.clone();
+        shell.write().unwrap().reload_config();
+        #[cfg(feature = "plugin")]
+        if shell.read().unwrap().config.plugin_paths != plugin_paths {
+            _plugins = PluginHost::new(shell.clone());
+        }
+
+        let Some(line) = line_editor.prompt_and_read_line(&shell.read().unwrap()) else {
+            let status = shell.read().unwrap().last_exit_code;
+            exit_shell(&dispatcher, &shell, login, status);
+        };
+
         let status = dispatcher.eval(&mut shell.write().unwrap(), &line);
         handle_error(status, &mut shell.write().unwrap());
+
+        if shell.read().unwrap().should_exit {
+            let status = shell.read().unwrap().exit_code;
+            exit_shell(&dispatcher, &shell, login, status);
+        }
+    }
+}
+
+// Runs each line of a script in turn, returning the exit status of the last command which was run
+// The name of the script is given with any error, along with the line it occurred on
+fn run_script(
+    dispatcher: &Dispatcher,
+    shell: &RwLock<ShellState>,
+    name: &str,
+    lines: impl Iterator<Item = String>,
+) -> i32 {
+    let shell = &mut shell.write().unwrap();
+    let status = dispatcher.eval_lines(shell, name, lines, &mut Streams::default());
+    handle_error(status, shell);
+    exit_status(shell)
+}
+
+// Runs a Rhai script, returning the status which the shell should exit with
+fn run_rhai_script(shell: &RwLock<ShellState>, contents: &str, arguments: Vec<String>) -> i32 {
+    let shell = &mut shell.write().unwrap();
+    // * A script which runs to the end keeps the status of the last command it ran
+    if let Err(error) = ScriptEngine::run(shell, contents, arguments, Output::Stdout) {
+        handle_error(Err(error), shell);
+    }
+
+    exit_status(shell)
+}
+
+// Gets the status which the shell exits with once it has run a script
+// * This is the status given to 'exit', or else that of the last command, as is available from '$?'
+fn exit_status(shell: &ShellState) -> i32 {
+    if shell.should_exit {
+        shell.exit_code
+    } else {
+        shell.last_exit_code
+    }
+}
+
+// Reads the lines of a script piped into the shell, one byte at a time
+// Commands in the script can read from the same input, so only the line which is about to be run
+// may be taken from it, as with 'read-file' run without a path or an executable such as 'head'
+fn stdin_lines() -> impl Iterator<Item = String> {
+    let mut input = Input::Inherit;
+    std::iter::from_fn(move || {
+        let mut line = Vec::new();
+        let mut byte = [0];
+        loop {
+            match input.read(&mut byte) {
+                Ok(1) if byte[0] != b'\n' => line.push(byte[0]),
+                Ok(1) => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                // * The last line does not have to end with a newline
+                _ if line.is_empty() => return None,
+                _ => break,
+            }
+        }
+
+        Some(String::from_utf8_lossy(&line).into_owned())
+    })
+}
+
+// Runs a script from the configuration directory, such as the rc script, if it exists
+fn run_script_file(dispatcher: &Dispatcher, shell: &RwLock<ShellState>, script: &Path) {
+    if !script.is_file() {
+        return;
     }
+
+    let shell = &mut shell.write().unwrap();
+    let status = dispatcher.eval_file(shell, script, &mut Streams::default());
+    handle_error(status, shell);
+}
+
+// Exits the shell with the given status, first running the logout script if it is a login shell
+fn exit_shell(dispatcher: &Dispatcher, shell: &RwLock<ShellState>, login: bool, status: i32) -> ! {
+    if login {
+        // * The logout script is run in full, even though 'exit' has already been called
+        shell.write().unwrap().should_exit = false;
+        let logout_file = shell.read().unwrap().paths.logout_file();
+        run_script_file(dispatcher, shell, &logout_file);
+    }
+
+    std::process::exit(status);
 }
 
 // Handles the return value of running a builtin or executable, setting flags and/or printing errors
 fn handle_error(potential_error: Result<()>, shell: &mut ShellState) {
-    shell.last_command_succeeded = potential_error.is_ok();
+    shell.set_status(&potential_error);
     if let Err(error) = potential_error {
-        eprintln!("{}", error);
+        shell.report_error(&error);
+    }
+}
+
+// Prints a message for each background job which has finished since the last prompt
+fn report_finished_jobs(shell: &mut ShellState) {
+    match shell.jobs.take_finished() {
+        Ok(jobs) => {
+            for job in jobs {
+                let notice = format!("[{}] {:<8} {}", job.id, job.status(), job.command);
+                shell.jobs.announce(notice);
+            }
+        }
+        Err(error) => eprintln!("{}", error),
     }
 }
 
 // Handles errors from which the shell cannot recover, mainly errors arising from shell setup
 fn crash_with_error(error: RushError) -> ! {
     eprintln!("{}", error);
-    // TODO: Maybe return status code for each error type?
-    std::process::exit(1);
+    std::process::exit(error.exit_code());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exits_scripts_with_the_status_of_the_last_command() {
+        let dispatcher = Dispatcher::default();
+        let run = |lines: &[&str]| {
+            let shell = RwLock::new(ShellState::for_tests());
+            let lines = lines.iter().map(|line| line.to_string());
+            run_script(&dispatcher, &shell, "-c", lines)
+        };
+
+        assert_eq!(run(&["false", "true"]), 0);
+        assert_eq!(run(&["true", "rush-test-unknown"]), 127);
+        assert_eq!(run(&["false", "exit", "true"]), 1);
+        assert_eq!(run(&["exit 3", "exit 4"]), 3);
+    }
+
+    #[test]
+    fn exits_rhai_scripts_with_the_status_of_the_last_command() {
+        let run = |script: &str| {
+            let shell = RwLock::new(ShellState::for_tests());
+            run_rhai_script(&shell, script, Vec::new())
+        };
+
+        assert_eq!(run(r#"command("true");"#), 0);
+        assert_eq!(run(r#"command("true"); command("false");"#), 1);
+        assert_eq!(run(r#"command("false"); command("true");"#), 0);
+        assert_eq!(run(r#"shell("exit 3");"#), 3);
+        assert_eq!(run(r#"throw "failed";"#), 1);
+    }
+
+    #[test]
+    fn runs_startup_scripts_only_if_they_exist() {
+        let shell = RwLock::new(ShellState::for_tests());
+        let dispatcher = Dispatcher::default();
+        let rc_file = shell.read().unwrap().paths.rc_file();
+
+        run_script_file(&dispatcher, &shell, &rc_file);
+        assert_eq!(shell.read().unwrap().last_exit_code, 0);
+
+        fs_err::create_dir_all(rc_file.parent().unwrap()).unwrap();
+        fs_err::write(&rc_file, "set STARTED=1\nfunction greet() { true; }\n").unwrap();
+        run_script_file(&dispatcher, &shell, &rc_file);
+        let shell = shell.read().unwrap();
+        assert_eq!(
+            shell.environment.get_variable("STARTED").as_deref(),
+            Some("1")
+        );
+        assert!(shell.functions.contains_key("greet"));
+    }
+
+    #[test]
+    fn parses_login_and_startup_options() {
+        let args = CommandLineArgs::try_parse_from(["rush", "-l", "--norc"]).unwrap();
+        assert!(args.login && args.no_rc && !args.no_profile);
+
+        // * Options after the script belong to the script, not to the shell
+        let args = CommandLineArgs::try_parse_from(["rush", "script.rush", "--login", "-c"]);
+        let args = args.unwrap();
+        assert!(!args.login && args.command.is_none());
+        assert_eq!(args.arguments, ["script.rush", "--login", "-c"]);
+    }
 }
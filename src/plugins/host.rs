@@ -46,7 +46,8 @@ impl PluginHost {
     fn spawn_runner(mut plugins: Vec<Box<dyn Plugin>>) -> mpsc::Sender<HookEvent> {
         let (tx, rx) = mpsc::channel::<HookEvent>();
 
-        spawn(move || loop {
+        // * The runner stops once the host is dropped, such as when the plugins are reloaded
+        spawn(move || {
             while let Ok(event) = rx.recv() {
                 let serialized_args = event
                     .hook_params
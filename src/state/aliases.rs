@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use toml_edit::{Item, Value};
+
+use super::config;
+use crate::errors::Result;
+
+/// Represents the aliases defined by the user, each of which expands to a command line
+// * Aliases are kept in order of name, so that they are listed and saved consistently
+#[derive(Clone, Default)]
+pub struct AliasTable {
+    aliases: BTreeMap<String, String>,
+    /// The aliases as they are in the config file, which tells which ones were changed in this
+    /// session
+    saved: BTreeMap<String, String>,
+    /// The config file which the aliases are saved to when asked, if any
+    file: Option<PathBuf>,
+}
+
+impl AliasTable {
+    /// Creates a table of the aliases from the '[aliases]' section of a config file, which any
+    /// changes can be saved back to
+    pub fn new(aliases: &BTreeMap<String, String>, filename: &Path) -> Self {
+        let aliases = valid_aliases(aliases);
+        Self {
+            saved: aliases.clone(),
+            aliases,
+            file: Some(filename.to_path_buf()),
+        }
+    }
+
+    /// Takes the aliases from a config file which has been read again, except for any which have
+    /// been changed in this session
+    pub fn reload(&mut self, aliases: &BTreeMap<String, String>) {
+        let saved = valid_aliases(aliases);
+        let mut reloaded = saved.clone();
+        for name in self.changed() {
+            match self.aliases.get(&name) {
+                Some(command) => reloaded.insert(name, command.clone()),
+                None => reloaded.remove(&name),
+            };
+        }
+
+        self.aliases = reloaded;
+        self.saved = saved;
+    }
+
+    /// Gets the command line which an alias expands to
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    /// Gets each alias and the command line it expands to, in order of name
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.aliases.iter()
+    }
+
+    /// Defines an alias for this session, replacing any existing alias with the same name
+    pub fn set(&mut self, name: &str, command: &str) -> Result<()> {
+        if !is_valid_alias_name(name) {
+            return Err(state_err!(InvalidAliasName: name));
+        }
+
+        self.aliases
+            .insert(name.to_owned(), command.trim().to_owned());
+        Ok(())
+    }
+
+    /// Removes an alias for this session
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        match self.aliases.remove(name) {
+            Some(_) => Ok(()),
+            None => Err(state_err!(UnknownAlias: name)),
+        }
+    }
+
+    /// Gets the names of the aliases which have been defined, changed, or removed in this session
+    fn changed(&self) -> Vec<String> {
+        let names: BTreeSet<&String> = self.aliases.keys().chain(self.saved.keys()).collect();
+        names
+            .into_iter()
+            .filter(|name| self.aliases.get(*name) != self.saved.get(*name))
+            .cloned()
+            .collect()
+    }
+
+    /// Writes the named aliases to the '[aliases]' section of their config file, if they have one,
+    /// or every alias changed in this session if no names are given
+    /// An alias which has been removed is removed from the file as well
+    // * The rest of the file is left as it is, including any comments
+    pub fn save(&mut self, names: &[String]) -> Result<()> {
+        let Some(filename) = &self.file else {
+            return Ok(());
+        };
+
+        let names = match names.is_empty() {
+            true => self.changed(),
+            false => names.to_vec(),
+        };
+
+        config::edit_file(filename, |document| {
+            for name in &names {
+                match self.aliases.get(name) {
+                    Some(command) => {
+                        config::set_value(document, "aliases", name, Value::from(command.as_str()))
+                    }
+                    None => {
+                        if let Some(section) = document
+                            .get_mut("aliases")
+                            .and_then(Item::as_table_like_mut)
+                        {
+                            section.remove(name);
+                        }
+                    }
+                }
+            }
+        })?;
+
+        for name in names {
+            match self.aliases.get(&name) {
+                Some(command) => self.saved.insert(name, command.clone()),
+                None => self.saved.remove(&name),
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Takes the aliases from a config file which can be used, trimming their command lines
+// * Aliases with names that could not be used as commands are skipped
+fn valid_aliases(aliases: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    aliases
+        .iter()
+        .filter(|(name, _)| is_valid_alias_name(name))
+        .map(|(name, command)| (name.clone(), command.trim().to_owned()))
+        .collect()
+}
+
+/// Whether an alias name can be used as a command name without being quoted
+fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace()
+                || matches!(c, '=' | '/' | '\'' | '"' | '\\' | '$' | '`')
+                || matches!(c, '|' | '&' | ';' | '<' | '>' | '(' | ')' | '#')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::config::Configuration;
+    use crate::state::TestDirectory;
+
+    /// Creates a config file in a test's directory with the given contents, returning its path
+    fn config_file(directory: &TestDirectory, contents: &str) -> PathBuf {
+        let filename = directory.join("config.toml");
+        fs_err::write(&filename, contents).unwrap();
+        filename
+    }
+
+    /// Creates a map of alias names to the command lines they expand to
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, command)| (name.to_string(), command.to_string()))
+            .collect()
+    }
+
+    /// Reads the aliases back from a config file
+    fn saved_aliases(filename: &Path) -> BTreeMap<String, String> {
+        Configuration::from_file(filename).unwrap().0.aliases
+    }
+
+    #[test]
+    fn skips_aliases_with_invalid_names() {
+        let aliases = aliases(&[("ll", " ls -l "), ("a b", "ls"), ("x=y", "ls"), ("", "ls")]);
+
+        let table = AliasTable::new(&aliases, Path::new("config.toml"));
+        let names: Vec<_> = table.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["ll"]);
+        assert_eq!(table.get("ll"), Some("ls -l"));
+    }
+
+    #[test]
+    fn saves_aliases_to_the_config_file() {
+        let contents = "# Settings\n[errors]\nshow = false # Quieter\n\n[aliases]\nold = \"ls\"\n";
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, contents);
+        let mut table = AliasTable::new(&saved_aliases(&filename), &filename);
+
+        // * Aliases are only written to the file when they are saved
+        table.set("ll", "ls -l").unwrap();
+        table.set("la", "ls -a").unwrap();
+        assert_eq!(saved_aliases(&filename), aliases(&[("old", "ls")]));
+
+        table.save(&["ll".to_owned()]).unwrap();
+        let expected = aliases(&[("ll", "ls -l"), ("old", "ls")]);
+        assert_eq!(saved_aliases(&filename), expected);
+
+        table.remove("old").unwrap();
+        table.save(&[]).unwrap();
+        let expected = aliases(&[("la", "ls -a"), ("ll", "ls -l")]);
+        assert_eq!(saved_aliases(&filename), expected);
+        assert!(table.get("old").is_none());
+        let contents = fs_err::read_to_string(&filename).unwrap();
+        assert!(contents.starts_with("# Settings\n[errors]\nshow = false # Quieter\n"));
+    }
+
+    #[test]
+    fn keeps_aliases_changed_in_the_session_when_reloaded() {
+        let file = aliases(&[("kept", "ls"), ("changed", "ls"), ("removed", "ls")]);
+        let mut table = AliasTable::new(&file, Path::new("config.toml"));
+        table.set("changed", "ls -a").unwrap();
+        table.set("session", "ls -l").unwrap();
+        table.remove("removed").unwrap();
+
+        let file = aliases(&[("kept", "pwd"), ("changed", "pwd"), ("removed", "pwd")]);
+        table.reload(&file);
+        let expected = aliases(&[("changed", "ls -a"), ("kept", "pwd"), ("session", "ls -l")]);
+        let aliases: BTreeMap<String, String> = table
+            .iter()
+            .map(|(name, command)| (name.clone(), command.clone()))
+            .collect();
+        assert_eq!(aliases, expected);
+    }
+
+    #[test]
+    fn rejects_invalid_names_and_unknown_aliases() {
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, "");
+        let mut table = AliasTable::new(&BTreeMap::new(), &filename);
+
+        for name in ["", "a b", "a|b", "$x", "a=b"] {
+            assert_eq!(
+                table.set(name, "ls").err().unwrap().name(),
+                "InvalidAliasName"
+            );
+        }
+
+        assert_eq!(
+            table.remove("missing").err().unwrap().name(),
+            "UnknownAlias"
+        );
+        assert!(saved_aliases(&filename).is_empty());
+    }
+}
@@ -1,13 +1,48 @@
-use std::{
-    io::{BufRead, BufReader},
-    path::PathBuf,
-};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::io::ErrorKind;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-use fs_err::File;
+use toml_edit::{table, DocumentMut, ImDocument, Item, Value};
 
 use crate::errors::{Handle, Result};
 
+/// The settings which can be shown, changed and saved with 'configure', by their names in the config
+/// file
+pub const SETTINGS: [&str; 7] = [
+    "prompt.truncation",
+    "prompt.multiline",
+    "history.directory-limit",
+    "errors.show",
+    "errors.verbose",
+    "files.no-clobber",
+    "files.no-match-error",
+];
+
+/// Where the current value of a setting came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingSource {
+    /// The setting has its default value
+    Default,
+    /// The setting was read from the config file, or has been saved to it
+    File,
+    /// The setting was changed since the shell started, and has not been saved
+    Session,
+}
+
+impl Display for SettingSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File => write!(f, "file"),
+            Self::Session => write!(f, "session"),
+        }
+    }
+}
+
 /// Represents any settings for the shell, most of which can be configured by the user
+#[derive(Clone)]
 pub struct Configuration {
     /// The truncation length for the prompt
     pub truncation: Option<usize>,
@@ -17,8 +52,20 @@ pub struct Configuration {
     pub multiline_prompt: bool,
     /// Whether or not to print out full error messages and status codes when a command fails
     pub show_errors: bool,
+    /// Whether to follow error messages with the cause of the error and an explanation of it
+    pub verbose_errors: bool,
+    /// Whether to refuse to overwrite existing files when redirecting output with '>'
+    pub no_clobber: bool,
+    /// Whether a glob pattern which matches no files is an error, rather than being used literally
+    pub no_match_error: bool,
     /// Paths to recursively search for plugins
     pub plugin_paths: Vec<PathBuf>,
+    /// The aliases defined in the configuration file, which the shell starts with
+    pub aliases: BTreeMap<String, String>,
+    /// The variables defined in the configuration file, which are exported when the shell starts
+    pub environment: BTreeMap<String, String>,
+    /// Where each setting which does not have its default value came from
+    sources: HashMap<&'static str, SettingSource>,
 }
 
 impl Default for Configuration {
@@ -28,76 +75,464 @@ impl Default for Configuration {
             history_limit: None,
             multiline_prompt: false,
             show_errors: true,
+            verbose_errors: false,
+            no_clobber: false,
+            no_match_error: false,
             plugin_paths: vec![],
+            aliases: BTreeMap::new(),
+            environment: BTreeMap::new(),
+            sources: HashMap::new(),
         }
     }
 }
 
 impl Configuration {
-    /// Scans a configuration file for settings and updates the configuration accordingly
-    pub fn from_file(filename: &str) -> Result<Self> {
-        let filename = PathBuf::from(filename);
-        let open_error_msg = format!("Config file '{}' could not be opened", filename.display());
-        let read_error_msg = format!("Config file '{}' could not be read", filename.display());
+    /// Reads the settings from a TOML configuration file, returning them along with a warning for
+    /// each setting which was ignored because it is unknown or has an invalid value
+    // * Only a file which is not valid TOML at all is an error, so that one mistake in a setting
+    // * does not throw away every other setting in the file
+    pub fn from_file(filename: &Path) -> Result<(Self, Vec<String>)> {
+        let source = fs_err::read_to_string(filename)
+            .replace_err(|| file_err!(CouldNotReadFile: filename))?;
+        let document = ImDocument::parse(source.as_str()).map_err(|error| {
+            let (line, column) = line_column(&source, error.span().unwrap_or_default().start);
+            file_err!(InvalidConfigFile: filename, line, column, error.message())
+        })?;
 
         let dirname = filename
             .parent()
             .replace_err(|| file_err!(CouldNotGetParent: filename))?;
-
+        let mut reader = ConfigReader {
+            filename,
+            source: &source,
+            warnings: Vec::new(),
+        };
         let mut config = Self::default();
-        let file = File::open(&filename)
-            .replace_err_with_msg(|| file_err!(CouldNotOpenFile: filename), &open_error_msg)?;
-        let reader = BufReader::new(file);
-
-        for line in reader.lines() {
-            let line = line
-                .replace_err_with_msg(|| file_err!(CouldNotReadFile: filename), &read_error_msg)?;
-            let tokens = line.split(": ").collect::<Vec<&str>>();
-            if tokens.len() != 2 {
-                return Err(file_err!(CouldNotReadFile: filename).set_context(&read_error_msg));
-            }
 
-            let (key, value) = (tokens[0], tokens[1]);
-
-            // ? Should these be underscores instead of hyphens?
-            match key {
-                "truncation" => {
-                    if let Ok(length) = value.parse::<usize>() {
-                        config.truncation = Some(length);
-                    } else if value == "false" {
-                        config.truncation = None;
-                    } else {
-                        return Err(
-                            file_err!(CouldNotReadFile: filename).set_context(&read_error_msg)
-                        );
+        for (section, item) in document.iter() {
+            let Some(table) = item.as_table_like() else {
+                reader.warn(item.span(), format!("'{}' is not a section", section));
+                continue;
+            };
+
+            for (key, item) in table.iter() {
+                let name = format!("{}.{}", section, key);
+                match name.as_str() {
+                    _ if SETTINGS.contains(&name.as_str()) => {
+                        if let Err(expected) = config.set(&name, item, SettingSource::File) {
+                            reader.invalid(&name, item, expected);
+                        }
                     }
-                }
-                "history-limit" => {
-                    if let Ok(limit) = value.parse::<usize>() {
-                        config.history_limit = Some(limit);
-                    } else if value == "false" {
-                        config.history_limit = None;
+                    "plugins.paths" => {
+                        // * Plugin paths are relative to the configuration file
+                        match item.as_array().map(|paths| {
+                            paths
+                                .iter()
+                                .map(|path| path.as_str())
+                                .collect::<Option<Vec<_>>>()
+                        }) {
+                            Some(Some(paths)) => config
+                                .plugin_paths
+                                .extend(paths.into_iter().map(|path| dirname.join(path))),
+                            _ => reader.invalid(&name, item, "a list of paths"),
+                        }
+                    }
+                    _ if section == "aliases" => match item.as_str() {
+                        Some(command) => {
+                            config.aliases.insert(key.to_owned(), command.to_owned());
+                        }
+                        None => reader.invalid(&name, item, "a command line in quotes"),
+                    },
+                    _ if section == "environment" => match item.as_value() {
+                        // * Numbers and booleans are allowed as well, and used as they are written
+                        Some(value) if !value.is_array() && !value.is_inline_table() => {
+                            let text = match value.as_str() {
+                                Some(text) => text.to_owned(),
+                                None => value.to_string().trim().to_owned(),
+                            };
+
+                            config.environment.insert(key.to_owned(), text);
+                        }
+                        _ => reader.invalid(&name, item, "a value in quotes"),
+                    },
+                    _ => {
+                        let span = table.key(key).and_then(|key| key.span());
+                        reader.warn(span, format!("Unknown setting '{}'", name));
                     }
                 }
-                "multiline-prompt" => {
-                    config.multiline_prompt = value.parse::<bool>().replace_err_with_msg(
-                        || file_err!(CouldNotReadFile: filename),
-                        &read_error_msg,
-                    )?;
-                }
-                "show-errors" => {
-                    config.show_errors = value.parse::<bool>().replace_err_with_msg(
-                        || file_err!(CouldNotReadFile: filename),
-                        &read_error_msg,
-                    )?;
-                }
-                "plugin-path" => {
-                    config.plugin_paths.push(dirname.join(value));
+            }
+        }
+
+        Ok((config, reader.warnings))
+    }
+
+    /// Changes a setting to a value from a config file, returning what the value should have been if
+    /// it is not valid for the setting
+    pub fn set(
+        &mut self,
+        key: &str,
+        item: &Item,
+        source: SettingSource,
+    ) -> std::result::Result<(), &'static str> {
+        const BOOL: &str = "true or false";
+        const LIMIT: &str = "a number which is not negative, or false";
+
+        match key {
+            "prompt.truncation" => self.truncation = read_limit(item).ok_or(LIMIT)?,
+            "prompt.multiline" => self.multiline_prompt = item.as_bool().ok_or(BOOL)?,
+            "history.directory-limit" => self.history_limit = read_limit(item).ok_or(LIMIT)?,
+            "errors.show" => self.show_errors = item.as_bool().ok_or(BOOL)?,
+            "errors.verbose" => self.verbose_errors = item.as_bool().ok_or(BOOL)?,
+            "files.no-clobber" => self.no_clobber = item.as_bool().ok_or(BOOL)?,
+            "files.no-match-error" => self.no_match_error = item.as_bool().ok_or(BOOL)?,
+            _ => return Err("a known setting"),
+        }
+
+        self.mark(key, source);
+        Ok(())
+    }
+
+    /// Takes the settings from a config file which has been read again, except for any which have
+    /// been changed in this session
+    pub fn reload(&mut self, config: Self) {
+        for key in SETTINGS {
+            if self.source(key) == SettingSource::Session {
+                continue;
+            }
+
+            // * The value came from a valid configuration, so it cannot be rejected
+            if let Some(value) = config.get(key) {
+                let _ = self.set(key, &Item::Value(value), config.source(key));
+            }
+        }
+
+        self.plugin_paths = config.plugin_paths;
+        self.aliases = config.aliases;
+        self.environment = config.environment;
+    }
+
+    /// Gets the current value of a setting, as it would be written in a config file
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let value = match key {
+            "prompt.truncation" => limit_value(self.truncation),
+            "prompt.multiline" => Value::from(self.multiline_prompt),
+            "history.directory-limit" => limit_value(self.history_limit),
+            "errors.show" => Value::from(self.show_errors),
+            "errors.verbose" => Value::from(self.verbose_errors),
+            "files.no-clobber" => Value::from(self.no_clobber),
+            "files.no-match-error" => Value::from(self.no_match_error),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Changes a setting back to its default value
+    // * A setting which came from the config file is now different from it, so it counts as changed
+    pub fn reset(&mut self, key: &str) -> Result<()> {
+        let value = Self::default()
+            .get(key)
+            .replace_err(|| state_err!(UnknownSetting: key))?;
+        let source = match self.source(key) {
+            SettingSource::Default => SettingSource::Default,
+            _ => SettingSource::Session,
+        };
+
+        self.set(key, &Item::Value(value), source)
+            .map_err(|_| state_err!(UnknownSetting: key))
+    }
+
+    /// Gets where the current value of a setting came from
+    pub fn source(&self, key: &str) -> SettingSource {
+        self.sources
+            .get(key)
+            .copied()
+            .unwrap_or(SettingSource::Default)
+    }
+
+    /// Records where the current value of a setting came from
+    pub fn mark(&mut self, key: &str, source: SettingSource) {
+        if let Some(key) = SETTINGS.into_iter().find(|setting| *setting == key) {
+            self.sources.insert(key, source);
+        }
+    }
+
+    /// Writes every setting which has been changed in this session to a config file, after which
+    /// they count as coming from the file
+    pub fn save(&mut self, filename: &Path) -> Result<()> {
+        let changed = SETTINGS
+            .into_iter()
+            .filter(|key| self.source(key) == SettingSource::Session)
+            .collect::<Vec<_>>();
+
+        edit_file(filename, |document| {
+            for key in &changed {
+                if let (Some((section, name)), Some(value)) = (key.split_once('.'), self.get(key)) {
+                    set_value(document, section, name, value);
                 }
-                _ => return Err(file_err!(CouldNotReadFile: filename).set_context(&read_error_msg)),
             }
+        })?;
+
+        for key in changed {
+            self.mark(key, SettingSource::File);
         }
 
-        Ok(config)
+        Ok(())
+    }
+}
+
+/// Edits a configuration file in place, so that any comments and formatting in it are kept
+/// A file which does not exist yet is created, along with its directory
+pub fn edit_file(filename: &Path, edit: impl FnOnce(&mut DocumentMut)) -> Result<()> {
+    let source = match fs_err::read_to_string(filename) {
+        Ok(source) => source,
+        Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(file_err!(CouldNotReadFile: filename).set_source(&error)),
+    };
+
+    let mut document = source.parse::<DocumentMut>().map_err(|error| {
+        let (line, column) = line_column(&source, error.span().unwrap_or_default().start);
+        file_err!(InvalidConfigFile: filename, line, column, error.message())
+    })?;
+
+    edit(&mut document);
+    if let Some(directory) = filename.parent() {
+        fs_err::create_dir_all(directory)
+            .replace_err(|| file_err!(CouldNotCreateDirectory: directory))?;
+    }
+
+    fs_err::write(filename, document.to_string())
+        .replace_err(|| file_err!(CouldNotWriteFile: filename))
+}
+
+/// Reads settings from a configuration file, collecting warnings about any which are ignored
+struct ConfigReader<'a> {
+    filename: &'a Path,
+    source: &'a str,
+    warnings: Vec<String>,
+}
+
+impl ConfigReader<'_> {
+    /// Records a warning about the part of the file at the given position
+    fn warn(&mut self, span: Option<Range<usize>>, message: String) {
+        let (line, column) = line_column(self.source, span.unwrap_or_default().start);
+        self.warnings.push(format!(
+            "{}:{}:{}: {}",
+            self.filename.display(),
+            line,
+            column,
+            message
+        ));
+    }
+
+    /// Records a warning about a setting whose value is not of the expected type
+    fn invalid(&mut self, name: &str, item: &Item, expected: &str) {
+        self.warn(
+            item.span(),
+            format!("Setting '{}' must be {}, so it was ignored", name, expected),
+        );
+    }
+}
+
+/// Reads a setting which is either a number, or false to disable it
+fn read_limit(item: &Item) -> Option<Option<usize>> {
+    match (item.as_integer(), item.as_bool()) {
+        (Some(number), _) => usize::try_from(number).ok().map(Some),
+        (_, Some(false)) => Some(None),
+        _ => None,
+    }
+}
+
+/// Writes a setting which is either a number, or disabled
+fn limit_value(limit: Option<usize>) -> Value {
+    match limit.and_then(|limit| i64::try_from(limit).ok()) {
+        Some(limit) => Value::from(limit),
+        None => Value::from(false),
+    }
+}
+
+/// Sets a value in a section of a config document, creating the section if it does not exist
+// * Any comments next to an existing value are kept, and a section which is not a table is replaced
+pub fn set_value(document: &mut DocumentMut, section: &str, key: &str, mut value: Value) {
+    let section = document.entry(section).or_insert_with(table);
+    if section.as_table_like().is_none() {
+        *section = table();
+    }
+
+    if let Some(section) = section.as_table_like_mut() {
+        match section.get_mut(key).and_then(Item::as_value_mut) {
+            Some(existing) => {
+                *value.decor_mut() = existing.decor().clone();
+                *existing = value;
+            }
+            None => {
+                section.insert(key, Item::Value(value));
+            }
+        }
+    }
+}
+
+/// Gets the line and column of a position in a file, both counted from 1
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset).unwrap_or(source);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TestDirectory;
+
+    /// Writes a config file in a test's directory with the given contents, returning its path
+    fn config_file(directory: &TestDirectory, contents: &str) -> PathBuf {
+        let filename = directory.join("config.toml");
+        fs_err::write(&filename, contents).unwrap();
+        filename
+    }
+
+    #[test]
+    fn reads_settings_from_a_file() {
+        let contents = concat!(
+            "[prompt]\ntruncation = 3\nmultiline = true\n",
+            "[history]\ndirectory-limit = false\n",
+            "[errors]\nshow = false\nverbose = true\n",
+            "[files]\nno-clobber = true\nno-match-error = true\n",
+            "[plugins]\npaths = [\"plugins\"]\n",
+            "[aliases]\nll = \"ls -l\"\n",
+            "[environment]\nEDITOR = \"vim\"\nLEVEL = 3\nDEBUG = true\n",
+        );
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, contents);
+
+        let (config, warnings) = Configuration::from_file(&filename).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.truncation, Some(3));
+        assert!(config.multiline_prompt);
+        assert_eq!(config.history_limit, None);
+        assert!(!config.show_errors && config.verbose_errors);
+        assert!(config.no_clobber && config.no_match_error);
+        assert_eq!(config.plugin_paths, [filename.with_file_name("plugins")]);
+        assert_eq!(config.aliases.get("ll").map(String::as_str), Some("ls -l"));
+
+        let environment = [("DEBUG", "true"), ("EDITOR", "vim"), ("LEVEL", "3")];
+        let environment = environment.map(|(name, value)| (name.to_owned(), value.to_owned()));
+        assert_eq!(config.environment, BTreeMap::from(environment));
+
+        assert_eq!(config.source("errors.show"), SettingSource::File);
+        assert_eq!(config.source("prompt.truncation"), SettingSource::File);
+        let empty = TestDirectory::new();
+        let filename = config_file(&empty, "");
+        let (config, _) = Configuration::from_file(&filename).unwrap();
+        assert_eq!(config.source("errors.show"), SettingSource::Default);
+    }
+
+    #[test]
+    fn warns_about_unknown_and_invalid_settings() {
+        let contents = concat!(
+            "title = \"rush\"\n",
+            "[errors]\nshow = \"no\"\nverbose = true\ncolour = true\n",
+            "[prompt]\ntruncation = -1\n",
+            "[aliases]\nll = 3\n",
+        );
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, contents);
+
+        let (config, warnings) = Configuration::from_file(&filename).unwrap();
+        let name = filename.display();
+        let expected = [
+            format!("{}:1:9: 'title' is not a section", name),
+            format!(
+                "{}:3:8: Setting 'errors.show' must be true or false, so it was ignored",
+                name
+            ),
+            format!("{}:5:1: Unknown setting 'errors.colour'", name),
+            format!(
+                "{}:7:14: Setting 'prompt.truncation' must be a number which is not negative, or \
+                 false, so it was ignored",
+                name
+            ),
+            format!(
+                "{}:9:6: Setting 'aliases.ll' must be a command line in quotes, so it was ignored",
+                name
+            ),
+        ];
+        assert_eq!(warnings, expected);
+
+        // * The valid settings in the file are still used
+        assert!(config.show_errors && config.verbose_errors);
+        assert_eq!(config.truncation, None);
+        assert!(config.aliases.is_empty());
+    }
+
+    #[test]
+    fn reports_where_files_are_not_valid_toml() {
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, "[errors]\nshow =\n");
+
+        let error = Configuration::from_file(&filename).err().unwrap();
+        assert_eq!(error.name(), "InvalidConfigFile");
+        let message = format!(
+            "Config file '{}' is invalid at line 2, column 7",
+            filename.display()
+        );
+        assert!(error.to_string().starts_with(&message), "{}", error);
+
+        let missing = filename.with_file_name("missing.toml");
+        let error = Configuration::from_file(&missing).err().unwrap();
+        assert_eq!(error.name(), "CouldNotReadFile");
+    }
+
+    #[test]
+    fn keeps_settings_changed_in_the_session_when_reloading() {
+        let contents = "[errors]\nshow = false\nverbose = true\n";
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, contents);
+        let (mut config, _) = Configuration::from_file(&filename).unwrap();
+
+        config.reset("errors.show").unwrap();
+        config.reset("files.no-clobber").unwrap();
+        assert!(config.show_errors);
+        assert_eq!(config.source("errors.show"), SettingSource::Session);
+        assert_eq!(config.source("files.no-clobber"), SettingSource::Default);
+        assert_eq!(
+            config.reset("errors.colour").err().unwrap().name(),
+            "UnknownSetting"
+        );
+
+        fs_err::write(&filename, "[errors]\nshow = false\nverbose = false\n").unwrap();
+        config.reload(Configuration::from_file(&filename).unwrap().0);
+        assert!(config.show_errors && !config.verbose_errors);
+        assert_eq!(config.source("errors.show"), SettingSource::Session);
+        assert_eq!(config.source("errors.verbose"), SettingSource::File);
+    }
+
+    #[test]
+    fn saves_settings_changed_in_the_session_keeping_comments() {
+        let contents = "# My settings\n[errors]\nshow = true # Always\nverbose = false\n";
+        let directory = TestDirectory::new();
+        let filename = config_file(&directory, contents);
+        let (mut config, _) = Configuration::from_file(&filename).unwrap();
+        config.show_errors = false;
+        config.mark("errors.show", SettingSource::Session);
+        config.no_clobber = true;
+        config.mark("files.no-clobber", SettingSource::Session);
+        // * Settings which were not changed in the session are left as they are in the file
+        config.verbose_errors = true;
+
+        config.save(&filename).unwrap();
+        assert_eq!(config.source("errors.show"), SettingSource::File);
+        let contents = fs_err::read_to_string(&filename).unwrap();
+        assert!(contents.starts_with("# My settings\n[errors]\nshow = false # Always\n"));
+
+        let (saved, _) = Configuration::from_file(&filename).unwrap();
+        assert!(!saved.show_errors && !saved.verbose_errors && saved.no_clobber);
     }
 }
@@ -1,10 +1,11 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::env;
 use std::fmt::{Display, Formatter};
 use std::path::{Path as StdPath, PathBuf};
 
 use bitflags::bitflags;
 use clap::ValueEnum;
+use nix::unistd::{getuid, User};
 
 use super::path::Path;
 use crate::errors::{Handle, Result};
@@ -60,6 +61,7 @@ bitflags! {
 // * Environment variables are represented in all caps by convention,
 // * any fields that are not actual environment variables are represented in the usual snake_case
 #[allow(non_snake_case)]
+#[derive(Clone)]
 pub struct Environment {
     pub USER: String,
     pub HOME: PathBuf,
@@ -70,16 +72,28 @@ pub struct Environment {
     // ? Should these be `ShellState` fields instead?
     backward_directories: VecDeque<Path>,
     forward_directories: VecDeque<Path>,
-    #[allow(dead_code)]
     custom_variables: HashMap<String, String>,
+    /// The names of variables which are passed to commands run by the shell
+    exported_variables: HashSet<String>,
+    /// The names of variables inherited from the shell's own environment which have been unset
+    removed_variables: HashSet<String>,
+}
+
+/// The state of a variable before it was set for a single command
+pub struct SavedVariable {
+    name: String,
+    value: Option<String>,
+    exported: bool,
 }
 
 #[allow(non_snake_case)]
 impl Environment {
     pub fn new() -> Result<Self> {
-        let USER = get_parent_env_var(EnvVariable::USER)?;
+        // * USER is not set in every environment, such as under cron or in containers, so the name
+        // * is looked up from the password database instead, and left empty if that fails too
+        let USER = get_parent_env_var(EnvVariable::USER).unwrap_or_else(|_| current_user_name());
         let HOME = PathBuf::from(get_parent_env_var(EnvVariable::HOME)?);
-        let CWD = Path::try_from_str(get_parent_env_var(EnvVariable::CWD)?.as_str(), Some(&HOME))?;
+        let CWD = Path::try_from_str(get_parent_env_var(EnvVariable::CWD)?.as_str())?;
         let PATH = convert_path_var(get_parent_env_var(EnvVariable::PATH)?.as_str())?;
 
         Ok(Self {
@@ -90,6 +104,8 @@ impl Environment {
             backward_directories: VecDeque::new(),
             forward_directories: VecDeque::new(),
             custom_variables: HashMap::new(),
+            exported_variables: HashSet::new(),
+            removed_variables: HashSet::new(),
         })
     }
 
@@ -112,10 +128,186 @@ impl Environment {
         Ok(())
     }
 
+    /// Updates the shell process's environment variables to match the internal representation,
+    /// which is needed after anything that shares the process (such as a subshell) may have changed them
+    pub fn sync_process_env(&self) -> Result<()> {
+        self.update_process_env_vars(EnvVariables::all())
+    }
+
+    /// Sets a variable, parsing the value into the typed form if it is one of the `EnvVariable`s
+    pub fn set_variable(&mut self, name: &str, value: &str) -> Result<()> {
+        match typed_variable(name) {
+            Some(EnvVariable::USER) => self.USER = value.to_owned(),
+            Some(EnvVariable::HOME) => self.HOME = PathBuf::from(value),
+            Some(EnvVariable::PATH) => self.PATH = convert_path_var(value)?,
+            // * CWD has to match the directory the shell is in, so it is only changed by changing
+            // * directory
+            Some(EnvVariable::CWD) => return Err(state_err!(ProtectedVariable: name)),
+            None => return self.set_custom_variable(name, value),
+        }
+
+        Ok(())
+    }
+
+    /// Sets a shell variable which is not one of the typed `EnvVariable`s
+    // * The variable stays local to the shell unless it has been exported, or was inherited from the
+    // * shell's own environment (POSIX marks every variable inherited at startup for export)
+    // * An inherited variable which was unset stays out of the environment of commands until it is
+    // * exported again
+    fn set_custom_variable(&mut self, name: &str, value: &str) -> Result<()> {
+        check_custom_variable_name(name)?;
+        if env::var_os(name).is_some() && !self.removed_variables.contains(name) {
+            self.exported_variables.insert(name.to_owned());
+        }
+
+        self.custom_variables
+            .insert(name.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    /// Marks a variable to be passed to commands run by the shell, including any later value it is given
+    pub fn export_variable(&mut self, name: &str) -> Result<()> {
+        // * The typed variables are always passed to commands, so there is nothing to do for them
+        if typed_variable(name).is_some() {
+            return Ok(());
+        }
+
+        check_custom_variable_name(name)?;
+        self.exported_variables.insert(name.to_owned());
+        Ok(())
+    }
+
+    /// Removes a variable, including one inherited from the shell's own environment
+    pub fn unset_variable(&mut self, name: &str) -> Result<()> {
+        check_custom_variable_name(name)?;
+        self.custom_variables.remove(name);
+        self.exported_variables.remove(name);
+        if env::var_os(name).is_some() {
+            self.removed_variables.insert(name.to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Sets and exports a variable for the duration of a single command, such as 'FOO=1' in
+    /// 'FOO=1 source-file script', returning its previous state so that it can be restored after
+    pub fn set_temporary_variable(&mut self, name: &str, value: &str) -> Result<SavedVariable> {
+        // * The typed variables are always exported, so only their values have to be restored
+        if typed_variable(name).is_some() {
+            let saved = SavedVariable {
+                name: name.to_owned(),
+                value: self.get_variable(name),
+                exported: true,
+            };
+
+            self.set_variable(name, value)?;
+            return Ok(saved);
+        }
+
+        check_custom_variable_name(name)?;
+        let saved = SavedVariable {
+            name: name.to_owned(),
+            value: self.custom_variables.get(name).cloned(),
+            exported: self.exported_variables.contains(name),
+        };
+
+        self.custom_variables
+            .insert(name.to_owned(), value.to_owned());
+        self.exported_variables.insert(name.to_owned());
+        Ok(saved)
+    }
+
+    /// Puts a variable set by `set_temporary_variable` back the way it was before
+    // * A variable which was unset is kept out of commands again once it is no longer exported
+    pub fn restore_variable(&mut self, saved: SavedVariable) {
+        if typed_variable(&saved.name).is_some() {
+            // * The value was read from the typed variable itself, so it can always be set again
+            if let Some(value) = &saved.value {
+                let _ = self.set_variable(&saved.name, value);
+            }
+
+            return;
+        }
+
+        match saved.value {
+            Some(value) => self.custom_variables.insert(saved.name.clone(), value),
+            None => self.custom_variables.remove(&saved.name),
+        };
+
+        if !saved.exported {
+            self.exported_variables.remove(&saved.name);
+        }
+    }
+
+    /// Gets the shell's own variables, sorted by name, along with whether each one is exported
+    pub fn custom_variables(&self) -> Vec<(&str, &str, bool)> {
+        let mut variables: Vec<(&str, &str, bool)> = self
+            .custom_variables
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_str(),
+                    value.as_str(),
+                    self.exported_variables.contains(name),
+                )
+            })
+            .collect();
+        variables.sort();
+        variables
+    }
+
+    /// Gets the names and values of the variables to pass to commands run by the shell,
+    /// on top of the environment the shell was started with
+    // * The typed variables are always passed on, so that changes made to them reach commands too
+    pub fn exported_variables(&self) -> Vec<(String, String)> {
+        let typed = EnvVariable::value_variants().iter().filter_map(|variable| {
+            let name = variable.to_legacy_string();
+            self.get_variable(&name).map(|value| (name, value))
+        });
+
+        typed
+            .chain(
+                self.custom_variables
+                    .iter()
+                    .filter(|(name, _)| self.exported_variables.contains(*name))
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            )
+            .collect()
+    }
+
+    /// Gets the names of the variables which should not be passed on from the shell's own environment
+    // * A variable which was unset and then exported again is passed on with its new value instead
+    pub fn removed_variables(&self) -> Vec<String> {
+        self.removed_variables.iter().cloned().collect()
+    }
+
+    /// Gets the value of a variable, looking in the shell's own variables first, then the typed
+    /// `EnvVariable`s, and finally the environment the shell was started with
+    pub fn get_variable(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.custom_variables.get(name) {
+            return Some(value.clone());
+        }
+
+        match typed_variable(name) {
+            Some(EnvVariable::USER) => Some(self.USER.clone()),
+            Some(EnvVariable::HOME) => Some(self.HOME.display().to_string()),
+            Some(EnvVariable::CWD) => Some(self.CWD.to_string()),
+            Some(EnvVariable::PATH) => Some(
+                self.PATH
+                    .iter()
+                    .map(Path::to_string)
+                    .collect::<Vec<String>>()
+                    .join(":"),
+            ),
+            None if self.removed_variables.contains(name) => None,
+            None => env::var(name).ok(),
+        }
+    }
+
     /// Sets the current working directory and stores the previous working directory
     pub fn set_CWD(&mut self, new_directory: &StdPath, history_limit: Option<usize>) -> Result<()> {
         let starting_directory = self.CWD.clone();
-        let new_directory = Path::try_from_path(new_directory, Some(&self.HOME))?;
+        let new_directory = Path::try_from_path(new_directory)?;
 
         // Add the old directory to the history, avoiding duplicates
         if new_directory != starting_directory {
@@ -194,27 +386,220 @@ impl Environment {
         &self.CWD
     }
 
+    /// Getter for the most recent directory in the back history
+    pub fn last_directory(&self) -> Option<&Path> {
+        self.backward_directories.back()
+    }
+
     /// Getter for the PATH
     pub fn PATH(&self) -> &VecDeque<Path> {
         &self.PATH
     }
 }
 
+/// Finds the typed variable with the given name
+// * Both the shell's names and the legacy names (such as PWD) refer to the typed variables
+fn typed_variable(name: &str) -> Option<EnvVariable> {
+    EnvVariable::value_variants()
+        .iter()
+        .find(|variable| variable.to_string() == name || variable.to_legacy_string() == name)
+        .copied()
+}
+
+/// Checks that a name can be used for one of the shell's own variables
+fn check_custom_variable_name(name: &str) -> Result<()> {
+    let mut characters = name.chars();
+    let valid_start = characters
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !valid_start || !characters.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(state_err!(InvalidVariableName: name));
+    }
+
+    match typed_variable(name) {
+        Some(_) => Err(state_err!(ProtectedVariable: name)),
+        None => Ok(()),
+    }
+}
+
 /// Gets the environment variables from the parent process during shell initialization
 fn get_parent_env_var(variable: EnvVariable) -> Result<String> {
     std::env::var(variable.to_legacy_string()).replace_err(|| state_err!(MissingEnv: variable))
 }
 
+/// Gets the name of the user which the shell is running as, or an empty name if it is not known
+fn current_user_name() -> String {
+    User::from_uid(getuid())
+        .ok()
+        .flatten()
+        .map(|user| user.name)
+        .unwrap_or_default()
+}
+
 /// Converts the PATH environment variable from a string to a collection of `Path`s
 fn convert_path_var(path: &str) -> Result<VecDeque<Path>> {
     let mut paths = VecDeque::new();
     let path_strings = path.split(':').collect::<Vec<&str>>();
 
     for path_string in path_strings {
-        if let Ok(path) = Path::try_from_str(path_string, None) {
+        if let Ok(path) = Path::try_from_str(path_string) {
             paths.push_back(path);
         }
     }
 
     Ok(paths)
 }
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+impl Environment {
+    /// Creates an environment for tests, which uses placeholders for any of the typed variables
+    /// missing from the shell's own environment instead of failing
+    // * The values match the process where they can, since syncing the process environment (as a
+    // * subshell does) would otherwise change it for every other test
+    pub fn for_tests() -> Self {
+        let current = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        let PATH = env::var_os("PATH")
+            .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|path| Path::try_from_path(path).ok())
+            .collect();
+
+        Self {
+            USER: env::var("USER").unwrap_or_else(|_| String::from("user")),
+            HOME: env::var_os("HOME").map_or_else(|| PathBuf::from("/"), PathBuf::from),
+            CWD: Path::try_from_path(&current).unwrap(),
+            PATH,
+            backward_directories: VecDeque::new(),
+            forward_directories: VecDeque::new(),
+            custom_variables: HashMap::new(),
+            exported_variables: HashSet::new(),
+            removed_variables: HashSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::*;
+    use crate::exec::{Executable, Input, Output, Streams};
+
+    /// Gets the variables which a command run by the shell actually receives
+    fn child_variables(environment: &Environment) -> HashMap<String, String> {
+        // * The command is found with the PATH of the tests, since the environment may change it
+        let env = Path::try_resolve_executable("env", Environment::for_tests().PATH()).unwrap();
+        let (mut reader, writer) = io::pipe().unwrap();
+        let mut streams = Streams::new(Input::Inherit, Output::Pipe(writer));
+        let mut child = Executable::new(env)
+            .with_environment(environment)
+            .spawn(vec!["env"], &mut streams, None)
+            .unwrap();
+        // * The shell's copy of the writer has to be closed for the output to end
+        drop(streams);
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        child.wait().unwrap();
+        output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    /// Finds a variable which the shell inherited and which it can change, without having to
+    /// change the environment of the tests to create one
+    fn inherited_variable() -> String {
+        env::vars()
+            .map(|(name, _)| name)
+            .find(|name| {
+                typed_variable(name).is_none()
+                    && Environment::for_tests().set_variable(name, "").is_ok()
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_shell_variables_local_until_exported() {
+        let mut environment = Environment::for_tests();
+        environment
+            .set_variable("RUSH_TEST_LOCAL", "value")
+            .unwrap();
+        assert_eq!(
+            environment.get_variable("RUSH_TEST_LOCAL").as_deref(),
+            Some("value")
+        );
+        assert!(!child_variables(&environment).contains_key("RUSH_TEST_LOCAL"));
+
+        environment.export_variable("RUSH_TEST_LOCAL").unwrap();
+        let variables = child_variables(&environment);
+        assert_eq!(
+            variables.get("RUSH_TEST_LOCAL").map(String::as_str),
+            Some("value")
+        );
+    }
+
+    #[test]
+    fn passes_changes_to_inherited_variables_to_commands() {
+        let name = inherited_variable();
+        let mut environment = Environment::for_tests();
+        environment.set_variable(&name, "rush-test-value").unwrap();
+        let variables = child_variables(&environment);
+        assert_eq!(
+            variables.get(&name).map(String::as_str),
+            Some("rush-test-value")
+        );
+    }
+
+    #[test]
+    fn keeps_unset_variables_from_commands_until_exported() {
+        let name = inherited_variable();
+        let mut environment = Environment::for_tests();
+        environment.unset_variable(&name).unwrap();
+        assert_eq!(environment.get_variable(&name), None);
+        assert!(!child_variables(&environment).contains_key(&name));
+
+        environment.set_variable(&name, "new").unwrap();
+        assert_eq!(environment.get_variable(&name).as_deref(), Some("new"));
+        assert!(!child_variables(&environment).contains_key(&name));
+
+        environment.export_variable(&name).unwrap();
+        let variables = child_variables(&environment);
+        assert_eq!(variables.get(&name).map(String::as_str), Some("new"));
+    }
+
+    #[test]
+    fn sets_typed_variables_and_passes_them_to_commands() {
+        let mut environment = Environment::for_tests();
+        let directory = env::temp_dir().canonicalize().unwrap();
+        let path = format!("/:{}:/rush-test-missing", directory.display());
+        environment.set_variable("PATH", &path).unwrap();
+        environment.set_variable("HOME", "/rush-test-home").unwrap();
+        assert_eq!(environment.PATH().len(), 2);
+
+        let variables = child_variables(&environment);
+        let expected = format!("/:{}", directory.display());
+        assert_eq!(variables.get("PATH"), Some(&expected));
+        assert_eq!(
+            variables.get("HOME").map(String::as_str),
+            Some("/rush-test-home")
+        );
+
+        let saved = environment.set_temporary_variable("PATH", "/").unwrap();
+        assert_eq!(environment.get_variable("PATH").as_deref(), Some("/"));
+        environment.restore_variable(saved);
+        assert_eq!(environment.get_variable("PATH"), Some(expected));
+    }
+
+    #[test]
+    fn rejects_invalid_and_protected_variable_names() {
+        let mut environment = Environment::for_tests();
+        assert!(environment.set_variable("1ABC", "value").is_err());
+        assert!(environment.set_variable("PWD", "value").is_err());
+        assert!(environment.set_variable("CWD", "value").is_err());
+        assert!(environment.unset_variable("PATH").is_err());
+    }
+}
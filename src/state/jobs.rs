@@ -0,0 +1,507 @@
+use std::fmt::{Display, Formatter};
+use std::io::{self, IsTerminal};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use nix::errno::Errno;
+use nix::sys::signal::{killpg, signal, SigHandler, SigSet, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{getpid, setpgid, tcsetpgrp, Pid};
+
+use crate::errors::{Handle, Result};
+
+/// The signals sent by the terminal, which the shell has to survive while it controls the terminal
+const TERMINAL_SIGNALS: [Signal; 5] = [
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGTSTP,
+    Signal::SIGTTIN,
+    Signal::SIGTTOU,
+];
+
+/// Represents the state of a job as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Stopped,
+    /// The job has finished, with the exit code of its last process
+    Done(i32),
+    /// The job has finished, but how is not known, since it was waited for somewhere else
+    Unknown,
+}
+
+impl Display for JobStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // * pad() is used so that the status can be aligned in the job list
+        match self {
+            Self::Running => f.pad("Running"),
+            Self::Stopped => f.pad("Stopped"),
+            Self::Done(0) => f.pad("Done"),
+            Self::Done(code) => f.pad(&format!("Exit {}", code)),
+            Self::Unknown => f.pad("Unknown"),
+        }
+    }
+}
+
+impl JobStatus {
+    /// Converts the status into a `Result`, as though the job were a single command
+    pub fn into_result(self) -> Result<()> {
+        match self {
+            // * A job which was stopped has already been reported, and can still succeed once resumed
+            Self::Running | Self::Stopped | Self::Done(0) => Ok(()),
+            Self::Done(code) => Err(executable_err!(FailedToExecute: code as isize)),
+            // * Success cannot be assumed for a process whose exit code was lost
+            Self::Unknown => Err(executable_err!(CouldNotWait)),
+        }
+    }
+
+    /// Whether the job has finished, whether or not its exit code is known
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Done(_) | Self::Unknown)
+    }
+}
+
+/// A process belonging to a job, along with its last known status
+#[derive(Debug, Clone)]
+struct JobProcess {
+    pid: Pid,
+    status: JobStatus,
+    /// The signal which stopped the process, if it is stopped
+    stop_signal: Option<Signal>,
+}
+
+/// Represents a pipeline which was started in its own process group, usually in the background
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: usize,
+    /// The process group which all of the job's processes belong to
+    pub pgid: Pid,
+    /// The text of the command which started the job
+    pub command: String,
+    processes: Vec<JobProcess>,
+}
+
+impl Job {
+    /// Creates a job made up of the given processes, which is given an ID once it is added to a table
+    pub fn new(pids: Vec<u32>, command: &str) -> Self {
+        let processes: Vec<JobProcess> = pids
+            .into_iter()
+            .map(|pid| JobProcess {
+                pid: Pid::from_raw(pid as i32),
+                status: JobStatus::Running,
+                stop_signal: None,
+            })
+            .collect();
+
+        // * The first process in a pipeline is the leader of the process group
+        let pgid = processes
+            .first()
+            .map_or(Pid::from_raw(0), |process| process.pid);
+
+        Self {
+            id: 0,
+            pgid,
+            command: command.to_owned(),
+            processes,
+        }
+    }
+
+    /// Gets the status of the job, which is decided by the statuses of its processes
+    pub fn status(&self) -> JobStatus {
+        if self
+            .processes
+            .iter()
+            .any(|process| process.status == JobStatus::Stopped)
+        {
+            JobStatus::Stopped
+        } else if self
+            .processes
+            .iter()
+            .any(|process| process.status == JobStatus::Running)
+        {
+            JobStatus::Running
+        } else {
+            // * As with pipelines, the status of a job is the status of its last process
+            self.processes
+                .last()
+                .map_or(JobStatus::Done(0), |process| process.status)
+        }
+    }
+
+    /// Gets the status of each process in the job, in the order they were started
+    pub fn process_statuses(&self) -> Vec<JobStatus> {
+        self.processes
+            .iter()
+            .map(|process| process.status)
+            .collect()
+    }
+
+    /// Sends a signal to every process in the job
+    pub fn signal(&self, signal: Signal) -> Result<()> {
+        killpg(self.pgid, signal).replace_err(|| state_err!(CouldNotSignalJob: self.id.to_string()))
+    }
+
+    /// Continues the job if it was stopped
+    pub fn resume(&mut self) -> Result<()> {
+        self.signal(Signal::SIGCONT)?;
+        for process in &mut self.processes {
+            if process.status == JobStatus::Stopped {
+                process.status = JobStatus::Running;
+                process.stop_signal = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Blocks until every process in the job has either finished or been stopped
+    pub fn wait(&mut self) -> Result<JobStatus> {
+        for process in &mut self.processes {
+            if process.status == JobStatus::Running {
+                let status = waitpid(process.pid, Some(WaitPidFlag::WUNTRACED));
+                process.update(status)?;
+            }
+        }
+
+        Ok(self.status())
+    }
+
+    /// Checks whether any of the job's processes have changed state, without blocking
+    fn poll(&mut self) -> Result<()> {
+        let flags = WaitPidFlag::WNOHANG | WaitPidFlag::WUNTRACED | WaitPidFlag::WCONTINUED;
+        for process in &mut self.processes {
+            if !process.status.is_finished() {
+                let status = waitpid(process.pid, Some(flags));
+                process.update(status)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the job was stopped for trying to use the terminal while it was in the background
+    fn stopped_by_terminal(&self) -> bool {
+        self.processes.iter().any(|process| {
+            matches!(
+                process.stop_signal,
+                Some(Signal::SIGTTIN) | Some(Signal::SIGTTOU)
+            )
+        })
+    }
+}
+
+impl JobProcess {
+    /// Updates the status of the process from the result of `waitpid`
+    fn update(&mut self, status: nix::Result<WaitStatus>) -> Result<()> {
+        self.stop_signal = match status {
+            Ok(WaitStatus::Stopped(_, signal)) => Some(signal),
+            Ok(WaitStatus::StillAlive) => self.stop_signal,
+            _ => None,
+        };
+
+        self.status = convert_status(status, self.status)?;
+        Ok(())
+    }
+}
+
+/// Keeps track of the jobs started by the shell
+// * A copy of the shell state (such as a subshell) runs in the same process, so it keeps the jobs
+// * and job control of the original, and can wait for them just the same
+#[derive(Debug, Default, Clone)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+    /// The process group of the shell, if it has control of the terminal
+    // * This is only set for an interactive shell, since otherwise there is no terminal to control
+    shell_pgid: Option<Pid>,
+}
+
+impl JobTable {
+    /// Takes control of the terminal if the shell is running in one, so that foreground jobs can be
+    /// given the terminal and receive the signals sent by keys such as Ctrl-C and Ctrl-Z
+    pub fn enable_job_control(&mut self) -> Result<()> {
+        if !io::stdin().is_terminal() {
+            return Ok(());
+        }
+
+        // The shell has to survive the signals sent from the terminal, but its children should not
+        // inherit this, so the signals are caught and discarded rather than ignored
+        // * Signal handlers (unlike ignored signals) are reset when a child process is started
+        let discarded = Arc::new(AtomicBool::new(false));
+        for signal in TERMINAL_SIGNALS {
+            signal_hook::flag::register(signal as i32, Arc::clone(&discarded))
+                .replace_err(|| state_err!(CouldNotControlTerminal))?;
+        }
+
+        // * This fails if the shell is already the leader of its session, in which case it is
+        // * already the leader of its process group as well
+        let pgid = getpid();
+        let _ = setpgid(pgid, pgid);
+        self.shell_pgid = Some(pgid);
+        self.give_terminal(pgid)
+    }
+
+    /// Gives up control of the terminal in a forked copy of the shell, which runs as a background
+    /// job and so should be interrupted and stopped like any other process in it
+    pub fn leave_job_control(&mut self) {
+        if self.shell_pgid.take().is_none() {
+            return;
+        }
+
+        for terminal_signal in TERMINAL_SIGNALS {
+            // * Restoring the default action cannot interfere with a handler which is running,
+            // * since the copy of the shell only has the one thread which forked it
+            let _ = unsafe { signal(terminal_signal, SigHandler::SigDfl) };
+        }
+    }
+
+    /// Whether the shell has control of the terminal, meaning jobs should get their own process group
+    pub fn has_job_control(&self) -> bool {
+        self.shell_pgid.is_some()
+    }
+
+    /// Tells the user about a change in the state of a job, such as it being started in the
+    /// background, stopped, or finished
+    // * Like bash, the shell only shows these when it has control of the terminal, so scripts do
+    // * not print a line for every job they run
+    pub fn announce(&self, notice: impl Display) {
+        if self.has_job_control() {
+            eprintln!("{}", notice);
+        }
+    }
+
+    /// Adds a job to the table, returning its ID
+    // * A job which was taken out of the table (such as by 'fg') keeps the ID it had before
+    pub fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+
+        let id = job.id;
+        let index = self.jobs.partition_point(|other| other.id < id);
+        self.jobs.insert(index, job);
+        id
+    }
+
+    /// Gets the job with the given ID
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Removes the job with the given ID from the table
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    /// Gets all of the jobs in the table, from oldest to newest
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Finds the ID of a job from a job specifier such as `%1`, `1`, or `%+`,
+    /// or the most recent job if no specifier is given
+    pub fn resolve(&self, specifier: Option<&str>) -> Result<usize> {
+        match specifier.map(|s| s.strip_prefix('%').unwrap_or(s)) {
+            None | Some("+") | Some("%") => self
+                .jobs
+                .last()
+                .map(|job| job.id)
+                .replace_err(|| state_err!(NoCurrentJob)),
+            Some(id) => id
+                .parse::<usize>()
+                .ok()
+                .filter(|id| self.jobs.iter().any(|job| job.id == *id))
+                .replace_err(|| state_err!(UnknownJob: id)),
+        }
+    }
+
+    /// Checks every job for changes in state, without blocking
+    pub fn update(&mut self) -> Result<()> {
+        for job in &mut self.jobs {
+            job.poll()?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes a process group the foreground process group of the terminal, if the shell controls it
+    pub fn give_terminal(&self, pgid: Pid) -> Result<()> {
+        if self.shell_pgid.is_none() {
+            return Ok(());
+        }
+
+        // * A process outside of the foreground process group is stopped by SIGTTOU when it tries
+        // * to change the foreground process group, unless the signal is blocked
+        let mut signals = SigSet::empty();
+        signals.add(Signal::SIGTTOU);
+        signals
+            .thread_block()
+            .replace_err(|| state_err!(CouldNotControlTerminal))?;
+        let result = tcsetpgrp(io::stdin().as_raw_fd(), pgid);
+        signals
+            .thread_unblock()
+            .replace_err(|| state_err!(CouldNotControlTerminal))?;
+
+        result.replace_err(|| state_err!(CouldNotControlTerminal))
+    }
+
+    /// Gives the terminal to a job and waits for it to finish or be stopped, then takes the terminal
+    /// back, returning the status of each process in the job
+    /// A job which is stopped is added to the table so that it can be resumed later
+    pub fn wait_in_foreground(&mut self, mut job: Job) -> Result<Vec<JobStatus>> {
+        self.give_terminal(job.pgid)?;
+        let mut status = job.wait();
+        // * A process can try to use the terminal before it has been handed over,
+        // * in which case it is stopped and has to be continued
+        while self.has_job_control()
+            && matches!(status, Ok(JobStatus::Stopped))
+            && job.stopped_by_terminal()
+        {
+            status = job.resume().and_then(|_| job.wait());
+        }
+
+        if let Some(pgid) = self.shell_pgid {
+            self.give_terminal(pgid)?;
+        }
+
+        let status = status?;
+        let statuses = job.process_statuses();
+        if status == JobStatus::Stopped {
+            let command = job.command.clone();
+            let id = self.add(job);
+            // * The line is started on a new line, since the terminal is usually left after '^Z'
+            self.announce(format!("\n[{}] {:<8} {}", id, status, command));
+        }
+
+        Ok(statuses)
+    }
+
+    /// Checks every job for changes in state and removes the jobs which have finished
+    pub fn take_finished(&mut self) -> Result<Vec<Job>> {
+        self.update()?;
+        let (finished, remaining) = std::mem::take(&mut self.jobs)
+            .into_iter()
+            .partition(|job| job.status().is_finished());
+        self.jobs = remaining;
+
+        Ok(finished)
+    }
+}
+
+/// Converts the result of `waitpid` into the new status of a process
+fn convert_status(status: nix::Result<WaitStatus>, previous: JobStatus) -> Result<JobStatus> {
+    Ok(match status {
+        Ok(WaitStatus::Exited(_, code)) => JobStatus::Done(code),
+        // * 128 + the signal number is the conventional exit code for a process killed by a signal
+        Ok(WaitStatus::Signaled(_, signal, _)) => JobStatus::Done(128 + signal as i32),
+        Ok(WaitStatus::Stopped(..)) => JobStatus::Stopped,
+        Ok(WaitStatus::Continued(_)) => JobStatus::Running,
+        Ok(_) => previous,
+        // * The process has already been reaped somewhere else, so there is nothing left to wait for,
+        // * but its exit code is lost along with it
+        Err(Errno::ECHILD) => JobStatus::Unknown,
+        Err(_) => return Err(executable_err!(CouldNotWait)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_wait_statuses() {
+        let pid = Pid::from_raw(1);
+        let exited = convert_status(Ok(WaitStatus::Exited(pid, 3)), JobStatus::Running);
+        assert_eq!(exited.unwrap(), JobStatus::Done(3));
+
+        let killed = Ok(WaitStatus::Signaled(pid, Signal::SIGKILL, false));
+        assert_eq!(
+            convert_status(killed, JobStatus::Running).unwrap(),
+            JobStatus::Done(137)
+        );
+
+        let alive = convert_status(Ok(WaitStatus::StillAlive), JobStatus::Stopped);
+        assert_eq!(alive.unwrap(), JobStatus::Stopped);
+    }
+
+    #[test]
+    fn does_not_assume_success_for_processes_reaped_elsewhere() {
+        let status = convert_status(Err(Errno::ECHILD), JobStatus::Running).unwrap();
+        assert_eq!(status, JobStatus::Unknown);
+        assert!(status.is_finished());
+        assert!(status.into_result().is_err());
+        assert!(convert_status(Err(Errno::EINVAL), JobStatus::Running).is_err());
+    }
+
+    #[test]
+    fn keeps_jobs_when_cloned() {
+        let mut table = JobTable::default();
+        let first = table.add(Job::new(vec![100, 101], "sleep 1 | cat"));
+        let second = table.add(Job::new(vec![200], "sleep 2"));
+
+        let copy = table.clone();
+        let ids: Vec<usize> = copy.jobs().iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec![first, second]);
+        assert_eq!(copy.jobs()[0].pgid, Pid::from_raw(100));
+        assert_eq!(copy.resolve(None).unwrap(), second);
+    }
+
+    /// Starts a process in a process group of its own, as a background job would be
+    // * The job waits for the process itself, just as the shell does
+    fn start_job(command: &str) -> Job {
+        use std::os::unix::process::CommandExt;
+        let pid = std::process::Command::new("sh")
+            .args(["-c", command])
+            .process_group(0)
+            .spawn()
+            .map(|child| child.id())
+            .unwrap();
+
+        Job::new(vec![pid], command)
+    }
+
+    #[test]
+    fn stops_resumes_and_ends_jobs() {
+        let mut job = start_job("sleep 10");
+        job.signal(Signal::SIGTSTP).unwrap();
+        assert_eq!(job.wait().unwrap(), JobStatus::Stopped);
+        assert!(!job.stopped_by_terminal());
+        assert!(job.status().into_result().is_ok());
+
+        job.resume().unwrap();
+        assert_eq!(job.status(), JobStatus::Running);
+        job.signal(Signal::SIGTERM).unwrap();
+        assert_eq!(job.wait().unwrap(), JobStatus::Done(143));
+        assert_eq!(job.process_statuses(), [JobStatus::Done(143)]);
+    }
+
+    #[test]
+    fn keeps_stopped_foreground_jobs_in_the_table() {
+        let mut table = JobTable::default();
+        assert!(!table.has_job_control());
+        let first = table.add(Job::new(vec![100], "sleep 1"));
+
+        let job = start_job("kill -STOP $$; exit 4");
+        let statuses = table.wait_in_foreground(job).unwrap();
+        assert_eq!(statuses, [JobStatus::Stopped]);
+        let id = table.resolve(None).unwrap();
+        assert_eq!(id, first + 1);
+
+        // * A job taken out of the table keeps its ID when it is added again
+        let mut job = table.remove(id).unwrap();
+        job.resume().unwrap();
+        assert_eq!(table.wait_in_foreground(job).unwrap(), [JobStatus::Done(4)]);
+        assert_eq!(table.jobs().len(), 1);
+
+        let mut job = start_job("kill -STOP $$");
+        job.id = 7;
+        table.wait_in_foreground(job).unwrap();
+        let ids: Vec<usize> = table.jobs().iter().map(|job| job.id).collect();
+        assert_eq!(ids, [first, 7]);
+
+        let mut job = table.remove(7).unwrap();
+        job.resume().unwrap();
+        assert_eq!(job.wait().unwrap(), JobStatus::Done(0));
+    }
+}
@@ -1,8 +1,17 @@
+mod aliases;
 mod config;
 mod environment;
+mod jobs;
 mod path;
+mod paths;
 mod shell;
 
-pub use environment::{EnvVariable, EnvVariables, Environment};
+pub use aliases::AliasTable;
+pub use config::{SettingSource, SETTINGS};
+pub use environment::{EnvVariable, Environment};
+pub use jobs::{Job, JobStatus};
 pub use path::Path;
+#[cfg(test)]
+pub use paths::TestDirectory;
+pub use paths::{PathOverrides, ShellPaths};
 pub use shell::ShellState;
@@ -28,17 +28,11 @@ impl From<Path> for PathBuf {
 
 impl Path {
     /// Attempts to construct a new `Path` from a string by resolving it to an absolute path
-    pub fn try_from_str(path: &str, home_directory: Option<&StdPath>) -> Result<Self> {
-        // The home directory shorthand must be expanded before resolving the path,
-        // because PathBuf is not user-aware and only uses absolute and relative paths
-        let expanded_path = match home_directory {
-            Some(home_directory) => expand_home(path, home_directory)?,
-            None => PathBuf::from(path),
-        };
-
+    // * Shorthands such as '~' are expanded by the evaluator before the path gets here
+    pub fn try_from_str(path: &str) -> Result<Self> {
         // Canonicalizing a path will resolve any relative or absolute paths
-        let absolute_path = canonicalize(&expanded_path)
-            .replace_err(|| file_err!(CouldNotCanonicalize: expanded_path))?;
+        let absolute_path =
+            canonicalize(path).replace_err(|| file_err!(CouldNotCanonicalize: path))?;
 
         // If the file system can canonicalize the path, it should exist,
         // but this is added for extra precaution
@@ -50,12 +44,12 @@ impl Path {
     }
 
     /// Attempts to construct a new `Path` from a `std::path::Path` by resolving it to an absolute path
-    pub fn try_from_path(path: &StdPath, home_directory: Option<&StdPath>) -> Result<Self> {
+    pub fn try_from_path(path: &StdPath) -> Result<Self> {
         let path_string = path
             .to_str()
             .replace_err(|| file_err!(FailedToConvertPathToString: path))?;
 
-        Self::try_from_str(path_string, home_directory)
+        Self::try_from_str(path_string)
     }
 
     /// Attempts to locate an executable file in the PATH
@@ -121,19 +115,3 @@ impl Path {
         truncated_directories.join("/")
     }
 }
-
-/// Expands the home directory shorthand in a path string
-fn expand_home(path: &str, home_directory: &StdPath) -> Result<PathBuf> {
-    if path.starts_with('~') {
-        Ok(PathBuf::from(
-            path.replace(
-                '~',
-                home_directory
-                    .to_str()
-                    .replace_err(|| file_err!(FailedToConvertPathToString: home_directory))?,
-            ),
-        ))
-    } else {
-        Ok(PathBuf::from(path))
-    }
-}
@@ -0,0 +1,263 @@
+use std::env;
+use std::path::{Path as StdPath, PathBuf};
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(test)]
+use std::sync::Arc;
+
+use crate::errors::{Handle, Result};
+
+/// The configuration file which is created on the first run, with every setting at its default
+const DEFAULT_CONFIG: &str = include_str!("../../config/config.toml");
+
+/// Represents the locations of the files which the shell reads and writes, following the XDG Base
+/// Directory Specification
+#[derive(Clone, Debug)]
+pub struct ShellPaths {
+    /// The configuration file, which may be outside of the configuration directory if overridden
+    pub config_file: PathBuf,
+    /// Whether the configuration file was given with '--config' or 'RUSH_CONFIG', in which case
+    /// the shell never creates it
+    pub config_file_given: bool,
+    /// The directory for files written by the user, such as the config ('$XDG_CONFIG_HOME/rush')
+    pub config_dir: PathBuf,
+    /// The directory for files used by the shell, such as plugins ('$XDG_DATA_HOME/rush')
+    pub data_dir: PathBuf,
+    /// The directory for files which are kept between sessions, such as history
+    /// ('$XDG_STATE_HOME/rush')
+    pub state_dir: PathBuf,
+    /// The directory which holds every file of a test, which is removed once the last copy of the
+    /// paths is dropped
+    #[cfg(test)]
+    test_directory: Option<Arc<TestDirectory>>,
+}
+
+/// Locations given on the command line, which take precedence over any from the environment
+#[derive(Default)]
+pub struct PathOverrides {
+    pub config_file: Option<PathBuf>,
+    pub config_dir: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+}
+
+impl ShellPaths {
+    /// Resolves the locations of the shell's files from the command line, then the environment,
+    /// and then the defaults of the XDG Base Directory Specification
+    // * The configuration file can also be set with 'RUSH_CONFIG', without moving anything else
+    pub fn new(overrides: PathOverrides) -> Self {
+        let config_dir = overrides.config_dir.unwrap_or_else(|| {
+            xdg_directory(non_empty_var("XDG_CONFIG_HOME"), ".config").join("rush")
+        });
+        let data_dir = overrides.data_dir.unwrap_or_else(|| {
+            xdg_directory(non_empty_var("XDG_DATA_HOME"), ".local/share").join("rush")
+        });
+        let state_dir = overrides.state_dir.unwrap_or_else(|| {
+            xdg_directory(non_empty_var("XDG_STATE_HOME"), ".local/state").join("rush")
+        });
+        let given_config_file = overrides
+            .config_file
+            .or_else(|| non_empty_var("RUSH_CONFIG").map(PathBuf::from));
+        let config_file_given = given_config_file.is_some();
+        let config_file = given_config_file.unwrap_or_else(|| config_dir.join("config.toml"));
+
+        Self {
+            config_file,
+            config_file_given,
+            config_dir,
+            data_dir,
+            state_dir,
+            #[cfg(test)]
+            test_directory: None,
+        }
+    }
+
+    /// The script which is run when an interactive shell starts
+    pub fn rc_file(&self) -> PathBuf {
+        self.config_dir.join("rc.rush")
+    }
+
+    /// The script which is run when a login shell starts, before the rc script
+    pub fn profile_file(&self) -> PathBuf {
+        self.config_dir.join("profile.rush")
+    }
+
+    /// The script which is run when a login shell exits
+    pub fn logout_file(&self) -> PathBuf {
+        self.config_dir.join("logout.rush")
+    }
+
+    /// The file which the command history is saved to
+    pub fn history_file(&self) -> PathBuf {
+        self.state_dir.join("history.rush")
+    }
+
+    /// The directory which plugins are loaded from by default
+    pub fn plugin_dir(&self) -> PathBuf {
+        self.data_dir.join("plugins")
+    }
+
+    /// Creates the shell's directories, and a default configuration file, if they do not exist yet
+    /// A configuration file which was given explicitly is never created, since the user chose
+    /// where it should be
+    // * This is only done for an interactive shell, so that scripts and '-c' leave no files behind
+    pub fn create_defaults(&self) -> Result<()> {
+        for directory in [&self.config_dir, &self.data_dir, &self.state_dir] {
+            fs_err::create_dir_all(directory)
+                .replace_err(|| file_err!(CouldNotCreateDirectory: directory))?;
+        }
+
+        if !self.config_file_given && !self.config_file.exists() {
+            fs_err::write(&self.config_file, DEFAULT_CONFIG)
+                .replace_err(|| file_err!(CouldNotCreateFile: self.config_file))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl ShellPaths {
+    /// Creates the locations of the shell's files for a test, inside an empty directory of its own
+    pub fn for_tests() -> Self {
+        let directory = TestDirectory::new();
+        let mut paths = Self::new(PathOverrides {
+            config_dir: Some(directory.join("config")),
+            data_dir: Some(directory.join("data")),
+            state_dir: Some(directory.join("state")),
+            ..Default::default()
+        });
+        paths.test_directory = Some(Arc::new(directory));
+        paths
+    }
+}
+
+/// An empty directory for the files of a single test, which is removed along with its contents
+/// once it is dropped
+#[cfg(test)]
+#[derive(Debug)]
+pub struct TestDirectory {
+    path: PathBuf,
+}
+
+#[cfg(test)]
+impl TestDirectory {
+    pub fn new() -> Self {
+        // * Tests run in parallel, so each directory is numbered to keep them apart
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let number = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("rush-test-{}-{}", std::process::id(), number));
+        let _ = fs_err::remove_dir_all(&path);
+        fs_err::create_dir_all(&path).unwrap();
+
+        Self { path }
+    }
+
+    pub fn path(&self) -> &StdPath {
+        &self.path
+    }
+
+    /// Gets the path of a file inside the directory
+    pub fn join(&self, path: impl AsRef<StdPath>) -> PathBuf {
+        self.path.join(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = fs_err::remove_dir_all(&self.path);
+    }
+}
+
+/// Gets a base directory from the value of an XDG variable, or from its default location in the
+/// home directory
+// * The specification requires relative paths in these variables to be ignored
+fn xdg_directory(value: Option<String>, default: &str) -> PathBuf {
+    match value.map(PathBuf::from) {
+        Some(directory) if directory.is_absolute() => directory,
+        _ => home_directory().join(default),
+    }
+}
+
+/// Gets the home directory of the user, or the root directory if it is not known
+fn home_directory() -> PathBuf {
+    non_empty_var("HOME").map_or_else(|| StdPath::new("/").to_path_buf(), PathBuf::from)
+}
+
+/// Gets the value of an environment variable, if it is set and not empty
+fn non_empty_var(variable: &str) -> Option<String> {
+    env::var(variable).ok().filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::config::Configuration;
+
+    #[test]
+    fn derives_files_from_the_directories() {
+        let paths = ShellPaths::new(PathOverrides {
+            config_dir: Some(PathBuf::from("/config")),
+            data_dir: Some(PathBuf::from("/data")),
+            state_dir: Some(PathBuf::from("/state")),
+            ..Default::default()
+        });
+
+        assert_eq!(paths.config_file, PathBuf::from("/config/config.toml"));
+        assert_eq!(paths.rc_file(), PathBuf::from("/config/rc.rush"));
+        assert_eq!(paths.profile_file(), PathBuf::from("/config/profile.rush"));
+        assert_eq!(paths.logout_file(), PathBuf::from("/config/logout.rush"));
+        assert_eq!(paths.plugin_dir(), PathBuf::from("/data/plugins"));
+        assert_eq!(paths.history_file(), PathBuf::from("/state/history.rush"));
+
+        let overridden = ShellPaths::new(PathOverrides {
+            config_file: Some(PathBuf::from("/given.toml")),
+            ..Default::default()
+        });
+        assert_eq!(overridden.config_file, PathBuf::from("/given.toml"));
+    }
+
+    #[test]
+    fn ignores_relative_xdg_directories() {
+        let default = home_directory().join(".config");
+        let absolute = xdg_directory(Some(String::from("/xdg/config")), ".config");
+        assert_eq!(absolute, PathBuf::from("/xdg/config"));
+        let relative = xdg_directory(Some(String::from("xdg/config")), ".config");
+        assert_eq!(relative, default);
+        assert_eq!(xdg_directory(None, ".config"), default);
+    }
+
+    #[test]
+    fn creates_a_default_config_file_once() {
+        let paths = ShellPaths::for_tests();
+        paths.create_defaults().unwrap();
+
+        for directory in [&paths.config_dir, &paths.data_dir, &paths.state_dir] {
+            assert!(directory.is_dir());
+        }
+
+        let (_, warnings) = Configuration::from_file(&paths.config_file).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        fs_err::write(&paths.config_file, "[errors]\nshow = false\n").unwrap();
+        paths.create_defaults().unwrap();
+        let contents = fs_err::read_to_string(&paths.config_file).unwrap();
+        assert_eq!(contents, "[errors]\nshow = false\n");
+    }
+
+    #[test]
+    fn never_creates_config_files_which_were_given() {
+        let directory = TestDirectory::new();
+        let paths = ShellPaths::new(PathOverrides {
+            config_file: Some(directory.join("given.toml")),
+            config_dir: Some(directory.join("config")),
+            data_dir: Some(directory.join("data")),
+            state_dir: Some(directory.join("state")),
+        });
+
+        paths.create_defaults().unwrap();
+        assert!(paths.config_dir.is_dir());
+        assert!(!paths.config_file.exists());
+    }
+}
@@ -1,33 +1,182 @@
+use std::collections::HashMap;
+use std::io::Write;
 use std::sync::{Arc, RwLock};
 
 use crossterm::style::Stylize;
 
+use super::aliases::AliasTable;
 use super::config::Configuration;
 use super::environment::Environment;
+use super::jobs::JobTable;
+use super::paths::ShellPaths;
 use super::Path;
-use crate::errors::Result;
+use crate::errors::{Result, RushError};
+use crate::eval::{Dispatcher, Function, ScriptEngine};
+use crate::exec::{strip_styles, Output};
 
 /// Represents the shell state and provides methods for interacting with it
+#[derive(Clone)]
 pub struct ShellState {
     pub environment: Environment,
     pub config: Configuration,
-    pub last_command_succeeded: bool,
+    pub jobs: JobTable,
+    /// The locations of the configuration and other files used by the shell
+    pub paths: ShellPaths,
+    /// The aliases defined by the user, which are expanded when a line is parsed
+    pub aliases: AliasTable,
+    /// The positional arguments, such as '$1', where the first is the name of the shell or script
+    pub arguments: Vec<String>,
+    /// The builtins which commands are resolved against, shared so that builtins such as
+    /// 'source-file' can run commands with the same dispatcher as the rest of the shell
+    pub dispatcher: Arc<Dispatcher>,
+    /// The engine which runs Rhai scripts
+    pub scripts: ScriptEngine,
+    /// The functions which have been defined, by name
+    pub functions: HashMap<String, Function>,
+    /// The exit status of the last command, which is available as '$?'
+    pub last_exit_code: i32,
+    /// The name of the most recent error, which 'explain-error' explains by default
+    pub last_error: Option<String>,
+    /// The contents of the config file as of when it was last read, to detect changes to it
+    config_contents: Option<String>,
     pub should_exit: bool,
+    /// The status which the shell exits with once 'exit' has been run, which is '$?' by default
+    pub exit_code: i32,
 }
 
 impl ShellState {
-    pub fn new() -> Result<Arc<RwLock<Self>>> {
-        let config =
-            Configuration::from_file("./config/config.rush").unwrap_or(Configuration::default());
+    pub fn new(paths: ShellPaths) -> Result<Arc<RwLock<Self>>> {
+        // * A broken config file is reported rather than ignored, and is left alone so it can be fixed
+        // * One which has not been created yet leaves every setting at its default, unless it was
+        // * given explicitly, in which case it is expected to exist
+        let config_contents = fs_err::read_to_string(&paths.config_file).ok();
+        let (mut config, warnings) = match paths.config_file_given || paths.config_file.exists() {
+            true => Configuration::from_file(&paths.config_file).unwrap_or_else(|error| {
+                eprintln!("{}", error);
+                (Configuration::default(), Vec::new())
+            }),
+            false => (Configuration::default(), Vec::new()),
+        };
+        for warning in warnings {
+            eprintln!("{} {}", "Warning:".yellow().bold(), warning);
+        }
+
+        if paths.plugin_dir().is_dir() {
+            config.plugin_paths.push(paths.plugin_dir());
+        }
+
+        let aliases = AliasTable::new(&config.aliases, &paths.config_file);
+        let mut environment = Environment::new()?;
+        for (name, value) in &config.environment {
+            let result = environment
+                .set_variable(name, value)
+                .and_then(|_| environment.export_variable(name));
+            if let Err(error) = result {
+                eprintln!("{} {}", "Warning:".yellow().bold(), error);
+            }
+        }
 
         Ok(Arc::new(RwLock::new(Self {
-            environment: Environment::new()?,
+            environment,
             config,
-            last_command_succeeded: true,
+            jobs: JobTable::default(),
+            paths,
+            aliases,
+            arguments: vec![String::from("rush")],
+            dispatcher: Arc::new(Dispatcher::default()),
+            scripts: ScriptEngine::default(),
+            functions: HashMap::new(),
+            last_exit_code: 0,
+            last_error: None,
+            config_contents,
             should_exit: false,
+            exit_code: 0,
         })))
     }
 
+    /// Reads the config file again if it has changed since it was last read, so that changes to it
+    /// take effect without restarting the shell
+    // * The file is not watched, but polled before each prompt, so an edit takes effect at the next
+    // * prompt rather than straight away
+    // * The contents are compared rather than the modification time, which may not change for an
+    // * edit made within the same second as the last read
+    // * Settings changed in this session are kept, and variables are not set again, since they may
+    // * have been changed since the shell started
+    // * An edit which makes the file invalid TOML is reported, and the settings in use are kept until
+    // * it is fixed
+    pub fn reload_config(&mut self) {
+        let contents = fs_err::read_to_string(&self.paths.config_file).ok();
+        if contents == self.config_contents {
+            return;
+        }
+
+        self.config_contents = contents;
+        let (mut config, warnings) = match Configuration::from_file(&self.paths.config_file) {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!(
+                    "{}\n{} The config file was not reloaded, so the previous settings are kept",
+                    error,
+                    "Warning:".yellow().bold()
+                );
+                return;
+            }
+        };
+
+        // * As when the shell starts, settings which are unknown or invalid are skipped, and the
+        // * rest of the file is still applied
+        for warning in warnings {
+            eprintln!("{} {}", "Warning:".yellow().bold(), warning);
+        }
+
+        if self.paths.plugin_dir().is_dir() {
+            config.plugin_paths.push(self.paths.plugin_dir());
+        }
+
+        self.config.reload(config);
+        self.aliases.reload(&self.config.aliases);
+    }
+
+    /// Records the result of a command as the status of the last command
+    pub fn set_status(&mut self, result: &Result<()>) {
+        self.last_exit_code = result.as_ref().map_or_else(RushError::exit_code, |_| 0);
+        if let Err(error) = result {
+            self.last_error = Some(error.name());
+        }
+    }
+
+    /// Prints an error for a command which failed, unless errors are hidden or it has already been
+    /// reported
+    pub fn report_error(&self, error: &RushError) {
+        self.report_error_to(error, &mut Output::Stderr);
+    }
+
+    /// Writes an error for a command which failed to an output, such as the command's redirected
+    /// error stream, unless errors are hidden or it has already been reported
+    /// In verbose mode, the error is followed by its cause and an explanation of it
+    pub fn report_error_to(&self, error: &RushError, output: &mut Output) {
+        if !self.config.show_errors || error.is_reported() {
+            return;
+        }
+
+        let mut report = format!("{}\n", error);
+        if self.config.verbose_errors {
+            for source in error.sources() {
+                report.push_str(&format!("{} {}\n", "Caused by:".bold(), source));
+            }
+
+            report.push_str(&format!(
+                "{} ({})\n{}\n",
+                "Explanation".bold(),
+                error.name(),
+                error.explanation()
+            ));
+        }
+
+        // * Nothing more can be done if the error itself cannot be written
+        let _ = output.write_all(strip_styles(report, output).as_bytes());
+    }
+
     /// Generates the prompt string used by the `LineEditor`
     pub fn generate_prompt(&self) -> String {
         let user = self.environment.USER.clone();
@@ -40,9 +189,9 @@ impl ShellState {
         };
 
         // ? What is the actual name for this?
-        let prompt_tick = match self.last_command_succeeded {
-            true => "❯".green(),
-            false => "❯".red(),
+        let prompt_tick = match self.last_exit_code {
+            0 => "❯".green(),
+            _ => "❯".red(),
         }
         .bold();
 
@@ -61,3 +210,149 @@ impl ShellState {
         self.environment.CWD()
     }
 }
+
+#[cfg(test)]
+impl ShellState {
+    /// Creates a shell state for a test, with the default settings and its files in an empty
+    /// directory of its own
+    // * Errors are hidden so that commands which are meant to fail do not write over the output of
+    // * the tests, and tests of how errors are reported turn them back on
+    pub fn for_tests() -> Self {
+        let paths = ShellPaths::for_tests();
+        let mut config = Configuration::default();
+        config.show_errors = false;
+
+        Self {
+            environment: Environment::for_tests(),
+            config,
+            jobs: JobTable::default(),
+            aliases: AliasTable::new(&Default::default(), &paths.config_file),
+            paths,
+            arguments: vec![String::from("rush")],
+            dispatcher: Arc::new(Dispatcher::default()),
+            scripts: ScriptEngine::default(),
+            functions: HashMap::new(),
+            last_exit_code: 0,
+            last_error: None,
+            config_contents: None,
+            should_exit: false,
+            exit_code: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path as StdPath;
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::state::SettingSource;
+
+    /// Reports an error to a file in the way the shell state is configured to, returning what
+    /// was written
+    fn report(shell: &ShellState, error: &RushError) -> String {
+        fs_err::create_dir_all(&shell.paths.data_dir).unwrap();
+        let file = shell.paths.data_dir.join("errors.txt");
+        let mut output = Output::File(fs_err::File::create(&file).unwrap().into());
+        shell.report_error_to(error, &mut output);
+        fs_err::read_to_string(&file).unwrap()
+    }
+
+    #[test]
+    fn reports_errors_unless_they_are_hidden() {
+        let mut shell = ShellState::for_tests();
+        let mut error = state_err!(NoPreviousDirectory);
+        assert_eq!(report(&shell, &error), "");
+
+        shell.config.show_errors = true;
+        assert_eq!(report(&shell, &error), format!("{}\n", error));
+        error.mark_reported();
+        assert_eq!(report(&shell, &error), "");
+    }
+
+    #[test]
+    fn explains_errors_in_verbose_mode() {
+        let mut shell = ShellState::for_tests();
+        shell.config.show_errors = true;
+        shell.config.verbose_errors = true;
+        let cause = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+        let error = file_err!(CouldNotReadFile: StdPath::new("missing.txt")).set_source(&cause);
+
+        let report = report(&shell, &error);
+        let expected = format!(
+            "{}\nCaused by: no such file\nExplanation (CouldNotReadFile)\n{}\n",
+            error,
+            error.explanation()
+        );
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn records_the_status_and_name_of_errors() {
+        let mut shell = ShellState::for_tests();
+        shell.set_status(&Err(dispatch_err!(UnexpectedToken: ")")));
+        assert_eq!(shell.last_exit_code, 2);
+        assert_eq!(shell.last_error.as_deref(), Some("UnexpectedToken"));
+
+        // * The last error is kept for 'explain-error' after a command succeeds
+        shell.set_status(&Ok(()));
+        assert_eq!(shell.last_exit_code, 0);
+        assert_eq!(shell.last_error.as_deref(), Some("UnexpectedToken"));
+    }
+
+    /// Writes the config file of a shell state, always marking it as modified at the same time,
+    /// as with edits made within the same second
+    fn write_config(shell: &ShellState, contents: &str) {
+        fs_err::create_dir_all(&shell.paths.config_dir).unwrap();
+        fs_err::write(&shell.paths.config_file, contents).unwrap();
+        let modified = SystemTime::UNIX_EPOCH;
+        let file = std::fs::File::options()
+            .write(true)
+            .open(&shell.paths.config_file);
+        file.unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn reloads_the_config_file_once_it_changes() {
+        let mut shell = ShellState::for_tests();
+        assert_eq!(shell.config_contents, None);
+        shell.reload_config();
+        assert!(!shell.config.show_errors);
+
+        // * Unknown settings are skipped, and the rest of the file is still applied
+        let contents = "[errors]\nshow = true\ncolour = true\n[aliases]\nll = \"ls -l\"\n";
+        write_config(&shell, contents);
+        shell.reload_config();
+        assert!(shell.config.show_errors);
+        assert_eq!(shell.aliases.get("ll"), Some("ls -l"));
+
+        // * The file is only read again once its contents have changed, even if it was modified
+        // * within the same second as it was last read
+        shell.config.show_errors = false;
+        shell.reload_config();
+        assert!(!shell.config.show_errors);
+        write_config(&shell, "[errors]\nshow = true\n");
+        shell.reload_config();
+        assert!(shell.config.show_errors);
+        assert_eq!(shell.aliases.get("ll"), None);
+    }
+
+    #[test]
+    fn keeps_the_previous_settings_if_the_config_file_is_invalid() {
+        let mut shell = ShellState::for_tests();
+        write_config(&shell, "[errors]\nverbose = true\n");
+        shell.reload_config();
+        shell.config.show_errors = false;
+        shell.config.mark("errors.show", SettingSource::Session);
+
+        write_config(&shell, "[errors]\nverbose =\n");
+        shell.reload_config();
+        assert!(shell.config.verbose_errors);
+
+        // * Settings changed in the session are kept when the file is fixed
+        write_config(&shell, "[errors]\nshow = true\nverbose = false\n");
+        shell.reload_config();
+        assert!(!shell.config.show_errors && !shell.config.verbose_errors);
+    }
+}
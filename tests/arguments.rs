@@ -0,0 +1,49 @@
+use std::env;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Runs the shell with the given arguments, with its files in an empty directory
+fn run(name: &str, arguments: &[&str]) -> Output {
+    let directory = env::temp_dir().join(format!("rush-test-{}-{}", std::process::id(), name));
+    let dir = |name: &str| -> PathBuf { directory.join(name) };
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rush"))
+        .arg("--config-dir")
+        .arg(dir("config"))
+        .arg("--data-dir")
+        .arg(dir("data"))
+        .arg("--state-dir")
+        .arg(dir("state"))
+        .args(arguments)
+        .output()
+        .unwrap();
+    let _ = fs_err::remove_dir_all(&directory);
+    output
+}
+
+#[test]
+fn uses_the_first_argument_after_commands_as_the_shell_name() {
+    let output = run("command", &["-c", "echo $0 $1 $#", "a", "b"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a b 1\n");
+
+    // * Without any arguments, '$0' is the name of the shell
+    let output = run("command-name", &["-c", "echo $0 $#"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "rush 0\n");
+}
+
+#[test]
+fn uses_the_script_as_the_shell_name() {
+    let directory = env::temp_dir().join(format!("rush-test-{}-script", std::process::id()));
+    fs_err::create_dir_all(&directory).unwrap();
+    let script = directory.join("script.rush");
+    fs_err::write(&script, "echo $0 $1 $2 $#\n").unwrap();
+
+    let output = run("script-files", &[&script.to_string_lossy(), "a", "b"]);
+    let _ = fs_err::remove_dir_all(&directory);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{} a b 2\n", script.display())
+    );
+}
@@ -0,0 +1,43 @@
+use std::env;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn returns_before_blocks_in_the_background_finish() {
+    let directory = env::temp_dir().join(format!("rush-test-{}-background", std::process::id()));
+    fs_err::create_dir_all(&directory).unwrap();
+    let marker = directory.join("finished");
+    let line = format!(
+        "{{ sleep 1; echo done > '{}'; }} & echo after",
+        marker.display()
+    );
+
+    // * The output is not captured, since the block in the background keeps it open until it ends
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_rush"))
+        .arg("--config-dir")
+        .arg(directory.join("config"))
+        .arg("--data-dir")
+        .arg(directory.join("data"))
+        .arg("--state-dir")
+        .arg(directory.join("state"))
+        .args(["-c", &line])
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(start.elapsed() < Duration::from_millis(900));
+    assert!(!marker.exists());
+
+    // * The block is still run in full once the shell has moved on
+    // * The file is created by the redirection before anything is written to it
+    let finished = || fs_err::read_to_string(&marker).is_ok_and(|contents| contents == "done\n");
+    while !finished() && start.elapsed() < Duration::from_secs(10) {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let finished = finished();
+    let _ = fs_err::remove_dir_all(&directory);
+    assert!(finished);
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs the shell with a script piped into its input, with its files in an empty directory
+/// Returns `None` if the shell did not exit within a few seconds
+fn run_piped(name: &str, script: &str) -> Option<Output> {
+    let directory = env::temp_dir().join(format!("rush-test-{}-{}", std::process::id(), name));
+    let dir = |name: &str| -> PathBuf { directory.join(name) };
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rush"))
+        .arg("--config-dir")
+        .arg(dir("config"))
+        .arg("--data-dir")
+        .arg(dir("data"))
+        .arg("--state-dir")
+        .arg(dir("state"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+
+    // * A shell which is stuck waiting for its own input is killed rather than hanging the tests
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() && start.elapsed() < Duration::from_secs(10) {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let finished = child.try_wait().unwrap().is_some();
+    if !finished {
+        child.kill().unwrap();
+    }
+
+    let output = child.wait_with_output().unwrap();
+    let _ = fs_err::remove_dir_all(&directory);
+    finished.then_some(output)
+}
+
+#[test]
+fn passes_the_rest_of_piped_scripts_to_builtins() {
+    let output = run_piped("builtins", "echo one\nread-file\ntwo\nthree\n").unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "one\ntwo\nthree\n");
+}
+
+#[test]
+fn leaves_lines_after_the_current_one_for_executables() {
+    let script = "echo one\nsh -c 'read line; echo \"read $line\"'\necho two\necho three\n";
+    let output = run_piped("executables", script).unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "one\nread echo two\nthree\n"
+    );
+}